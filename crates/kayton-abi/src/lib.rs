@@ -52,9 +52,12 @@ pub struct KayValueInfo {
 #[derive(Debug, Clone)]
 pub enum KayValueKind {
     Int(i64),
+    Float(f64),
     Bool(bool),
     String(Arc<str>),
     Bytes(Arc<[u8]>),
+    List(Arc<[KayRawHandle]>),
+    Map(Arc<[(KayRawHandle, KayRawHandle)]>),
     Unit,
     Capsule { tag: &'static str },
 }
@@ -74,9 +77,13 @@ pub struct KayCapsuleData {
 #[derive(Debug)]
 pub struct KayContextVTable {
     pub alloc_int: fn(KayContextId, i64) -> KayAbiResult<KayRawHandle>,
+    pub alloc_float: fn(KayContextId, f64) -> KayAbiResult<KayRawHandle>,
     pub alloc_bool: fn(KayContextId, bool) -> KayAbiResult<KayRawHandle>,
     pub alloc_string: fn(KayContextId, Arc<str>) -> KayAbiResult<KayRawHandle>,
     pub alloc_bytes: fn(KayContextId, Arc<[u8]>) -> KayAbiResult<KayRawHandle>,
+    pub alloc_list: fn(KayContextId, Vec<KayRawHandle>) -> KayAbiResult<KayRawHandle>,
+    pub alloc_map:
+        fn(KayContextId, Vec<(KayRawHandle, KayRawHandle)>) -> KayAbiResult<KayRawHandle>,
     pub alloc_unit: fn(KayContextId) -> KayAbiResult<KayRawHandle>,
     pub inc_ref: fn(KayContextId, KayRawHandle) -> KayAbiResult<()>,
     pub dec_ref: fn(KayContextId, KayRawHandle) -> KayAbiResult<()>,
@@ -65,6 +65,11 @@ impl KayCtx {
             .map(|raw| KayHandle::new(self.clone(), raw))
     }
 
+    pub fn alloc_float(&self, value: f64) -> KayResult<KayHandle> {
+        self.invoke(|ctx| (ctx.vtable.alloc_float)(ctx.id, value))
+            .map(|raw| KayHandle::new(self.clone(), raw))
+    }
+
     pub fn alloc_bool(&self, value: bool) -> KayResult<KayHandle> {
         self.invoke(|ctx| (ctx.vtable.alloc_bool)(ctx.id, value))
             .map(|raw| KayHandle::new(self.clone(), raw))
@@ -82,6 +87,21 @@ impl KayCtx {
             .map(|raw| KayHandle::new(self.clone(), raw))
     }
 
+    pub fn alloc_list(&self, items: &[KayHandle]) -> KayResult<KayHandle> {
+        let raw_items = items.iter().map(|h| h.raw).collect::<Vec<_>>();
+        self.invoke(|ctx| (ctx.vtable.alloc_list)(ctx.id, raw_items))
+            .map(|raw| KayHandle::new(self.clone(), raw))
+    }
+
+    pub fn alloc_map(&self, entries: &[(KayHandle, KayHandle)]) -> KayResult<KayHandle> {
+        let raw_entries = entries
+            .iter()
+            .map(|(key, value)| (key.raw, value.raw))
+            .collect::<Vec<_>>();
+        self.invoke(|ctx| (ctx.vtable.alloc_map)(ctx.id, raw_entries))
+            .map(|raw| KayHandle::new(self.clone(), raw))
+    }
+
     pub fn alloc_unit(&self) -> KayResult<KayHandle> {
         self.invoke(|ctx| (ctx.vtable.alloc_unit)(ctx.id))
             .map(|raw| KayHandle::new(self.clone(), raw))
@@ -436,6 +456,25 @@ impl FromKay for i64 {
     }
 }
 
+impl ToKay for f64 {
+    fn to_kay(self, ctx: &KayCtx) -> KayResult<KayHandle> {
+        ctx.alloc_float(self)
+    }
+}
+
+impl FromKay for f64 {
+    fn from_kay(_ctx: &KayCtx, handle: &KayHandle) -> KayResult<Self> {
+        match handle.describe()? {
+            KayValueKind::Float(value) => Ok(value),
+            other => Err(KayApiError::TypeMismatch {
+                expected: "float",
+                found: other,
+            }
+            .into()),
+        }
+    }
+}
+
 impl ToKay for bool {
     fn to_kay(self, ctx: &KayCtx) -> KayResult<KayHandle> {
         ctx.alloc_bool(self)
@@ -480,6 +519,32 @@ impl FromKay for String {
     }
 }
 
+impl<T: ToKay> ToKay for Vec<T> {
+    fn to_kay(self, ctx: &KayCtx) -> KayResult<KayHandle> {
+        let items = self
+            .into_iter()
+            .map(|item| item.to_kay(ctx))
+            .collect::<KayResult<Vec<_>>>()?;
+        ctx.alloc_list(&items)
+    }
+}
+
+impl<T: FromKay> FromKay for Vec<T> {
+    fn from_kay(ctx: &KayCtx, handle: &KayHandle) -> KayResult<Self> {
+        match handle.describe()? {
+            KayValueKind::List(items) => items
+                .iter()
+                .map(|raw| T::from_kay(ctx, &ctx.clone_raw(*raw)?))
+                .collect(),
+            other => Err(KayApiError::TypeMismatch {
+                expected: "list",
+                found: other,
+            }
+            .into()),
+        }
+    }
+}
+
 pub struct HandleScope<'ctx> {
     ctx: &'ctx KayCtx,
     handles: RefCell<Vec<KayRawHandle>>,
@@ -567,6 +632,29 @@ impl KayExtension {
     }
 }
 
+/// Symbol a plugin library exports to hand its extensions to the host.
+pub const PLUGIN_ENTRY: &str = "kayton_plugin_extensions";
+
+/// Signature of the [`PLUGIN_ENTRY`] function. Plugins pass Rust types across
+/// the library boundary, so they must be built with the same compiler and
+/// `kayton-api` as the host.
+pub type KayPluginEntry = fn() -> &'static [KayExtension];
+
+/// Exports `$extensions`, a `fn() -> &'static [KayExtension]`, as the entry
+/// point of a plugin library.
+#[macro_export]
+macro_rules! export_extensions {
+    ($extensions:path) => {
+        #[allow(unsafe_code)]
+        #[no_mangle]
+        pub fn kayton_plugin_extensions() -> &'static [$crate::KayExtension] {
+            $extensions()
+        }
+
+        const _: $crate::KayPluginEntry = kayton_plugin_extensions;
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,887 @@
+//! Textual form of a [`BytecodeModule`].
+//!
+//! [`disassemble`] prints a module and [`assemble`] parses the same text back:
+//!
+//! ```text
+//! struct Point x y
+//! enum Shape Circle/1 Empty/0
+//! global LIMIT int 10
+//! import print
+//! init <init>
+//!
+//! fn main params=0 locals=1 max_stack=2
+//!     load_const int 1
+//!     store_local 0
+//! L0:
+//!     load_local 0
+//!     jump_if_false L0
+//!     call_host_dynamic "print" 1
+//!     return
+//! end
+//! ```
+//!
+//! Constants are written inline and interned into the pool on assembly, so
+//! constant indices are not preserved. Debug info is printed as comments and
+//! is not read back. Functions and globals are referred to
+//! by name, or by `@index` when the name is not unique. `;` starts a comment.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::{
+    BytecodeModule, ConstId, Constant, EnumLayout, Function, Instruction, StructLayout,
+    VariantLayout,
+};
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("line {line}: {message}")]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+pub fn disassemble(module: &BytecodeModule) -> String {
+    let printer = Printer { module };
+    let mut out = String::new();
+    for constant in &module.constants {
+        match constant {
+            Constant::Struct(layout) => {
+                let _ = write!(out, "struct {}", name(&layout.name));
+                for field in &layout.fields {
+                    let _ = write!(out, " {}", name(field));
+                }
+                out.push('\n');
+            }
+            Constant::Enum(layout) => {
+                let _ = write!(out, "enum {}", name(&layout.name));
+                for variant in &layout.variants {
+                    let _ = write!(out, " {}/{}", name(&variant.name), variant.arity);
+                }
+                out.push('\n');
+            }
+            _ => {}
+        }
+    }
+    for global in &module.globals {
+        let _ = writeln!(
+            out,
+            "global {} {}",
+            name(&global.name),
+            printer.constant(global.value)
+        );
+    }
+    for import in &module.imports {
+        let _ = writeln!(out, "import {}", name(import));
+    }
+    if let Some(init) = module.initializer {
+        let _ = writeln!(out, "init {}", printer.function(init));
+    }
+    for (id, function) in module.functions.iter().enumerate() {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        printer.function_body(&mut out, id as u32, function);
+    }
+    out
+}
+
+struct Printer<'a> {
+    module: &'a BytecodeModule,
+}
+
+impl Printer<'_> {
+    fn function_body(&self, out: &mut String, id: u32, function: &Function) {
+        let _ = write!(
+            out,
+            "fn {} params={} locals={} max_stack={}",
+            name(&function.name),
+            function.params,
+            function.locals,
+            function.max_stack
+        );
+        if self.function(id).starts_with('@') {
+            let _ = write!(out, " ; @{id}");
+        }
+        out.push('\n');
+
+        let mut targets: Vec<usize> = function
+            .instructions
+            .iter()
+            .filter_map(|instr| match instr {
+                Instruction::Jump(target) | Instruction::JumpIfFalse(target) => Some(*target),
+                _ => None,
+            })
+            .filter(|target| *target <= function.instructions.len())
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+        let label = |target: usize| match targets.binary_search(&target) {
+            Ok(index) => format!("L{index}"),
+            Err(_) => target.to_string(),
+        };
+
+        if !function.debug.locals.is_empty() {
+            let locals: Vec<String> = function
+                .debug
+                .locals
+                .iter()
+                .map(|local| format!("{} {}", local.slot, local.name))
+                .collect();
+            let _ = writeln!(out, "    ; locals: {}", locals.join(", "));
+        }
+
+        let mut last_line = None;
+        for (idx, instr) in function.instructions.iter().enumerate() {
+            if targets.binary_search(&idx).is_ok() {
+                let _ = writeln!(out, "{}:", label(idx));
+            }
+            if let Some(span) = function.span_at(idx) {
+                if last_line != Some((span.file, span.line)) {
+                    last_line = Some((span.file, span.line));
+                    let file = self
+                        .module
+                        .source_files
+                        .get(span.file as usize)
+                        .map_or("?", |file| file.as_str());
+                    let _ = writeln!(out, "    ; {file}:{}", span.line);
+                }
+            }
+            let _ = writeln!(out, "    {}", self.instruction(instr, &label));
+        }
+        if targets.last() == Some(&function.instructions.len()) {
+            let _ = writeln!(out, "{}:", label(function.instructions.len()));
+        }
+        out.push_str("end\n");
+    }
+
+    fn instruction(&self, instr: &Instruction, label: &dyn Fn(usize) -> String) -> String {
+        let mnemonic = mnemonic(instr);
+        let operands = match *instr {
+            Instruction::LoadConst(id) => vec![self.constant(id)],
+            Instruction::LoadLocal(n)
+            | Instruction::StoreLocal(n)
+            | Instruction::CallValue(n)
+            | Instruction::LoadField(n)
+            | Instruction::StoreField(n)
+            | Instruction::LoadVariantField(n)
+            | Instruction::MakeList(n)
+            | Instruction::MakeTuple(n)
+            | Instruction::UnpackTuple(n) => vec![n.to_string()],
+            Instruction::LoadGlobal(id) | Instruction::StoreGlobal(id) => vec![self.global(id)],
+            Instruction::Jump(target) | Instruction::JumpIfFalse(target) => vec![label(target)],
+            Instruction::Call(func, n) | Instruction::MakeClosure(func, n) => {
+                vec![self.function(func), n.to_string()]
+            }
+            Instruction::CallHost(slot, n) => vec![slot.to_string(), n.to_string()],
+            Instruction::CallHostDynamic(id, n) | Instruction::CallMethod(id, n) => {
+                vec![self.constant(id), n.to_string()]
+            }
+            Instruction::LoadFieldNamed(id) | Instruction::StoreFieldNamed(id) => {
+                vec![self.constant(id)]
+            }
+            Instruction::MakeStruct(id) => match self.module.constants.get(id as usize) {
+                Some(Constant::Struct(layout)) => vec![name(&layout.name)],
+                _ => vec![format!("?{id}")],
+            },
+            Instruction::MakeVariant(id, tag) | Instruction::TestVariant(id, tag) => {
+                match self.module.constants.get(id as usize) {
+                    Some(Constant::Enum(layout)) => vec![
+                        name(&layout.name),
+                        layout
+                            .variants
+                            .get(tag as usize)
+                            .map_or_else(|| tag.to_string(), |variant| name(&variant.name)),
+                    ],
+                    _ => vec![format!("?{id}"), tag.to_string()],
+                }
+            }
+            _ => Vec::new(),
+        };
+        let mut line = mnemonic.to_string();
+        for operand in operands {
+            line.push(' ');
+            line.push_str(&operand);
+        }
+        line
+    }
+
+    fn constant(&self, id: ConstId) -> String {
+        match self.module.constants.get(id as usize) {
+            Some(Constant::Int(value)) => format!("int {value}"),
+            Some(Constant::Float(value)) => format!("float {value:?}"),
+            Some(Constant::Bool(value)) => value.to_string(),
+            Some(Constant::String(value)) => quote(value),
+            Some(Constant::Unit) => "unit".to_string(),
+            Some(Constant::Struct(layout)) => format!("struct {}", name(&layout.name)),
+            Some(Constant::Enum(layout)) => format!("enum {}", name(&layout.name)),
+            None => format!("?{id}"),
+        }
+    }
+
+    fn function(&self, id: u32) -> String {
+        let functions = &self.module.functions;
+        match functions.get(id as usize) {
+            Some(function) if functions.iter().filter(|f| f.name == function.name).count() == 1 => {
+                name(&function.name)
+            }
+            _ => format!("@{id}"),
+        }
+    }
+
+    fn global(&self, id: u32) -> String {
+        let globals = &self.module.globals;
+        match globals.get(id as usize) {
+            Some(global) if globals.iter().filter(|g| g.name == global.name).count() == 1 => {
+                name(&global.name)
+            }
+            _ => format!("@{id}"),
+        }
+    }
+}
+
+fn mnemonic(instr: &Instruction) -> &'static str {
+    match instr {
+        Instruction::LoadConst(_) => "load_const",
+        Instruction::LoadLocal(_) => "load_local",
+        Instruction::StoreLocal(_) => "store_local",
+        Instruction::LoadGlobal(_) => "load_global",
+        Instruction::StoreGlobal(_) => "store_global",
+        Instruction::Jump(_) => "jump",
+        Instruction::JumpIfFalse(_) => "jump_if_false",
+        Instruction::Add => "add",
+        Instruction::Sub => "sub",
+        Instruction::Mul => "mul",
+        Instruction::Div => "div",
+        Instruction::Mod => "mod",
+        Instruction::BitAnd => "bit_and",
+        Instruction::BitOr => "bit_or",
+        Instruction::BitXor => "bit_xor",
+        Instruction::Shl => "shl",
+        Instruction::Shr => "shr",
+        Instruction::Neg => "neg",
+        Instruction::Not => "not",
+        Instruction::BitNot => "bit_not",
+        Instruction::Eq => "eq",
+        Instruction::Ne => "ne",
+        Instruction::Lt => "lt",
+        Instruction::Le => "le",
+        Instruction::Gt => "gt",
+        Instruction::Ge => "ge",
+        Instruction::Call(_, _) => "call",
+        Instruction::CallHost(_, _) => "call_host",
+        Instruction::CallHostDynamic(_, _) => "call_host_dynamic",
+        Instruction::CallMethod(_, _) => "call_method",
+        Instruction::CallValue(_) => "call_value",
+        Instruction::MakeClosure(_, _) => "make_closure",
+        Instruction::MakeStruct(_) => "make_struct",
+        Instruction::LoadField(_) => "load_field",
+        Instruction::StoreField(_) => "store_field",
+        Instruction::LoadFieldNamed(_) => "load_field_named",
+        Instruction::StoreFieldNamed(_) => "store_field_named",
+        Instruction::MakeVariant(_, _) => "make_variant",
+        Instruction::TestVariant(_, _) => "test_variant",
+        Instruction::LoadVariantField(_) => "load_variant_field",
+        Instruction::MakeList(_) => "make_list",
+        Instruction::MakeTuple(_) => "make_tuple",
+        Instruction::UnpackTuple(_) => "unpack_tuple",
+        Instruction::Index => "index",
+        Instruction::StoreIndex => "store_index",
+        Instruction::Len => "len",
+        Instruction::Return => "return",
+        Instruction::Pop => "pop",
+    }
+}
+
+/// Instructions without operands, by mnemonic.
+fn nullary(mnemonic: &str) -> Option<Instruction> {
+    Some(match mnemonic {
+        "add" => Instruction::Add,
+        "sub" => Instruction::Sub,
+        "mul" => Instruction::Mul,
+        "div" => Instruction::Div,
+        "mod" => Instruction::Mod,
+        "bit_and" => Instruction::BitAnd,
+        "bit_or" => Instruction::BitOr,
+        "bit_xor" => Instruction::BitXor,
+        "shl" => Instruction::Shl,
+        "shr" => Instruction::Shr,
+        "neg" => Instruction::Neg,
+        "not" => Instruction::Not,
+        "bit_not" => Instruction::BitNot,
+        "eq" => Instruction::Eq,
+        "ne" => Instruction::Ne,
+        "lt" => Instruction::Lt,
+        "le" => Instruction::Le,
+        "gt" => Instruction::Gt,
+        "ge" => Instruction::Ge,
+        "index" => Instruction::Index,
+        "store_index" => Instruction::StoreIndex,
+        "len" => Instruction::Len,
+        "return" => Instruction::Return,
+        "pop" => Instruction::Pop,
+        _ => return None,
+    })
+}
+
+/// Writes `value` bare when it reads back as a single name token.
+fn name(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value.starts_with(['@', '?', '"'])
+        && !value.ends_with(':')
+        && !value.contains(|c: char| c.is_whitespace() || c == ';' || c == '"' || c == '/');
+    if plain {
+        value.to_string()
+    } else {
+        quote(value)
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.escape_debug())
+}
+
+pub fn assemble(text: &str) -> Result<BytecodeModule, AsmError> {
+    let mut lines = Vec::new();
+    for (index, source) in text.lines().enumerate() {
+        let tokens = tokenize(source, index + 1)?;
+        if !tokens.is_empty() {
+            lines.push(Line {
+                tokens,
+                pos: 0,
+                line: index + 1,
+            });
+        }
+    }
+    let mut assembler = Assembler::default();
+    assembler.declarations(&mut lines)?;
+    assembler.bodies(&mut lines)?;
+    Ok(assembler.module)
+}
+
+#[derive(Default)]
+struct Assembler {
+    module: BytecodeModule,
+    functions: Vec<String>,
+    structs: HashMap<String, ConstId>,
+    enums: HashMap<String, ConstId>,
+}
+
+impl Assembler {
+    /// First pass: function names and every top-level declaration, so that
+    /// bodies may refer to items declared further down.
+    fn declarations(&mut self, lines: &mut [Line]) -> Result<(), AsmError> {
+        let mut in_function = false;
+        let mut init = None;
+        for line in lines.iter_mut() {
+            let keyword = line.word()?;
+            if in_function {
+                in_function = keyword != "end";
+                continue;
+            }
+            match keyword.as_str() {
+                "fn" => {
+                    self.functions.push(line.name()?);
+                    in_function = true;
+                }
+                "struct" => {
+                    let name = line.name()?;
+                    let mut fields = Vec::new();
+                    while !line.at_end() {
+                        fields.push(line.name()?);
+                    }
+                    let id = self.intern(Constant::Struct(StructLayout {
+                        name: name.clone(),
+                        fields,
+                    }));
+                    if self.structs.insert(name.clone(), id).is_some() {
+                        return Err(line.error(format!("struct `{name}` is declared twice")));
+                    }
+                }
+                "enum" => {
+                    let name = line.name()?;
+                    let mut variants = Vec::new();
+                    while !line.at_end() {
+                        let variant = line.name()?;
+                        let (variant, arity) = match variant.rsplit_once('/') {
+                            Some((variant, arity)) => (
+                                variant.to_string(),
+                                arity.parse().map_err(|_| {
+                                    line.error(format!("invalid variant arity `{arity}`"))
+                                })?,
+                            ),
+                            None => (variant, 0),
+                        };
+                        variants.push(VariantLayout {
+                            name: variant,
+                            arity,
+                        });
+                    }
+                    let id = self.intern(Constant::Enum(EnumLayout {
+                        name: name.clone(),
+                        variants,
+                    }));
+                    if self.enums.insert(name.clone(), id).is_some() {
+                        return Err(line.error(format!("enum `{name}` is declared twice")));
+                    }
+                }
+                "global" => {
+                    let name = line.name()?;
+                    let value = self.constant(line)?;
+                    self.module.add_global(name, value);
+                }
+                "import" => {
+                    let name = line.name()?;
+                    self.module.imports.push(name.into());
+                }
+                "init" => init = Some(line.line),
+                "end" => return Err(line.error("`end` without a matching `fn`")),
+                other => return Err(line.error(format!("expected a declaration, found `{other}`"))),
+            }
+            if keyword != "fn" && keyword != "init" {
+                line.finish()?;
+            }
+        }
+        if in_function {
+            let name = self.functions.last().cloned().unwrap_or_default();
+            return Err(AsmError {
+                line: lines.last().map_or(0, |line| line.line),
+                message: format!("missing `end` for function `{name}`"),
+            });
+        }
+        if let Some(number) = init {
+            let line = lines
+                .iter_mut()
+                .find(|line| line.line == number)
+                .expect("init line");
+            self.module.initializer = Some(self.function(line)?);
+            line.finish()?;
+        }
+        Ok(())
+    }
+
+    fn bodies(&mut self, lines: &mut [Line]) -> Result<(), AsmError> {
+        for line in lines.iter_mut() {
+            line.pos = 0;
+        }
+        let mut index = 0;
+        while index < lines.len() {
+            if lines[index].tokens[0] != Token::Word("fn".into()) {
+                index += 1;
+                continue;
+            }
+            let header = &mut lines[index];
+            header.word()?;
+            let name = header.name()?;
+            let (mut params, mut locals, mut max_stack) = (0, 0, 0);
+            while !header.at_end() {
+                let setting = header.word()?;
+                let (key, value) = setting.split_once('=').ok_or_else(|| {
+                    header.error(format!("expected `key=value`, found `{setting}`"))
+                })?;
+                let invalid = || header.error(format!("invalid value for `{key}`"));
+                match key {
+                    "params" => params = value.parse().map_err(|_| invalid())?,
+                    "locals" => locals = value.parse().map_err(|_| invalid())?,
+                    "max_stack" => max_stack = value.parse().map_err(|_| invalid())?,
+                    _ => return Err(header.error(format!("unknown function setting `{key}`"))),
+                }
+            }
+            index += 1;
+
+            let mut instructions = Vec::new();
+            let mut labels = HashMap::new();
+            let mut jumps = Vec::new();
+            loop {
+                let line = &mut lines[index];
+                index += 1;
+                let word = line.word()?;
+                if word == "end" {
+                    line.finish()?;
+                    break;
+                }
+                if let Some(label) = word.strip_suffix(':') {
+                    if labels
+                        .insert(label.to_string(), instructions.len())
+                        .is_some()
+                    {
+                        return Err(line.error(format!("label `{label}` is defined twice")));
+                    }
+                    line.finish()?;
+                    continue;
+                }
+                let instruction = match word.as_str() {
+                    "jump" | "jump_if_false" => {
+                        let target = line.word()?;
+                        jumps.push((instructions.len(), target, line.line));
+                        if word == "jump" {
+                            Instruction::Jump(0)
+                        } else {
+                            Instruction::JumpIfFalse(0)
+                        }
+                    }
+                    _ => self.instruction(&word, line)?,
+                };
+                line.finish()?;
+                instructions.push(instruction);
+            }
+            for (at, target, line) in jumps {
+                let resolved = match labels.get(&target) {
+                    Some(resolved) => *resolved,
+                    None => target.parse().map_err(|_| AsmError {
+                        line,
+                        message: format!("unknown label `{target}`"),
+                    })?,
+                };
+                match &mut instructions[at] {
+                    Instruction::Jump(slot) | Instruction::JumpIfFalse(slot) => *slot = resolved,
+                    _ => unreachable!("only jumps are patched"),
+                }
+            }
+            self.module.add_function(Function {
+                max_stack,
+                ..Function::new(name, params, locals, instructions)
+            });
+        }
+        Ok(())
+    }
+
+    fn instruction(&mut self, word: &str, line: &mut Line) -> Result<Instruction, AsmError> {
+        if let Some(instruction) = nullary(word) {
+            return Ok(instruction);
+        }
+        Ok(match word {
+            "load_const" => Instruction::LoadConst(self.constant(line)?),
+            "load_local" => Instruction::LoadLocal(line.number()?),
+            "store_local" => Instruction::StoreLocal(line.number()?),
+            "load_global" => Instruction::LoadGlobal(self.global(line)?),
+            "store_global" => Instruction::StoreGlobal(self.global(line)?),
+            "call" => Instruction::Call(self.function(line)?, line.number()?),
+            "call_host" => Instruction::CallHost(line.number()?, line.number()?),
+            "call_host_dynamic" => {
+                Instruction::CallHostDynamic(self.constant(line)?, line.number()?)
+            }
+            "call_method" => Instruction::CallMethod(self.constant(line)?, line.number()?),
+            "call_value" => Instruction::CallValue(line.number()?),
+            "make_closure" => Instruction::MakeClosure(self.function(line)?, line.number()?),
+            "make_struct" => {
+                let name = line.name()?;
+                let id = self
+                    .structs
+                    .get(&name)
+                    .copied()
+                    .ok_or_else(|| line.error(format!("struct `{name}` is not declared")))?;
+                Instruction::MakeStruct(id)
+            }
+            "load_field" => Instruction::LoadField(line.number()?),
+            "store_field" => Instruction::StoreField(line.number()?),
+            "load_field_named" => Instruction::LoadFieldNamed(self.constant(line)?),
+            "store_field_named" => Instruction::StoreFieldNamed(self.constant(line)?),
+            "make_variant" => {
+                let (id, tag) = self.variant(line)?;
+                Instruction::MakeVariant(id, tag)
+            }
+            "test_variant" => {
+                let (id, tag) = self.variant(line)?;
+                Instruction::TestVariant(id, tag)
+            }
+            "load_variant_field" => Instruction::LoadVariantField(line.number()?),
+            "make_list" => Instruction::MakeList(line.number()?),
+            "make_tuple" => Instruction::MakeTuple(line.number()?),
+            "unpack_tuple" => Instruction::UnpackTuple(line.number()?),
+            other => return Err(line.error(format!("unknown instruction `{other}`"))),
+        })
+    }
+
+    fn intern(&mut self, constant: Constant) -> ConstId {
+        match self.module.constants.iter().position(|c| *c == constant) {
+            Some(id) => id as ConstId,
+            None => self.module.add_constant(constant),
+        }
+    }
+
+    fn constant(&mut self, line: &mut Line) -> Result<ConstId, AsmError> {
+        let constant = match line.next()? {
+            Token::Str(value) => Constant::String(value),
+            Token::Word(word) => match word.as_str() {
+                "int" => Constant::Int(line.number()?),
+                "float" => Constant::Float(line.number()?),
+                "true" => Constant::Bool(true),
+                "false" => Constant::Bool(false),
+                "unit" => Constant::Unit,
+                "struct" | "enum" => {
+                    let name = line.name()?;
+                    let layouts = if word == "struct" {
+                        &self.structs
+                    } else {
+                        &self.enums
+                    };
+                    return layouts
+                        .get(&name)
+                        .copied()
+                        .ok_or_else(|| line.error(format!("{word} `{name}` is not declared")));
+                }
+                other => return Err(line.error(format!("expected a constant, found `{other}`"))),
+            },
+        };
+        Ok(self.intern(constant))
+    }
+
+    fn function(&self, line: &mut Line) -> Result<u32, AsmError> {
+        let name = line.name()?;
+        resolve(&name, &self.functions, line, "function")
+    }
+
+    fn global(&self, line: &mut Line) -> Result<u32, AsmError> {
+        let name = line.name()?;
+        let globals: Vec<String> = self
+            .module
+            .globals
+            .iter()
+            .map(|global| global.name.to_string())
+            .collect();
+        resolve(&name, &globals, line, "global")
+    }
+
+    fn variant(&self, line: &mut Line) -> Result<(ConstId, u16), AsmError> {
+        let name = line.name()?;
+        let id = self
+            .enums
+            .get(&name)
+            .copied()
+            .ok_or_else(|| line.error(format!("enum `{name}` is not declared")))?;
+        let Some(Constant::Enum(layout)) = self.module.constants.get(id as usize) else {
+            unreachable!("enum names map to enum layouts");
+        };
+        let variant = line.name()?;
+        let tag = match layout.variants.iter().position(|v| v.name == variant) {
+            Some(tag) => tag as u16,
+            None => variant
+                .parse()
+                .map_err(|_| line.error(format!("enum `{name}` has no variant `{variant}`")))?,
+        };
+        Ok((id, tag))
+    }
+}
+
+/// Resolves `name` to an index in `names`, accepting `@index` as well.
+fn resolve(name: &str, names: &[String], line: &Line, what: &str) -> Result<u32, AsmError> {
+    if let Some(index) = name.strip_prefix('@') {
+        return index
+            .parse()
+            .map_err(|_| line.error(format!("invalid {what} index `{name}`")));
+    }
+    let mut matches = names.iter().enumerate().filter(|(_, n)| *n == name);
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) => Ok(index as u32),
+        (Some(_), Some(_)) => Err(line.error(format!(
+            "{what} name `{name}` is ambiguous; refer to it by index as `@N`"
+        ))),
+        (None, _) => Err(line.error(format!("unknown {what} `{name}`"))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+}
+
+struct Line {
+    tokens: Vec<Token>,
+    pos: usize,
+    line: usize,
+}
+
+impl Line {
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos == self.tokens.len()
+    }
+
+    fn next(&mut self) -> Result<Token, AsmError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| self.error("unexpected end of line"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn word(&mut self) -> Result<String, AsmError> {
+        match self.next()? {
+            Token::Word(word) => Ok(word),
+            Token::Str(value) => {
+                Err(self.error(format!("expected a word, found {}", quote(&value))))
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, AsmError> {
+        match self.next()? {
+            Token::Word(word) | Token::Str(word) => Ok(word),
+        }
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, AsmError> {
+        let word = self.word()?;
+        word.parse()
+            .map_err(|_| self.error(format!("expected a number, found `{word}`")))
+    }
+
+    fn finish(&self) -> Result<(), AsmError> {
+        match self.tokens.get(self.pos) {
+            None => Ok(()),
+            Some(Token::Word(word)) => Err(self.error(format!("unexpected `{word}`"))),
+            Some(Token::Str(value)) => Err(self.error(format!("unexpected {}", quote(value)))),
+        }
+    }
+}
+
+fn tokenize(source: &str, line: usize) -> Result<Vec<Token>, AsmError> {
+    let error = |message: &str| AsmError {
+        line,
+        message: message.to_string(),
+    };
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == ';' {
+            break;
+        } else if c == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next().ok_or_else(|| error("unterminated string"))? {
+                    '"' => break,
+                    '\\' => value.push(match chars.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some(c @ ('\\' | '"' | '\'')) => c,
+                        Some('u') => {
+                            let mut hex = String::new();
+                            if chars.next() != Some('{') {
+                                return Err(error("expected `{` after `\\u`"));
+                            }
+                            for c in chars.by_ref() {
+                                if c == '}' {
+                                    break;
+                                }
+                                hex.push(c);
+                            }
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| error("invalid unicode escape"))?
+                        }
+                        _ => return Err(error("invalid escape in string")),
+                    }),
+                    c => value.push(c),
+                }
+            }
+            tokens.push(Token::Str(value));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == ';' || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"struct Point x y
+enum Shape Circle/1 Empty/0
+global ORIGIN unit
+import print
+init <init>
+
+fn <init> params=0 locals=0 max_stack=2
+    load_const int 0
+    load_const float -1.5
+    make_struct Point
+    store_global ORIGIN
+    load_const unit
+    return
+end
+
+fn main params=1 locals=2 max_stack=3
+    load_local 0
+    jump_if_false L0
+    load_const "tab\there"
+    call_host_dynamic "print" 1
+    pop
+L0:
+    load_const int 2
+    make_variant Shape Circle
+    test_variant Shape Circle
+    make_closure @3 0
+    store_local 1
+    call @3 0
+    return
+end
+
+fn main::<lambda> params=0 locals=0 max_stack=1 ; @2
+    load_global ORIGIN
+    return
+end
+
+fn main::<lambda> params=0 locals=0 max_stack=1 ; @3
+    load_const true
+    return
+end
+"#;
+
+    #[test]
+    fn disassembly_round_trips() {
+        let module = assemble(SAMPLE).expect("assemble");
+        assert_eq!(module.initializer, Some(0));
+        assert_eq!(module.imports, vec!["print"]);
+        assert!(matches!(
+            module.functions[1].instructions[1],
+            Instruction::JumpIfFalse(5)
+        ));
+        assert_eq!(disassemble(&module), SAMPLE);
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let error = |text: &str| assemble(text).unwrap_err();
+        assert_eq!(
+            error("fn main\n    jump nowhere\nend\n"),
+            AsmError {
+                line: 2,
+                message: "unknown label `nowhere`".into(),
+            }
+        );
+        assert_eq!(error("fn main\n    frobnicate\nend\n").line, 2);
+        assert_eq!(error("fn main\n    return\n").line, 2);
+        assert_eq!(
+            error("fn f\nend\nfn f\nend\nfn main\n    call f 0\nend\n").line,
+            6
+        );
+        assert_eq!(error("\n\nfn main\n    load_const \"open\nend\n").line, 4);
+    }
+}
@@ -0,0 +1,942 @@
+//! The `.kbc` binary container for [`BytecodeModule`].
+//!
+//! Layout, all integers little-endian:
+//!
+//! ```text
+//! magic      4 bytes  "\x7fKBC"
+//! version    u16
+//! sections   u16      number of section table entries
+//! table      sections x { kind: u8, offset: u32, length: u32 }
+//! payloads   section bodies, in table order, back to back
+//! checksum   u32      CRC-32 of every preceding byte
+//! ```
+//!
+//! Section bodies use unsigned LEB128 varints for counts and indices and
+//! zigzag varints for signed integers. Readers skip section kinds they do not
+//! know, so new optional sections do not need a version bump.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use smol_str::SmolStr;
+use thiserror::Error;
+
+use crate::{
+    BytecodeModule, Constant, DebugInfo, EnumLayout, Function, Global, Instruction, LineEntry,
+    LocalVariable, SourceSpan, StructLayout, VariantLayout,
+};
+
+pub const MAGIC: [u8; 4] = *b"\x7fKBC";
+pub const FORMAT_VERSION: u16 = 1;
+
+const HEADER_LEN: u64 = 8;
+const TABLE_ENTRY_LEN: u64 = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    Constants,
+    Functions,
+    Globals,
+    Imports,
+    Debug,
+}
+
+impl SectionKind {
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(SectionKind::Constants),
+            2 => Some(SectionKind::Functions),
+            3 => Some(SectionKind::Globals),
+            4 => Some(SectionKind::Imports),
+            5 => Some(SectionKind::Debug),
+            _ => None,
+        }
+    }
+
+    fn tag(self) -> u8 {
+        match self {
+            SectionKind::Constants => 1,
+            SectionKind::Functions => 2,
+            SectionKind::Globals => 3,
+            SectionKind::Imports => 4,
+            SectionKind::Debug => 5,
+        }
+    }
+}
+
+impl fmt::Display for SectionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SectionKind::Constants => "constants",
+            SectionKind::Functions => "functions",
+            SectionKind::Globals => "globals",
+            SectionKind::Imports => "imports",
+            SectionKind::Debug => "debug",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    #[error("not a kbc module (bad magic number)")]
+    BadMagic,
+    #[error("unsupported kbc format version {found} (this build reads version {supported})")]
+    UnsupportedVersion { found: u16, supported: u16 },
+    #[error("input truncated at byte {offset}")]
+    Truncated { offset: u64 },
+    #[error("section at byte {offset} does not follow the previous one")]
+    SectionOffset { offset: u64 },
+    #[error("duplicate {0} section")]
+    DuplicateSection(SectionKind),
+    #[error("missing {0} section")]
+    MissingSection(SectionKind),
+    #[error("malformed {section} section at byte {offset}: {reason}")]
+    Malformed {
+        section: SectionKind,
+        offset: u64,
+        reason: &'static str,
+    },
+    #[error("checksum mismatch: stored {stored:#010x}, computed {computed:#010x}")]
+    ChecksumMismatch { stored: u32, computed: u32 },
+    #[error("unexpected data after the checksum")]
+    TrailingData,
+    #[error("failed to read kbc input: {0}")]
+    Io(#[from] io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum EncodeError {
+    #[error("{section} section does not fit in the kbc section table ({value} bytes)")]
+    SectionTooLarge { section: SectionKind, value: u64 },
+    #[error("failed to write kbc output: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Encodes `module` into a `.kbc` byte buffer.
+pub fn encode(module: &BytecodeModule) -> Result<Vec<u8>, EncodeError> {
+    let mut out = Vec::new();
+    write(module, &mut out)?;
+    Ok(out)
+}
+
+pub fn write<W: Write>(module: &BytecodeModule, out: W) -> Result<(), EncodeError> {
+    let mut sections = vec![
+        (SectionKind::Constants, encode_constants(module)),
+        (SectionKind::Functions, encode_functions(module)),
+        (SectionKind::Globals, encode_globals(module)),
+        (SectionKind::Imports, encode_imports(module)),
+    ];
+    if has_debug_info(module) {
+        sections.push((SectionKind::Debug, encode_debug(module)));
+    }
+    let mut out = ChecksumWriter {
+        inner: out,
+        crc: Crc32::new(),
+    };
+    out.write_all(&MAGIC)?;
+    out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    out.write_all(&(sections.len() as u16).to_le_bytes())?;
+    let mut offset = HEADER_LEN + TABLE_ENTRY_LEN * sections.len() as u64;
+    for (kind, body) in &sections {
+        out.write_all(&[kind.tag()])?;
+        out.write_all(&table_field(*kind, offset)?.to_le_bytes())?;
+        out.write_all(&table_field(*kind, body.len() as u64)?.to_le_bytes())?;
+        offset += body.len() as u64;
+    }
+    for (_, body) in &sections {
+        out.write_all(body)?;
+    }
+    let checksum = out.crc.finish();
+    out.inner.write_all(&checksum.to_le_bytes())?;
+    Ok(())
+}
+
+/// Narrows a section offset or length to its `u32` table field.
+fn table_field(section: SectionKind, value: u64) -> Result<u32, EncodeError> {
+    u32::try_from(value).map_err(|_| EncodeError::SectionTooLarge { section, value })
+}
+
+/// Decodes a complete `.kbc` buffer.
+pub fn decode(bytes: &[u8]) -> Result<BytecodeModule, DecodeError> {
+    read(bytes)
+}
+
+/// Decodes a `.kbc` module from `input`, reading it front to back once.
+pub fn read<R: Read>(input: R) -> Result<BytecodeModule, DecodeError> {
+    let mut reader = ChecksumReader {
+        inner: input,
+        crc: Crc32::new(),
+        offset: 0,
+    };
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(DecodeError::BadMagic);
+    }
+    let version = reader.read_u16()?;
+    if version != FORMAT_VERSION {
+        return Err(DecodeError::UnsupportedVersion {
+            found: version,
+            supported: FORMAT_VERSION,
+        });
+    }
+    let count = reader.read_u16()?;
+    let mut table = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let mut tag = [0; 1];
+        reader.read_exact(&mut tag)?;
+        let offset = reader.read_u32()? as u64;
+        let length = reader.read_u32()? as u64;
+        table.push((tag[0], offset, length));
+    }
+
+    let mut module = BytecodeModule::new();
+    let mut seen = Vec::new();
+    let mut debug = None;
+    for (tag, offset, length) in table {
+        if offset != reader.offset {
+            return Err(DecodeError::SectionOffset { offset });
+        }
+        let body = reader.read_section(length)?;
+        let Some(kind) = SectionKind::from_tag(tag) else {
+            continue;
+        };
+        if seen.contains(&kind) {
+            return Err(DecodeError::DuplicateSection(kind));
+        }
+        seen.push(kind);
+        let mut cursor = Cursor {
+            bytes: &body,
+            pos: 0,
+            base: offset,
+            section: kind,
+        };
+        match kind {
+            SectionKind::Constants => module.constants = decode_constants(&mut cursor)?,
+            SectionKind::Functions => {
+                let (functions, initializer) = decode_functions(&mut cursor)?;
+                module.functions = functions;
+                module.initializer = initializer;
+            }
+            SectionKind::Globals => module.globals = decode_globals(&mut cursor)?,
+            SectionKind::Imports => module.imports = decode_imports(&mut cursor)?,
+            SectionKind::Debug => debug = Some((offset, decode_debug(&mut cursor)?)),
+        }
+        cursor.finish()?;
+    }
+    if let Some((offset, (files, infos))) = debug {
+        if infos.len() != module.functions.len() {
+            return Err(DecodeError::Malformed {
+                section: SectionKind::Debug,
+                offset,
+                reason: "debug info does not match the number of functions",
+            });
+        }
+        module.source_files = files;
+        for (function, info) in module.functions.iter_mut().zip(infos) {
+            function.debug = info;
+        }
+    }
+    for required in [
+        SectionKind::Constants,
+        SectionKind::Functions,
+        SectionKind::Globals,
+    ] {
+        if !seen.contains(&required) {
+            return Err(DecodeError::MissingSection(required));
+        }
+    }
+
+    let computed = reader.crc.finish();
+    let stored = reader.read_u32()?;
+    if stored != computed {
+        return Err(DecodeError::ChecksumMismatch { stored, computed });
+    }
+    let mut extra = [0; 1];
+    if reader.inner.read(&mut extra)? != 0 {
+        return Err(DecodeError::TrailingData);
+    }
+    Ok(module)
+}
+
+fn encode_constants(module: &BytecodeModule) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_varint(&mut out, module.constants.len() as u64);
+    for constant in &module.constants {
+        match constant {
+            Constant::Int(value) => {
+                out.push(0);
+                put_varint(&mut out, ((value << 1) ^ (value >> 63)) as u64);
+            }
+            Constant::Float(value) => {
+                out.push(1);
+                out.extend_from_slice(&value.to_bits().to_le_bytes());
+            }
+            Constant::Bool(value) => {
+                out.push(2);
+                out.push(*value as u8);
+            }
+            Constant::String(value) => {
+                out.push(3);
+                put_str(&mut out, value);
+            }
+            Constant::Unit => out.push(4),
+            Constant::Struct(layout) => {
+                out.push(5);
+                put_str(&mut out, &layout.name);
+                put_varint(&mut out, layout.fields.len() as u64);
+                for field in &layout.fields {
+                    put_str(&mut out, field);
+                }
+            }
+            Constant::Enum(layout) => {
+                out.push(6);
+                put_str(&mut out, &layout.name);
+                put_varint(&mut out, layout.variants.len() as u64);
+                for variant in &layout.variants {
+                    put_str(&mut out, &variant.name);
+                    put_varint(&mut out, variant.arity as u64);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn decode_constants(cursor: &mut Cursor) -> Result<Vec<Constant>, DecodeError> {
+    let count = cursor.count()?;
+    let mut constants = Vec::with_capacity(count);
+    for _ in 0..count {
+        let constant = match cursor.u8()? {
+            0 => {
+                let raw = cursor.varint()?;
+                Constant::Int(((raw >> 1) as i64) ^ -((raw & 1) as i64))
+            }
+            1 => Constant::Float(f64::from_bits(u64::from_le_bytes(cursor.array()?))),
+            2 => match cursor.u8()? {
+                0 => Constant::Bool(false),
+                1 => Constant::Bool(true),
+                _ => return Err(cursor.malformed("invalid bool constant")),
+            },
+            3 => Constant::String(cursor.string()?),
+            4 => Constant::Unit,
+            5 => {
+                let name = cursor.string()?;
+                let fields = (0..cursor.count()?)
+                    .map(|_| cursor.string())
+                    .collect::<Result<_, _>>()?;
+                Constant::Struct(StructLayout { name, fields })
+            }
+            6 => {
+                let name = cursor.string()?;
+                let variants = (0..cursor.count()?)
+                    .map(|_| {
+                        Ok(VariantLayout {
+                            name: cursor.string()?,
+                            arity: cursor.u16()?,
+                        })
+                    })
+                    .collect::<Result<_, DecodeError>>()?;
+                Constant::Enum(EnumLayout { name, variants })
+            }
+            _ => return Err(cursor.malformed("unknown constant tag")),
+        };
+        constants.push(constant);
+    }
+    Ok(constants)
+}
+
+fn encode_functions(module: &BytecodeModule) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_varint(&mut out, module.functions.len() as u64);
+    for function in &module.functions {
+        put_str(&mut out, &function.name);
+        put_varint(&mut out, function.params as u64);
+        put_varint(&mut out, function.locals as u64);
+        put_varint(&mut out, function.max_stack as u64);
+        put_varint(&mut out, function.instructions.len() as u64);
+        for instruction in &function.instructions {
+            encode_instruction(&mut out, instruction);
+        }
+    }
+    put_varint(
+        &mut out,
+        module.initializer.map_or(0, |init| init as u64 + 1),
+    );
+    out
+}
+
+type DecodedFunctions = (Vec<Function>, Option<u32>);
+
+fn decode_functions(cursor: &mut Cursor) -> Result<DecodedFunctions, DecodeError> {
+    let count = cursor.count()?;
+    let mut functions = Vec::with_capacity(count);
+    for _ in 0..count {
+        let name = cursor.string()?;
+        let params = cursor.u16()?;
+        let locals = cursor.u16()?;
+        let max_stack = cursor.u32()?;
+        let instructions = (0..cursor.count()?)
+            .map(|_| decode_instruction(cursor))
+            .collect::<Result<_, _>>()?;
+        functions.push(Function {
+            max_stack,
+            ..Function::new(name, params, locals, instructions)
+        });
+    }
+    let initializer = match cursor.u32()? {
+        0 => None,
+        init => Some(init - 1),
+    };
+    Ok((functions, initializer))
+}
+
+fn encode_globals(module: &BytecodeModule) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_varint(&mut out, module.globals.len() as u64);
+    for global in &module.globals {
+        put_str(&mut out, &global.name);
+        put_varint(&mut out, global.value as u64);
+    }
+    out
+}
+
+fn decode_globals(cursor: &mut Cursor) -> Result<Vec<Global>, DecodeError> {
+    (0..cursor.count()?)
+        .map(|_| {
+            Ok(Global {
+                name: SmolStr::from(cursor.string()?),
+                value: cursor.u32()?,
+            })
+        })
+        .collect()
+}
+
+fn encode_imports(module: &BytecodeModule) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_varint(&mut out, module.imports.len() as u64);
+    for import in &module.imports {
+        put_str(&mut out, import);
+    }
+    out
+}
+
+fn decode_imports(cursor: &mut Cursor) -> Result<Vec<SmolStr>, DecodeError> {
+    (0..cursor.count()?)
+        .map(|_| cursor.string().map(SmolStr::from))
+        .collect()
+}
+
+fn has_debug_info(module: &BytecodeModule) -> bool {
+    !module.source_files.is_empty()
+        || module
+            .functions
+            .iter()
+            .any(|function| function.debug != DebugInfo::default())
+}
+
+fn encode_debug(module: &BytecodeModule) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_varint(&mut out, module.source_files.len() as u64);
+    for file in &module.source_files {
+        put_str(&mut out, file);
+    }
+    put_varint(&mut out, module.functions.len() as u64);
+    for function in &module.functions {
+        let debug = &function.debug;
+        put_varint(&mut out, debug.lines.len() as u64);
+        for entry in &debug.lines {
+            let span = &entry.span;
+            for value in [
+                entry.instruction,
+                span.file,
+                span.start,
+                span.end,
+                span.line,
+                span.column,
+            ] {
+                put_varint(&mut out, value as u64);
+            }
+        }
+        put_varint(&mut out, debug.locals.len() as u64);
+        for local in &debug.locals {
+            put_str(&mut out, &local.name);
+            put_varint(&mut out, local.slot as u64);
+            put_varint(&mut out, local.start as u64);
+            put_varint(&mut out, local.end as u64);
+        }
+    }
+    out
+}
+
+type DecodedDebug = (Vec<SmolStr>, Vec<DebugInfo>);
+
+fn decode_debug(cursor: &mut Cursor) -> Result<DecodedDebug, DecodeError> {
+    let files = (0..cursor.count()?)
+        .map(|_| cursor.string().map(SmolStr::from))
+        .collect::<Result<_, _>>()?;
+    let mut infos = Vec::new();
+    for _ in 0..cursor.count()? {
+        let lines = (0..cursor.count()?)
+            .map(|_| {
+                Ok(LineEntry {
+                    instruction: cursor.u32()?,
+                    span: SourceSpan {
+                        file: cursor.u32()?,
+                        start: cursor.u32()?,
+                        end: cursor.u32()?,
+                        line: cursor.u32()?,
+                        column: cursor.u32()?,
+                    },
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?;
+        let locals = (0..cursor.count()?)
+            .map(|_| {
+                Ok(LocalVariable {
+                    name: SmolStr::from(cursor.string()?),
+                    slot: cursor.u16()?,
+                    start: cursor.u32()?,
+                    end: cursor.u32()?,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?;
+        infos.push(DebugInfo { lines, locals });
+    }
+    Ok((files, infos))
+}
+
+fn encode_instruction(out: &mut Vec<u8>, instruction: &Instruction) {
+    let (opcode, operands): (u8, &[u64]) = match *instruction {
+        Instruction::LoadConst(id) => (0, &[id as u64]),
+        Instruction::LoadLocal(slot) => (1, &[slot as u64]),
+        Instruction::StoreLocal(slot) => (2, &[slot as u64]),
+        Instruction::LoadGlobal(id) => (3, &[id as u64]),
+        Instruction::StoreGlobal(id) => (4, &[id as u64]),
+        Instruction::Jump(target) => (5, &[target as u64]),
+        Instruction::JumpIfFalse(target) => (6, &[target as u64]),
+        Instruction::Add => (7, &[]),
+        Instruction::Sub => (8, &[]),
+        Instruction::Mul => (9, &[]),
+        Instruction::Div => (10, &[]),
+        Instruction::Mod => (11, &[]),
+        Instruction::BitAnd => (12, &[]),
+        Instruction::BitOr => (13, &[]),
+        Instruction::BitXor => (14, &[]),
+        Instruction::Shl => (15, &[]),
+        Instruction::Shr => (16, &[]),
+        Instruction::Neg => (17, &[]),
+        Instruction::Not => (18, &[]),
+        Instruction::BitNot => (19, &[]),
+        Instruction::Eq => (20, &[]),
+        Instruction::Ne => (21, &[]),
+        Instruction::Lt => (22, &[]),
+        Instruction::Le => (23, &[]),
+        Instruction::Gt => (24, &[]),
+        Instruction::Ge => (25, &[]),
+        Instruction::Call(func, argc) => (26, &[func as u64, argc as u64]),
+        Instruction::CallHost(slot, argc) => (27, &[slot as u64, argc as u64]),
+        Instruction::CallHostDynamic(name, argc) => (28, &[name as u64, argc as u64]),
+        Instruction::CallMethod(name, argc) => (29, &[name as u64, argc as u64]),
+        Instruction::CallValue(argc) => (30, &[argc as u64]),
+        Instruction::MakeClosure(func, captures) => (31, &[func as u64, captures as u64]),
+        Instruction::MakeStruct(layout) => (32, &[layout as u64]),
+        Instruction::LoadField(idx) => (33, &[idx as u64]),
+        Instruction::StoreField(idx) => (34, &[idx as u64]),
+        Instruction::LoadFieldNamed(name) => (35, &[name as u64]),
+        Instruction::StoreFieldNamed(name) => (36, &[name as u64]),
+        Instruction::MakeVariant(layout, tag) => (37, &[layout as u64, tag as u64]),
+        Instruction::TestVariant(layout, tag) => (38, &[layout as u64, tag as u64]),
+        Instruction::LoadVariantField(idx) => (39, &[idx as u64]),
+        Instruction::MakeList(len) => (40, &[len as u64]),
+        Instruction::MakeTuple(len) => (41, &[len as u64]),
+        Instruction::UnpackTuple(len) => (42, &[len as u64]),
+        Instruction::Index => (43, &[]),
+        Instruction::StoreIndex => (44, &[]),
+        Instruction::Return => (45, &[]),
+        Instruction::Pop => (46, &[]),
+        Instruction::Len => (47, &[]),
+    };
+    out.push(opcode);
+    for operand in operands {
+        put_varint(out, *operand);
+    }
+}
+
+fn decode_instruction(cursor: &mut Cursor) -> Result<Instruction, DecodeError> {
+    let instruction = match cursor.u8()? {
+        0 => Instruction::LoadConst(cursor.u32()?),
+        1 => Instruction::LoadLocal(cursor.u16()?),
+        2 => Instruction::StoreLocal(cursor.u16()?),
+        3 => Instruction::LoadGlobal(cursor.u32()?),
+        4 => Instruction::StoreGlobal(cursor.u32()?),
+        5 => Instruction::Jump(cursor.usize()?),
+        6 => Instruction::JumpIfFalse(cursor.usize()?),
+        7 => Instruction::Add,
+        8 => Instruction::Sub,
+        9 => Instruction::Mul,
+        10 => Instruction::Div,
+        11 => Instruction::Mod,
+        12 => Instruction::BitAnd,
+        13 => Instruction::BitOr,
+        14 => Instruction::BitXor,
+        15 => Instruction::Shl,
+        16 => Instruction::Shr,
+        17 => Instruction::Neg,
+        18 => Instruction::Not,
+        19 => Instruction::BitNot,
+        20 => Instruction::Eq,
+        21 => Instruction::Ne,
+        22 => Instruction::Lt,
+        23 => Instruction::Le,
+        24 => Instruction::Gt,
+        25 => Instruction::Ge,
+        26 => Instruction::Call(cursor.u32()?, cursor.u16()?),
+        27 => Instruction::CallHost(cursor.u32()?, cursor.u16()?),
+        28 => Instruction::CallHostDynamic(cursor.u32()?, cursor.u16()?),
+        29 => Instruction::CallMethod(cursor.u32()?, cursor.u16()?),
+        30 => Instruction::CallValue(cursor.u16()?),
+        31 => Instruction::MakeClosure(cursor.u32()?, cursor.u16()?),
+        32 => Instruction::MakeStruct(cursor.u32()?),
+        33 => Instruction::LoadField(cursor.u16()?),
+        34 => Instruction::StoreField(cursor.u16()?),
+        35 => Instruction::LoadFieldNamed(cursor.u32()?),
+        36 => Instruction::StoreFieldNamed(cursor.u32()?),
+        37 => Instruction::MakeVariant(cursor.u32()?, cursor.u16()?),
+        38 => Instruction::TestVariant(cursor.u32()?, cursor.u16()?),
+        39 => Instruction::LoadVariantField(cursor.u16()?),
+        40 => Instruction::MakeList(cursor.u16()?),
+        41 => Instruction::MakeTuple(cursor.u16()?),
+        42 => Instruction::UnpackTuple(cursor.u16()?),
+        43 => Instruction::Index,
+        44 => Instruction::StoreIndex,
+        45 => Instruction::Return,
+        46 => Instruction::Pop,
+        47 => Instruction::Len,
+        _ => return Err(cursor.malformed("unknown opcode")),
+    };
+    Ok(instruction)
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_str(out: &mut Vec<u8>, value: &str) {
+    put_varint(out, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Reads one section body that has already been pulled off the stream.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    base: u64,
+    section: SectionKind,
+}
+
+impl Cursor<'_> {
+    fn malformed(&self, reason: &'static str) -> DecodeError {
+        DecodeError::Malformed {
+            section: self.section,
+            offset: self.base + self.pos as u64,
+            reason,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], DecodeError> {
+        if self.bytes.len() - self.pos < len {
+            return Err(self.malformed("unexpected end of section"));
+        }
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut out = [0; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            if shift == 63 && byte > 1 {
+                break;
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.malformed("varint does not fit in 64 bits"))
+    }
+
+    fn bounded(&mut self, max: u64) -> Result<u64, DecodeError> {
+        let value = self.varint()?;
+        if value > max {
+            return Err(self.malformed("value out of range"));
+        }
+        Ok(value)
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(self.bounded(u16::MAX as u64)? as u16)
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(self.bounded(u32::MAX as u64)? as u32)
+    }
+
+    fn usize(&mut self) -> Result<usize, DecodeError> {
+        Ok(self.bounded(usize::MAX as u64)? as usize)
+    }
+
+    /// Reads an element count, rejecting counts that cannot possibly fit in
+    /// the rest of the section so corrupt input cannot force huge allocations.
+    fn count(&mut self) -> Result<usize, DecodeError> {
+        let remaining = (self.bytes.len() - self.pos) as u64;
+        let count = self.varint()?;
+        if count > remaining {
+            return Err(self.malformed("element count exceeds section length"));
+        }
+        Ok(count as usize)
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let len = self.count()?;
+        let bytes = self.take(len)?.to_vec();
+        String::from_utf8(bytes).map_err(|_| self.malformed("string is not valid UTF-8"))
+    }
+
+    fn finish(&self) -> Result<(), DecodeError> {
+        if self.pos != self.bytes.len() {
+            return Err(self.malformed("unused bytes at end of section"));
+        }
+        Ok(())
+    }
+}
+
+struct ChecksumReader<R> {
+    inner: R,
+    crc: Crc32,
+    offset: u64,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), DecodeError> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(0) => {
+                    return Err(DecodeError::Truncated {
+                        offset: self.offset + filled as u64,
+                    })
+                }
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        self.crc.update(buf);
+        self.offset += buf.len() as u64;
+        Ok(())
+    }
+
+    fn read_u16(&mut self) -> Result<u16, DecodeError> {
+        let mut bytes = [0; 2];
+        self.read_exact(&mut bytes)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        let mut bytes = [0; 4];
+        self.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    /// Reads `length` bytes without trusting `length` for the allocation.
+    fn read_section(&mut self, length: u64) -> Result<Vec<u8>, DecodeError> {
+        let mut body = Vec::new();
+        let read = (&mut self.inner).take(length).read_to_end(&mut body)?;
+        if (read as u64) < length {
+            return Err(DecodeError::Truncated {
+                offset: self.offset + read as u64,
+            });
+        }
+        self.crc.update(&body);
+        self.offset += length;
+        Ok(body)
+    }
+}
+
+struct ChecksumWriter<W> {
+    inner: W,
+    crc: Crc32,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.crc.update(bytes);
+        self.inner.write_all(bytes)
+    }
+}
+
+/// CRC-32 (IEEE 802.3), computed bitwise.
+struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (self.state & 1).wrapping_neg();
+                self.state = (self.state >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> BytecodeModule {
+        let mut module = BytecodeModule::new();
+        let unit = module.add_constant(Constant::Unit);
+        let big = module.add_constant(Constant::Int(i64::MIN));
+        module.add_constant(Constant::Float(-0.5));
+        module.add_constant(Constant::String("héllo".into()));
+        module.add_constant(Constant::Enum(EnumLayout {
+            name: "Shape".into(),
+            variants: vec![VariantLayout {
+                name: "Circle".into(),
+                arity: 1,
+            }],
+        }));
+        let global = module.add_global("LIMIT", unit);
+        let main = module.add_function(Function::new(
+            "main",
+            1,
+            2,
+            vec![
+                Instruction::LoadConst(big),
+                Instruction::StoreGlobal(global),
+                Instruction::JumpIfFalse(4),
+                Instruction::Call(0, 1),
+                Instruction::Return,
+            ],
+        ));
+        module.initializer = Some(main);
+        module.imports.push("print".into());
+        module.source_files.push("main.ktn".into());
+        module.functions[0].debug = DebugInfo {
+            lines: vec![LineEntry {
+                instruction: 1,
+                span: SourceSpan {
+                    file: 0,
+                    start: 12,
+                    end: 20,
+                    line: 2,
+                    column: 5,
+                },
+            }],
+            locals: vec![LocalVariable {
+                name: "n".into(),
+                slot: 0,
+                start: 0,
+                end: 5,
+            }],
+        };
+        module
+    }
+
+    #[test]
+    fn round_trips_through_binary() {
+        let module = sample();
+        let bytes = encode(&module).expect("encode");
+        assert_eq!(&bytes[..4], &MAGIC);
+        let decoded = decode(&bytes).expect("decode");
+        assert_eq!(decoded.constants, module.constants);
+        assert_eq!(decoded.initializer, module.initializer);
+        assert_eq!(decoded.imports, module.imports);
+        assert_eq!(decoded.source_files, module.source_files);
+        assert_eq!(decoded.functions[0].debug, module.functions[0].debug);
+        assert_eq!(decoded.globals[0].name, "LIMIT");
+        assert_eq!(
+            format!("{:?}", decoded.functions),
+            format!("{:?}", module.functions)
+        );
+        assert!(bytes.len() < module.to_json().expect("json").len());
+    }
+
+    #[test]
+    fn rejects_truncated_and_corrupt_input() {
+        let bytes = encode(&sample()).expect("encode");
+        for len in [0, 3, 10, bytes.len() - 1] {
+            assert!(
+                matches!(decode(&bytes[..len]), Err(DecodeError::Truncated { .. })),
+                "length {len}"
+            );
+        }
+
+        let mut corrupt = bytes.clone();
+        let last_payload = bytes.len() - 5;
+        corrupt[last_payload] ^= 0x01;
+        assert!(matches!(
+            decode(&corrupt),
+            Err(DecodeError::ChecksumMismatch { .. }) | Err(DecodeError::Malformed { .. })
+        ));
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'{';
+        assert!(matches!(decode(&bad_magic), Err(DecodeError::BadMagic)));
+
+        let mut future = bytes.clone();
+        future[4] = 2;
+        assert!(matches!(
+            decode(&future),
+            Err(DecodeError::UnsupportedVersion { found: 2, .. })
+        ));
+
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(matches!(decode(&trailing), Err(DecodeError::TrailingData)));
+    }
+
+    #[test]
+    fn rejects_sections_past_the_u32_table_range() {
+        assert_eq!(
+            table_field(SectionKind::Debug, u32::MAX as u64).ok(),
+            Some(u32::MAX)
+        );
+        assert!(matches!(
+            table_field(SectionKind::Debug, u32::MAX as u64 + 1),
+            Err(EncodeError::SectionTooLarge {
+                section: SectionKind::Debug,
+                value: 0x1_0000_0000,
+            })
+        ));
+    }
+}
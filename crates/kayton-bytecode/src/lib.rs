@@ -1,18 +1,54 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 use smol_str::SmolStr;
 
+pub mod asm;
+pub mod kbc;
+
 pub type ConstId = u32;
 pub type FunctionId = u32;
 pub type HostSlot = u32;
+pub type GlobalId = u32;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Constant {
     Int(i64),
+    Float(f64),
     Bool(bool),
     String(String),
     Unit,
+    Struct(StructLayout),
+    Enum(EnumLayout),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructLayout {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+impl StructLayout {
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field == name)
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnumLayout {
+    pub name: String,
+    pub variants: Vec<VariantLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VariantLayout {
+    pub name: String,
+    pub arity: u16,
+}
+
+/// A module-level variable. `value` is its initial value; globals with
+/// computed initializers start as `Unit` and are assigned by the module
+/// initializer.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Global {
     pub name: SmolStr,
@@ -24,7 +60,59 @@ pub struct Function {
     pub name: SmolStr,
     pub params: u16,
     pub locals: u16,
+    /// Deepest operand stack the function needs, filled in by [`Verifier`].
+    #[serde(default)]
+    pub max_stack: u32,
     pub instructions: Vec<Instruction>,
+    #[serde(default)]
+    pub debug: DebugInfo,
+}
+
+/// Maps a function's instructions back to source. Empty when the module was
+/// built without debug info.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DebugInfo {
+    /// Sorted by `instruction`; each entry covers instructions up to the next.
+    pub lines: Vec<LineEntry>,
+    pub locals: Vec<LocalVariable>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineEntry {
+    pub instruction: u32,
+    pub span: SourceSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    /// Index into [`BytecodeModule::source_files`].
+    pub file: u32,
+    pub start: u32,
+    pub end: u32,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// A named local slot, in scope for instructions `start..end`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalVariable {
+    pub name: SmolStr,
+    pub slot: u16,
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: SmolStr,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
 }
 
 impl Function {
@@ -38,9 +126,17 @@ impl Function {
             name: name.into(),
             params,
             locals,
+            max_stack: 0,
             instructions,
+            debug: DebugInfo::default(),
         }
     }
+
+    pub fn span_at(&self, instruction: usize) -> Option<&SourceSpan> {
+        let lines = &self.debug.lines;
+        let next = lines.partition_point(|entry| entry.instruction as usize <= instruction);
+        next.checked_sub(1).map(|idx| &lines[idx].span)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,14 +144,23 @@ pub enum Instruction {
     LoadConst(ConstId),
     LoadLocal(u16),
     StoreLocal(u16),
+    LoadGlobal(GlobalId),
+    StoreGlobal(GlobalId),
     Jump(usize),
     JumpIfFalse(usize),
     Add,
     Sub,
     Mul,
     Div,
+    Mod,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
     Neg,
     Not,
+    BitNot,
     Eq,
     Ne,
     Lt,
@@ -65,6 +170,23 @@ pub enum Instruction {
     Call(FunctionId, u16),
     CallHost(HostSlot, u16),
     CallHostDynamic(ConstId, u16),
+    CallMethod(ConstId, u16),
+    CallValue(u16),
+    MakeClosure(FunctionId, u16),
+    MakeStruct(ConstId),
+    LoadField(u16),
+    StoreField(u16),
+    LoadFieldNamed(ConstId),
+    StoreFieldNamed(ConstId),
+    MakeVariant(ConstId, u16),
+    TestVariant(ConstId, u16),
+    LoadVariantField(u16),
+    MakeList(u16),
+    MakeTuple(u16),
+    UnpackTuple(u16),
+    Index,
+    StoreIndex,
+    Len,
     Return,
     Pop,
 }
@@ -74,6 +196,16 @@ pub struct BytecodeModule {
     pub constants: Vec<Constant>,
     pub globals: Vec<Global>,
     pub functions: Vec<Function>,
+    /// Function run once before the entry point to evaluate global
+    /// initializers in declaration order.
+    #[serde(default)]
+    pub initializer: Option<FunctionId>,
+    /// Host functions called by name, for loaders that bind them up front.
+    #[serde(default)]
+    pub imports: Vec<SmolStr>,
+    /// Paths of the source files named by debug info.
+    #[serde(default)]
+    pub source_files: Vec<SmolStr>,
 }
 
 impl BytecodeModule {
@@ -87,11 +219,13 @@ impl BytecodeModule {
         id
     }
 
-    pub fn add_global(&mut self, name: impl Into<SmolStr>, value: ConstId) {
+    pub fn add_global(&mut self, name: impl Into<SmolStr>, value: ConstId) -> GlobalId {
+        let id = self.globals.len() as GlobalId;
         self.globals.push(Global {
             name: name.into(),
             value,
         });
+        id
     }
 
     pub fn add_function(&mut self, function: Function) -> FunctionId {
@@ -100,6 +234,19 @@ impl BytecodeModule {
         id
     }
 
+    /// Source location of `instruction` in `function`, if debug info has one.
+    pub fn location(&self, function: FunctionId, instruction: usize) -> Option<SourceLocation> {
+        let span = self
+            .functions
+            .get(function as usize)?
+            .span_at(instruction)?;
+        Some(SourceLocation {
+            file: self.source_files.get(span.file as usize)?.clone(),
+            line: span.line,
+            column: span.column,
+        })
+    }
+
     pub fn function_index(&self, name: &str) -> Option<FunctionId> {
         self.functions
             .iter()
@@ -107,15 +254,26 @@ impl BytecodeModule {
             .map(|idx| idx as FunctionId)
     }
 
-    pub fn serialize(&self) -> serde_json::Result<Vec<u8>> {
-        serde_json::to_vec(self)
+    /// Encodes the module in the binary `.kbc` format.
+    pub fn serialize(&self) -> Result<Vec<u8>, kbc::EncodeError> {
+        kbc::encode(self)
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, kbc::DecodeError> {
+        kbc::decode(bytes)
     }
 
-    pub fn deserialize(bytes: &[u8]) -> serde_json::Result<Self> {
+    /// JSON form of the module, for debugging.
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self)
+    }
+
+    pub fn from_json(bytes: &[u8]) -> serde_json::Result<Self> {
         serde_json::from_slice(bytes)
     }
 
-    pub fn verify(&self) -> Result<(), VerificationError> {
+    /// Checks the module and records each function's `max_stack`.
+    pub fn verify(&mut self) -> Result<(), VerificationError> {
         Verifier.verify(self)
     }
 }
@@ -126,66 +284,300 @@ pub enum VerificationError {
     BadConstant { instruction: usize },
     #[error("local index out of bounds at instruction {instruction}")]
     BadLocal { instruction: usize },
+    #[error("global index out of bounds at instruction {instruction}")]
+    BadGlobal { instruction: usize },
     #[error("function index out of bounds at instruction {instruction}")]
     BadFunction { instruction: usize },
     #[error("jump target out of bounds at instruction {instruction}")]
     BadJump { instruction: usize },
+    #[error("variant tag out of bounds at instruction {instruction}")]
+    BadVariant { instruction: usize },
+    #[error("call passes {found} arguments to a function taking {expected} at instruction {instruction}")]
+    CallArity {
+        instruction: usize,
+        expected: u16,
+        found: u16,
+    },
+    #[error("stack underflow at instruction {instruction}")]
+    StackUnderflow { instruction: usize },
+    #[error("stack height {found} does not match {expected} from another path at instruction {instruction}")]
+    StackMismatch {
+        instruction: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("execution falls off the end of the function after instruction {instruction}")]
+    FallThrough { instruction: usize },
+    #[error("return leaves {found} values on the stack instead of 1 at instruction {instruction}")]
+    ReturnHeight { instruction: usize, found: usize },
 }
 
 #[derive(Default)]
 pub struct Verifier;
 
 impl Verifier {
-    pub fn verify(&self, module: &BytecodeModule) -> Result<(), VerificationError> {
+    pub fn verify(&self, module: &mut BytecodeModule) -> Result<(), VerificationError> {
+        if let Some(init) = module.initializer {
+            if module.functions.get(init as usize).is_none() {
+                return Err(VerificationError::BadFunction { instruction: 0 });
+            }
+        }
         for function in &module.functions {
-            let local_limit = function.locals as usize;
-            for (idx, instr) in function.instructions.iter().enumerate() {
-                match instr {
-                    Instruction::LoadConst(id) => {
-                        if module.constants.get(*id as usize).is_none() {
-                            return Err(VerificationError::BadConstant { instruction: idx });
-                        }
+            self.check_operands(module, function)?;
+        }
+        let depths = module
+            .functions
+            .iter()
+            .map(|function| self.check_stack(module, function))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (function, depth) in module.functions.iter_mut().zip(depths) {
+            function.max_stack = depth;
+        }
+        Ok(())
+    }
+
+    fn check_operands(
+        &self,
+        module: &BytecodeModule,
+        function: &Function,
+    ) -> Result<(), VerificationError> {
+        let local_limit = function.locals as usize;
+        for (idx, instr) in function.instructions.iter().enumerate() {
+            match instr {
+                Instruction::LoadConst(id) => {
+                    if matches!(
+                        module.constants.get(*id as usize),
+                        None | Some(Constant::Struct(_) | Constant::Enum(_))
+                    ) {
+                        return Err(VerificationError::BadConstant { instruction: idx });
+                    }
+                }
+                Instruction::LoadLocal(local) | Instruction::StoreLocal(local) => {
+                    if (*local as usize) >= local_limit {
+                        return Err(VerificationError::BadLocal { instruction: idx });
                     }
-                    Instruction::LoadLocal(local) | Instruction::StoreLocal(local) => {
-                        if (*local as usize) >= local_limit {
-                            return Err(VerificationError::BadLocal { instruction: idx });
-                        }
+                }
+                Instruction::LoadGlobal(global) | Instruction::StoreGlobal(global) => {
+                    if module.globals.get(*global as usize).is_none() {
+                        return Err(VerificationError::BadGlobal { instruction: idx });
+                    }
+                }
+                Instruction::Call(func, arg_count) => {
+                    let Some(callee) = module.functions.get(*func as usize) else {
+                        return Err(VerificationError::BadFunction { instruction: idx });
+                    };
+                    if callee.params != *arg_count {
+                        return Err(VerificationError::CallArity {
+                            instruction: idx,
+                            expected: callee.params,
+                            found: *arg_count,
+                        });
+                    }
+                }
+                Instruction::MakeClosure(func, _) => {
+                    if module.functions.get(*func as usize).is_none() {
+                        return Err(VerificationError::BadFunction { instruction: idx });
+                    }
+                }
+                Instruction::CallHost(_, _) => {}
+                Instruction::CallHostDynamic(const_id, _) => {
+                    if module.constants.get(*const_id as usize).is_none() {
+                        return Err(VerificationError::BadConstant { instruction: idx });
+                    }
+                }
+                Instruction::MakeStruct(const_id) => {
+                    if !matches!(
+                        module.constants.get(*const_id as usize),
+                        Some(Constant::Struct(_))
+                    ) {
+                        return Err(VerificationError::BadConstant { instruction: idx });
                     }
-                    Instruction::Call(func, _) => {
-                        if module.functions.get(*func as usize).is_none() {
-                            return Err(VerificationError::BadFunction { instruction: idx });
-                        }
+                }
+                Instruction::MakeVariant(const_id, tag)
+                | Instruction::TestVariant(const_id, tag) => {
+                    let Some(Constant::Enum(layout)) = module.constants.get(*const_id as usize)
+                    else {
+                        return Err(VerificationError::BadConstant { instruction: idx });
+                    };
+                    if (*tag as usize) >= layout.variants.len() {
+                        return Err(VerificationError::BadVariant { instruction: idx });
                     }
-                    Instruction::CallHost(_, _) => {}
-                    Instruction::CallHostDynamic(const_id, _) => {
-                        if module.constants.get(*const_id as usize).is_none() {
-                            return Err(VerificationError::BadConstant { instruction: idx });
-                        }
+                }
+                Instruction::CallMethod(const_id, _)
+                | Instruction::LoadFieldNamed(const_id)
+                | Instruction::StoreFieldNamed(const_id) => {
+                    if !matches!(
+                        module.constants.get(*const_id as usize),
+                        Some(Constant::String(_))
+                    ) {
+                        return Err(VerificationError::BadConstant { instruction: idx });
                     }
-                    Instruction::Jump(target) | Instruction::JumpIfFalse(target) => {
-                        if *target >= function.instructions.len() {
-                            return Err(VerificationError::BadJump { instruction: idx });
-                        }
+                }
+                Instruction::Jump(target) | Instruction::JumpIfFalse(target) => {
+                    if *target >= function.instructions.len() {
+                        return Err(VerificationError::BadJump { instruction: idx });
                     }
-                    Instruction::Add
-                    | Instruction::Sub
-                    | Instruction::Mul
-                    | Instruction::Div
-                    | Instruction::Neg
-                    | Instruction::Not
-                    | Instruction::Eq
-                    | Instruction::Ne
-                    | Instruction::Lt
-                    | Instruction::Le
-                    | Instruction::Gt
-                    | Instruction::Ge
-                    | Instruction::Return
-                    | Instruction::Pop => {}
                 }
+                Instruction::Add
+                | Instruction::Sub
+                | Instruction::Mul
+                | Instruction::Div
+                | Instruction::Mod
+                | Instruction::BitAnd
+                | Instruction::BitOr
+                | Instruction::BitXor
+                | Instruction::Shl
+                | Instruction::Shr
+                | Instruction::Neg
+                | Instruction::Not
+                | Instruction::BitNot
+                | Instruction::Eq
+                | Instruction::Ne
+                | Instruction::Lt
+                | Instruction::Le
+                | Instruction::Gt
+                | Instruction::Ge
+                | Instruction::LoadField(_)
+                | Instruction::StoreField(_)
+                | Instruction::LoadVariantField(_)
+                | Instruction::MakeList(_)
+                | Instruction::MakeTuple(_)
+                | Instruction::UnpackTuple(_)
+                | Instruction::Index
+                | Instruction::StoreIndex
+                | Instruction::Len
+                | Instruction::CallValue(_)
+                | Instruction::Return
+                | Instruction::Pop => {}
             }
         }
         Ok(())
     }
+
+    /// Walks every reachable path through `function`, tracking the operand
+    /// stack height, and returns the deepest height seen. Assumes operands
+    /// have already passed [`Verifier::check_operands`].
+    fn check_stack(
+        &self,
+        module: &BytecodeModule,
+        function: &Function,
+    ) -> Result<u32, VerificationError> {
+        let code = &function.instructions;
+        if code.is_empty() {
+            return Err(VerificationError::FallThrough { instruction: 0 });
+        }
+        let mut heights: Vec<Option<usize>> = vec![None; code.len()];
+        heights[0] = Some(0);
+        let mut pending = vec![0];
+        let mut max = 0;
+        while let Some(idx) = pending.pop() {
+            let height = heights[idx].unwrap_or_default();
+            let (pops, pushes) = stack_effect(module, &code[idx]);
+            if height < pops {
+                return Err(VerificationError::StackUnderflow { instruction: idx });
+            }
+            if matches!(code[idx], Instruction::Return) && height != 1 {
+                return Err(VerificationError::ReturnHeight {
+                    instruction: idx,
+                    found: height,
+                });
+            }
+            let after = height - pops + pushes;
+            max = max.max(after);
+            let successors = match code[idx] {
+                Instruction::Jump(target) => [Some(target), None],
+                Instruction::JumpIfFalse(target) => [Some(target), Some(idx + 1)],
+                Instruction::Return => [None, None],
+                _ => [Some(idx + 1), None],
+            };
+            for next in successors.into_iter().flatten() {
+                if next == code.len() {
+                    return Err(VerificationError::FallThrough { instruction: idx });
+                }
+                match heights[next] {
+                    None => {
+                        heights[next] = Some(after);
+                        pending.push(next);
+                    }
+                    Some(expected) if expected != after => {
+                        return Err(VerificationError::StackMismatch {
+                            instruction: next,
+                            expected,
+                            found: after,
+                        });
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        Ok(u32::try_from(max).unwrap_or(u32::MAX))
+    }
+}
+
+/// Number of values an instruction pops and pushes.
+pub fn stack_effect(module: &BytecodeModule, instruction: &Instruction) -> (usize, usize) {
+    match *instruction {
+        Instruction::LoadConst(_) | Instruction::LoadLocal(_) | Instruction::LoadGlobal(_) => {
+            (0, 1)
+        }
+        Instruction::StoreLocal(_)
+        | Instruction::StoreGlobal(_)
+        | Instruction::JumpIfFalse(_)
+        | Instruction::Return
+        | Instruction::Pop => (1, 0),
+        Instruction::Jump(_) => (0, 0),
+        Instruction::Add
+        | Instruction::Sub
+        | Instruction::Mul
+        | Instruction::Div
+        | Instruction::Mod
+        | Instruction::BitAnd
+        | Instruction::BitOr
+        | Instruction::BitXor
+        | Instruction::Shl
+        | Instruction::Shr
+        | Instruction::Eq
+        | Instruction::Ne
+        | Instruction::Lt
+        | Instruction::Le
+        | Instruction::Gt
+        | Instruction::Ge
+        | Instruction::Index => (2, 1),
+        Instruction::Neg
+        | Instruction::Not
+        | Instruction::BitNot
+        | Instruction::LoadField(_)
+        | Instruction::LoadFieldNamed(_)
+        | Instruction::TestVariant(_, _)
+        | Instruction::LoadVariantField(_)
+        | Instruction::Len => (1, 1),
+        Instruction::StoreField(_) | Instruction::StoreFieldNamed(_) => (2, 0),
+        Instruction::StoreIndex => (3, 0),
+        Instruction::Call(_, count)
+        | Instruction::CallHost(_, count)
+        | Instruction::CallHostDynamic(_, count)
+        | Instruction::MakeClosure(_, count)
+        | Instruction::MakeList(count)
+        | Instruction::MakeTuple(count) => (count as usize, 1),
+        Instruction::CallMethod(_, count) | Instruction::CallValue(count) => {
+            (count as usize + 1, 1)
+        }
+        Instruction::UnpackTuple(count) => (1, count as usize),
+        Instruction::MakeStruct(layout) => match module.constants.get(layout as usize) {
+            Some(Constant::Struct(layout)) => (layout.fields.len(), 1),
+            _ => (0, 1),
+        },
+        Instruction::MakeVariant(layout, tag) => match module.constants.get(layout as usize) {
+            Some(Constant::Enum(layout)) => (
+                layout
+                    .variants
+                    .get(tag as usize)
+                    .map_or(0, |variant| variant.arity as usize),
+                1,
+            ),
+            _ => (0, 1),
+        },
+    }
 }
 
 #[cfg(test)]
@@ -209,5 +601,184 @@ mod tests {
         assert_eq!(module.constants, decoded.constants);
         assert_eq!(module.globals.len(), decoded.globals.len());
         assert_eq!(module.functions.len(), decoded.functions.len());
+
+        let json = module.to_json().expect("to_json");
+        let decoded = BytecodeModule::from_json(&json).expect("from_json");
+        assert_eq!(module.constants, decoded.constants);
+    }
+
+    #[test]
+    fn verify_rejects_struct_from_non_layout_constant() {
+        let mut module = BytecodeModule::new();
+        let name = module.add_constant(Constant::String("Point".into()));
+        module.add_function(Function::new(
+            "main",
+            0,
+            0,
+            vec![Instruction::MakeStruct(name), Instruction::Return],
+        ));
+        assert_eq!(
+            module.verify(),
+            Err(VerificationError::BadConstant { instruction: 0 })
+        );
+    }
+
+    #[test]
+    fn verify_rejects_out_of_range_global() {
+        let mut module = BytecodeModule::new();
+        let unit = module.add_constant(Constant::Unit);
+        let answer = module.add_global("ANSWER", unit);
+        module.add_function(Function::new(
+            "main",
+            0,
+            0,
+            vec![
+                Instruction::LoadGlobal(answer),
+                Instruction::StoreGlobal(answer + 1),
+                Instruction::Return,
+            ],
+        ));
+        assert_eq!(
+            module.verify(),
+            Err(VerificationError::BadGlobal { instruction: 1 })
+        );
+    }
+
+    #[test]
+    fn verify_rejects_out_of_range_variant_tag() {
+        let mut module = BytecodeModule::new();
+        let layout = module.add_constant(Constant::Enum(EnumLayout {
+            name: "Shape".into(),
+            variants: vec![VariantLayout {
+                name: "Empty".into(),
+                arity: 0,
+            }],
+        }));
+        module.add_function(Function::new(
+            "main",
+            0,
+            0,
+            vec![Instruction::MakeVariant(layout, 1), Instruction::Return],
+        ));
+        assert_eq!(
+            module.verify(),
+            Err(VerificationError::BadVariant { instruction: 0 })
+        );
+    }
+
+    fn single_function(params: u16, instructions: Vec<Instruction>) -> BytecodeModule {
+        let mut module = BytecodeModule::new();
+        module.add_constant(Constant::Int(1));
+        module.add_function(Function::new("main", params, params, instructions));
+        module
+    }
+
+    #[test]
+    fn verify_records_max_stack_depth() {
+        let mut module = single_function(
+            1,
+            vec![
+                Instruction::LoadLocal(0),
+                Instruction::JumpIfFalse(7),
+                Instruction::LoadConst(0),
+                Instruction::LoadConst(0),
+                Instruction::LoadConst(0),
+                Instruction::MakeTuple(3),
+                Instruction::Pop,
+                Instruction::LoadConst(0),
+                Instruction::Return,
+            ],
+        );
+        module.verify().expect("verify");
+        assert_eq!(module.functions[0].max_stack, 3);
+    }
+
+    #[test]
+    fn verify_rejects_operands_left_under_return_value() {
+        let mut module = single_function(
+            0,
+            vec![
+                Instruction::LoadConst(0),
+                Instruction::LoadConst(0),
+                Instruction::Return,
+            ],
+        );
+        assert_eq!(
+            module.verify(),
+            Err(VerificationError::ReturnHeight {
+                instruction: 2,
+                found: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn verify_rejects_stack_underflow() {
+        let mut module = single_function(0, vec![Instruction::Add, Instruction::Return]);
+        assert_eq!(
+            module.verify(),
+            Err(VerificationError::StackUnderflow { instruction: 0 })
+        );
+    }
+
+    #[test]
+    fn verify_rejects_mismatched_heights_at_join() {
+        let mut module = single_function(
+            1,
+            vec![
+                Instruction::LoadLocal(0),
+                Instruction::JumpIfFalse(3),
+                Instruction::LoadConst(0),
+                Instruction::LoadConst(0),
+                Instruction::Return,
+            ],
+        );
+        assert_eq!(
+            module.verify(),
+            Err(VerificationError::StackMismatch {
+                instruction: 3,
+                expected: 0,
+                found: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn verify_rejects_call_arity_mismatch() {
+        let mut module = single_function(
+            1,
+            vec![
+                Instruction::LoadConst(0),
+                Instruction::LoadConst(0),
+                Instruction::Call(0, 2),
+                Instruction::Return,
+            ],
+        );
+        assert_eq!(
+            module.verify(),
+            Err(VerificationError::CallArity {
+                instruction: 2,
+                expected: 1,
+                found: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn verify_rejects_reachable_fall_through() {
+        let mut module = single_function(
+            1,
+            vec![
+                Instruction::LoadLocal(0),
+                Instruction::JumpIfFalse(3),
+                Instruction::Jump(3),
+                Instruction::LoadConst(0),
+                Instruction::Pop,
+            ],
+        );
+        assert_eq!(
+            module.verify(),
+            Err(VerificationError::FallThrough { instruction: 4 })
+        );
     }
 }
@@ -1,14 +1,17 @@
+mod manifest;
+
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Context, Result};
 use clap::{Parser, Subcommand};
 use kayton_api::KayValueKind;
 use kayton_emitter_bc::emit;
-use kayton_front::parse_to_hir;
+use kayton_front::parse_program;
 use kayton_front::{diagnostics::Diagnostic, source::SourceMap};
-use kayton_host::KayHost;
-use kayton_sema::fast::analyze;
+use kayton_host::{load_plugin, KayHost};
+use kayton_sema::fast::{analyze, stdlib_builtins};
 use kayton_vm::{run_module, Value, VmError};
+use manifest::Project;
 
 #[derive(Parser)]
 #[command(name = "kayton", author, version, about = "Kayton language CLI")]
@@ -20,28 +23,80 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Parse, type-check, emit bytecode, and run a program
-    Run { file: PathBuf },
+    Run {
+        /// Program to run; defaults to the entry module of the nearest `kayton.toml`
+        file: Option<PathBuf>,
+        /// Function to call instead of `main`
+        #[arg(long)]
+        function: Option<String>,
+    },
+}
+
+struct RunConfig {
+    entry: PathBuf,
+    source_roots: Vec<PathBuf>,
+    plugins: Vec<PathBuf>,
+    function: String,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.command {
-        Commands::Run { file } => run_program(file),
+        Commands::Run { file, function } => {
+            let mut config = match file {
+                Some(entry) => RunConfig {
+                    entry,
+                    source_roots: Vec::new(),
+                    plugins: Vec::new(),
+                    function: "main".to_string(),
+                },
+                None => {
+                    let cwd =
+                        std::env::current_dir().context("failed to read current directory")?;
+                    let project = Project::discover(&cwd)?;
+                    RunConfig {
+                        entry: project.entry(),
+                        source_roots: project.source_roots(),
+                        plugins: project.plugins(),
+                        function: project
+                            .manifest
+                            .run
+                            .function
+                            .clone()
+                            .unwrap_or_else(|| "main".to_string()),
+                    }
+                }
+            };
+            if let Some(function) = function {
+                config.function = function;
+            }
+            run_program(config)
+        }
     }
 }
 
-fn run_program(path: PathBuf) -> Result<()> {
-    let parse = parse_to_hir(&path)?;
+fn run_program(config: RunConfig) -> Result<()> {
+    let parse = parse_program(&config.entry, &config.source_roots)?;
     report_diagnostics(&parse.diagnostics, &parse.source_map)?;
 
-    let analysis = analyze(&parse.module);
-    report_diagnostics(&analysis.diagnostics, &parse.source_map)?;
-
-    let bytecode = emit(&parse.module, &analysis).context("failed to emit bytecode")?;
     let host = KayHost::new();
     host.register_extensions(kayton_stdlib::extensions())
         .map_err(|err| anyhow!(format!("failed to register stdlib: {err:?}")))?;
-    let value = run_module(&bytecode, "main", &host).map_err(map_vm_error)?;
+    for plugin in &config.plugins {
+        let extensions = load_plugin(plugin)?;
+        host.register_extensions(extensions).map_err(|err| {
+            anyhow!(format!(
+                "failed to register plugin `{}`: {err:?}",
+                plugin.display()
+            ))
+        })?;
+    }
+    let analysis = analyze(&parse.module, &host.extension_names(), &stdlib_builtins());
+    report_diagnostics(&analysis.diagnostics, &parse.source_map)?;
+
+    let bytecode =
+        emit(&parse.module, &analysis, &parse.source_map).context("failed to emit bytecode")?;
+    let value = run_module(&bytecode, &config.function, &host).map_err(map_vm_error)?;
     if !matches!(value, Value::Unit) {
         let rendered = format_value(&value)?;
         if !rendered.is_empty() {
@@ -83,18 +138,73 @@ fn report_diagnostics(diags: &[Diagnostic], source_map: &SourceMap) -> Result<()
 fn format_value(value: &Value) -> anyhow::Result<String> {
     let rendered = match value {
         Value::Int(v) => v.to_string(),
+        Value::Float(v) => format!("{v:?}"),
         Value::Bool(v) => v.to_string(),
         Value::Str(s) => s.to_string(),
         Value::Unit => String::new(),
+        Value::Struct(value) => {
+            let fields = value
+                .layout
+                .fields
+                .iter()
+                .zip(value.fields.borrow().iter())
+                .map(|(name, field)| Ok(format!("{name}: {}", format_value(field)?)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            format!("{} {{ {} }}", value.layout.name, fields.join(", "))
+        }
+        Value::Enum(value) => {
+            let name = format!("{}::{}", value.layout.name, value.variant_name());
+            if value.fields.is_empty() {
+                name
+            } else {
+                let fields = value
+                    .fields
+                    .iter()
+                    .map(format_value)
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                format!("{name}({})", fields.join(", "))
+            }
+        }
+        Value::List(items) => {
+            let items = items
+                .borrow()
+                .iter()
+                .map(format_value)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            format!("[{}]", items.join(", "))
+        }
+        Value::Map(entries) => {
+            let entries = entries
+                .borrow()
+                .iter()
+                .map(|(key, value)| Ok(format!("{}: {}", format_value(key)?, format_value(value)?)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            format!("{{{}}}", entries.join(", "))
+        }
+        Value::Tuple(items) => {
+            let items = items
+                .iter()
+                .map(format_value)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            if items.len() == 1 {
+                format!("({},)", items[0])
+            } else {
+                format!("({})", items.join(", "))
+            }
+        }
+        Value::Function(closure) => format!("<fn {}>", closure.name),
         Value::Handle(handle) => match handle
             .describe()
             .map_err(|err| anyhow!(format!("host error: {err:?}")))?
         {
             KayValueKind::Int(v) => v.to_string(),
+            KayValueKind::Float(v) => format!("{v:?}"),
             KayValueKind::Bool(v) => v.to_string(),
             KayValueKind::Unit => String::new(),
             KayValueKind::String(data) => data.to_string(),
             KayValueKind::Bytes(data) => format!("bytes[{}]", data.len()),
+            KayValueKind::List(items) => format!("list[{}]", items.len()),
+            KayValueKind::Map(entries) => format!("map[{}]", entries.len()),
             KayValueKind::Capsule { tag } => format!("<capsule {tag}>", tag = tag),
         },
     };
@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+pub const MANIFEST_FILE: &str = "kayton.toml";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub package: Package,
+    #[serde(default)]
+    pub run: RunOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Package {
+    /// Entry module, relative to the manifest directory.
+    #[serde(default = "default_entry")]
+    pub entry: PathBuf,
+    /// Extra directories searched for `use`d modules.
+    #[serde(default)]
+    pub source_roots: Vec<PathBuf>,
+    /// Native extension libraries loaded into the host before running.
+    #[serde(default)]
+    pub plugins: Vec<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RunOptions {
+    /// Function called after the program is loaded; `main` if unset.
+    pub function: Option<String>,
+}
+
+fn default_entry() -> PathBuf {
+    PathBuf::from("src/main.ktn")
+}
+
+/// A parsed manifest together with the directory it was found in.
+#[derive(Debug)]
+pub struct Project {
+    pub root: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Project {
+    /// Finds the nearest `kayton.toml` in `start` or one of its ancestors.
+    pub fn discover(start: &Path) -> Result<Project> {
+        let found = start
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST_FILE))
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                anyhow!(
+                    "could not find `{MANIFEST_FILE}` in `{}` or any parent directory",
+                    start.display()
+                )
+            })?;
+        Project::load(&found)
+    }
+
+    pub fn load(path: &Path) -> Result<Project> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        let manifest: Manifest = toml::from_str(&text)
+            .with_context(|| format!("invalid manifest `{}`", path.display()))?;
+        let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(Project { root, manifest })
+    }
+
+    pub fn entry(&self) -> PathBuf {
+        self.root.join(&self.manifest.package.entry)
+    }
+
+    pub fn source_roots(&self) -> Vec<PathBuf> {
+        self.manifest
+            .package
+            .source_roots
+            .iter()
+            .map(|dir| self.root.join(dir))
+            .collect()
+    }
+
+    pub fn plugins(&self) -> Vec<PathBuf> {
+        self.manifest
+            .package
+            .plugins
+            .iter()
+            .map(|path| self.root.join(path))
+            .collect()
+    }
+}
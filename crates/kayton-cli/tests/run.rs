@@ -1,5 +1,8 @@
 use assert_cmd::prelude::*;
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
 use std::io::Write;
+use std::path::PathBuf;
 use std::process::Command;
 use tempfile::NamedTempFile;
 
@@ -19,3 +22,121 @@ fn run_command_executes_program() {
         .success()
         .stdout("5\n");
 }
+
+#[test]
+fn run_command_loads_sibling_modules() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    fs::write(
+        dir.path().join("main.ktn"),
+        "use util\nuse util::triple as t\n\nfn main():\n    util::double(2) + t(3)\n",
+    )
+    .expect("write main");
+    fs::write(
+        dir.path().join("util.ktn"),
+        "pub fn double(x):\n    add(x, x)\n\npub fn triple(x):\n    add(x, double(x))\n\nfn add(a, b):\n    a + b\n",
+    )
+    .expect("write util");
+
+    let mut cmd = Command::cargo_bin("kayton-cli").expect("binary");
+    cmd.arg("run")
+        .arg(dir.path().join("main.ktn"))
+        .assert()
+        .success()
+        .stdout("13\n");
+}
+
+#[test]
+fn run_command_uses_nearest_manifest() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let root = dir.path();
+    fs::create_dir_all(root.join("src/nested")).expect("create src");
+    fs::create_dir_all(root.join("lib")).expect("create lib");
+    fs::write(
+        root.join("kayton.toml"),
+        "[package]\nentry = \"src/app.ktn\"\nsource-roots = [\"lib\"]\n\n[run]\nfunction = \"start\"\n",
+    )
+    .expect("write manifest");
+    fs::write(
+        root.join("src/app.ktn"),
+        "use util\n\nfn start():\n    util::square(7)\n\nfn main():\n    0\n",
+    )
+    .expect("write entry");
+    fs::write(root.join("lib/util.ktn"), "pub fn square(x):\n    x * x\n").expect("write util");
+
+    let mut cmd = Command::cargo_bin("kayton-cli").expect("binary");
+    cmd.arg("run")
+        .current_dir(root.join("src/nested"))
+        .assert()
+        .success()
+        .stdout("49\n");
+
+    let mut cmd = Command::cargo_bin("kayton-cli").expect("binary");
+    cmd.args(["run", "--function", "main"])
+        .current_dir(root)
+        .assert()
+        .success()
+        .stdout("0\n");
+}
+
+/// The sample plugin library; cargo builds it next to this test binary as a
+/// dev-dependency.
+fn sample_plugin() -> PathBuf {
+    let exe = std::env::current_exe().expect("test binary");
+    let deps = exe.parent().expect("deps directory");
+    deps.join(format!("{DLL_PREFIX}kayton_plugin_sample{DLL_SUFFIX}"))
+}
+
+#[test]
+fn run_command_loads_manifest_plugins() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let root = dir.path();
+    fs::write(
+        root.join("kayton.toml"),
+        format!(
+            "[package]\nentry = \"main.ktn\"\nplugins = [{:?}]\n",
+            sample_plugin()
+        ),
+    )
+    .expect("write manifest");
+    fs::write(root.join("main.ktn"), "fn main():\n    triple(14)\n").expect("write entry");
+
+    let mut cmd = Command::cargo_bin("kayton-cli").expect("binary");
+    cmd.arg("run")
+        .current_dir(root)
+        .assert()
+        .success()
+        .stdout("42\n");
+}
+
+#[test]
+fn run_command_without_manifest_reports_error() {
+    let dir = tempfile::tempdir().expect("temp dir");
+
+    let mut cmd = Command::cargo_bin("kayton-cli").expect("binary");
+    let output = cmd
+        .arg("run")
+        .current_dir(dir.path())
+        .output()
+        .expect("run binary");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("could not find `kayton.toml`"), "{stderr}");
+}
+
+#[test]
+fn run_command_reports_runtime_error_location() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let path = dir.path().join("main.ktn");
+    fs::write(&path, "fn main():\n    let zero = 0\n    10 / zero\n").expect("write main");
+
+    let output = Command::cargo_bin("kayton-cli")
+        .expect("binary")
+        .arg("run")
+        .arg(&path)
+        .output()
+        .expect("run");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let expected = format!("division by zero ({}:3:5)", path.display());
+    assert!(stderr.contains(&expected), "{stderr}");
+}
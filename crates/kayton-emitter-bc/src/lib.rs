@@ -1,9 +1,14 @@
 use std::collections::HashMap;
 
-use kayton_bytecode::{BytecodeModule, Constant, Function, FunctionId, Instruction};
+use kayton_bytecode::{
+    stack_effect, BytecodeModule, ConstId, Constant, DebugInfo, EnumLayout, Function, FunctionId,
+    GlobalId, Instruction, LineEntry, LocalVariable, SourceSpan, StructLayout, VariantLayout,
+    VerificationError,
+};
 use kayton_front::hir::*;
 use kayton_front::interner::Symbol;
-use kayton_front::span::Span;
+use kayton_front::source::SourceMap;
+use kayton_front::span::{SourceId, Span};
 use kayton_sema::fast::FastAnalysis;
 use thiserror::Error;
 
@@ -11,86 +16,290 @@ use thiserror::Error;
 pub enum EmitterError {
     #[error("unknown name in expression")]
     UnknownName { span: Span },
-    #[error("global binding must be a literal for now")]
-    UnsupportedGlobal { span: Span },
-    #[error("unsupported call target")]
-    UnsupportedCallee { span: Span },
-    #[error("invalid integer literal")]
-    InvalidInteger { span: Span },
+    #[error("invalid float literal")]
+    InvalidFloat { span: Span },
+    #[error("range expressions are only supported as for loop iterables")]
+    UnsupportedRange { span: Span },
+    #[error("loop control outside of a loop")]
+    LoopControlOutsideLoop { span: Span },
+    #[error("missing field in struct literal")]
+    MissingField { span: Span },
+    #[error("unknown enum variant")]
+    UnknownVariant { span: Span },
+    #[error("wrong number of fields for enum variant")]
+    VariantArity { span: Span },
+    #[error("refutable pattern in let binding")]
+    RefutablePattern { span: Span },
+    #[error("emitted bytecode failed verification: {0}")]
+    Verification(#[from] VerificationError),
 }
 
-pub fn emit(module: &HirModule, analysis: &FastAnalysis) -> Result<BytecodeModule, EmitterError> {
-    let mut emitter = Emitter::new(module, analysis);
+/// Emits bytecode for `module`, with debug info resolved against `sources`.
+pub fn emit(
+    module: &HirModule,
+    analysis: &FastAnalysis,
+    sources: &SourceMap,
+) -> Result<BytecodeModule, EmitterError> {
+    let mut emitter = Emitter::new(module, analysis, sources);
+    emitter.collect_structs();
+    emitter.collect_enums();
     emitter.collect_functions();
+    emitter.collect_globals();
     emitter.emit_items()?;
-    Ok(emitter.finish())
+    let mut bytecode = emitter.finish();
+    bytecode.verify()?;
+    Ok(bytecode)
 }
 
 struct Emitter<'a> {
     module: &'a HirModule,
-    _analysis: &'a FastAnalysis,
+    analysis: &'a FastAnalysis,
+    sources: &'a SourceMap,
+    files: HashMap<SourceId, u32>,
     bytecode: BytecodeModule,
     function_indices: HashMap<Symbol, FunctionId>,
+    global_indices: HashMap<Symbol, GlobalId>,
+    method_indices: HashMap<String, FunctionId>,
+    next_function: FunctionId,
+    lambdas: Vec<(FunctionId, Function)>,
+    structs: HashMap<Symbol, StructEntry>,
+    enums: HashMap<Symbol, EnumEntry>,
     unit_const: u32,
 }
 
+#[derive(Clone)]
+struct StructEntry {
+    layout: ConstId,
+    fields: Vec<Symbol>,
+}
+
+#[derive(Clone)]
+struct EnumEntry {
+    layout: ConstId,
+    variants: Vec<(Symbol, usize)>,
+}
+
 impl<'a> Emitter<'a> {
-    fn new(module: &'a HirModule, analysis: &'a FastAnalysis) -> Self {
+    fn new(module: &'a HirModule, analysis: &'a FastAnalysis, sources: &'a SourceMap) -> Self {
         let mut bytecode = BytecodeModule::new();
         let unit_const = bytecode.add_constant(Constant::Unit);
         Self {
             module,
-            _analysis: analysis,
+            analysis,
+            sources,
+            files: HashMap::new(),
             bytecode,
             function_indices: HashMap::new(),
+            global_indices: HashMap::new(),
+            method_indices: HashMap::new(),
+            next_function: 0,
+            lambdas: Vec::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
             unit_const,
         }
     }
 
+    fn collect_structs(&mut self) {
+        for item in &self.module.items {
+            if let HirItem::Struct(decl) = item {
+                let layout = StructLayout {
+                    name: self.symbol_name(decl.name),
+                    fields: decl
+                        .fields
+                        .iter()
+                        .map(|field| self.symbol_name(field.name))
+                        .collect(),
+                };
+                let layout = self.add_constant(Constant::Struct(layout));
+                let fields = decl.fields.iter().map(|field| field.name).collect();
+                self.structs
+                    .insert(decl.name, StructEntry { layout, fields });
+            }
+        }
+    }
+
+    fn collect_enums(&mut self) {
+        for item in &self.module.items {
+            if let HirItem::Enum(decl) = item {
+                let layout = EnumLayout {
+                    name: self.symbol_name(decl.name),
+                    variants: decl
+                        .variants
+                        .iter()
+                        .map(|variant| VariantLayout {
+                            name: self.symbol_name(variant.name),
+                            arity: variant.fields.len() as u16,
+                        })
+                        .collect(),
+                };
+                let layout = self.add_constant(Constant::Enum(layout));
+                let variants = decl
+                    .variants
+                    .iter()
+                    .map(|variant| (variant.name, variant.fields.len()))
+                    .collect();
+                self.enums.insert(decl.name, EnumEntry { layout, variants });
+            }
+        }
+    }
+
+    fn resolve_variant(&self, path: &HirPath) -> Result<(ConstId, u16, usize), EmitterError> {
+        let unknown = EmitterError::UnknownVariant { span: path.span };
+        let [enum_ref, variant_ref] = path.segments.as_slice() else {
+            return Err(unknown);
+        };
+        let entry = self.enums.get(&enum_ref.name).ok_or(unknown)?;
+        entry
+            .variants
+            .iter()
+            .position(|(name, _)| *name == variant_ref.name)
+            .map(|tag| (entry.layout, tag as u16, entry.variants[tag].1))
+            .ok_or(EmitterError::UnknownVariant { span: path.span })
+    }
+
+    fn symbol_name(&self, symbol: Symbol) -> String {
+        self.module
+            .interner
+            .resolve(symbol)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("#{}", symbol.raw()))
+    }
+
     fn collect_functions(&mut self) {
         let mut next = 0u32;
         for item in &self.module.items {
-            if let HirItem::Function(func) = item {
-                self.function_indices.insert(func.name, next);
-                next += 1;
+            match item {
+                HirItem::Function(func) => {
+                    self.function_indices.insert(func.name, next);
+                    next += 1;
+                }
+                HirItem::Impl(block) => {
+                    for method in &block.methods {
+                        let name = self.method_name(block.ty.name, method.name);
+                        self.method_indices.insert(name, next);
+                        next += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.next_function = next;
+    }
+
+    /// Declares every binding of a top-level `let`. Literal initializers
+    /// become the global's initial value; the rest are left for the module
+    /// initializer.
+    fn collect_globals(&mut self) {
+        for item in &self.module.items {
+            let HirItem::Let(binding) = item else {
+                continue;
+            };
+            let initial = match &binding.pattern {
+                HirPattern::Binding(_) => self.fold_constant(&binding.value),
+                _ => None,
+            };
+            let value = match initial {
+                Some(constant) => self.add_constant(constant),
+                None => self.unit_const,
+            };
+            let mut names = Vec::new();
+            pattern_bindings(&binding.pattern, &mut names);
+            for name in names {
+                let global = self.bytecode.add_global(self.symbol_name(name), value);
+                self.global_indices.insert(name, global);
             }
         }
     }
 
     fn emit_items(&mut self) -> Result<(), EmitterError> {
+        let mut initializers = Vec::new();
         for item in &self.module.items {
             match item {
-                HirItem::Let(binding) => self.emit_global(binding)?,
+                HirItem::Let(binding) => {
+                    if !matches!(binding.pattern, HirPattern::Binding(_))
+                        || self.fold_constant(&binding.value).is_none()
+                    {
+                        initializers.push(binding);
+                    }
+                }
                 HirItem::Function(func) => {
-                    let function = self.emit_function(func)?;
+                    let function = self.emit_function(func, None)?;
                     self.bytecode.add_function(function);
                 }
+                HirItem::Impl(block) => {
+                    for method in &block.methods {
+                        let function = self.emit_function(method, Some(block.ty.name))?;
+                        self.bytecode.add_function(function);
+                    }
+                }
+                HirItem::Struct(_) | HirItem::Enum(_) => {}
             }
         }
+        if !initializers.is_empty() {
+            self.emit_initializer(&initializers)?;
+        }
         Ok(())
     }
 
-    fn emit_global(&mut self, binding: &HirLetBinding) -> Result<(), EmitterError> {
-        if let Some(constant) = self.fold_constant(&binding.value) {
-            let const_id = self.add_constant(constant);
-            if let Some(name) = self.module.interner.resolve(binding.name) {
-                self.bytecode.add_global(name.to_string(), const_id);
-            }
-            Ok(())
-        } else {
-            Err(EmitterError::UnsupportedGlobal { span: binding.span })
+    fn emit_initializer(&mut self, bindings: &[&HirLetBinding]) -> Result<(), EmitterError> {
+        let func_id = self.reserve_function();
+        let mut builder = FunctionBuilder::new(self, "<init>".to_string(), &[]);
+        for binding in bindings {
+            builder.emit_global_let(binding)?;
         }
+        builder.push_unit();
+        builder.instructions.push(Instruction::Return);
+        let function = builder.finish();
+        self.lambdas.push((func_id, function));
+        self.bytecode.initializer = Some(func_id);
+        Ok(())
     }
 
-    fn emit_function(&mut self, func: &HirFunction) -> Result<Function, EmitterError> {
-        let mut builder = FunctionBuilder::new(self, func);
+    fn emit_function(
+        &mut self,
+        func: &HirFunction,
+        owner: Option<Symbol>,
+    ) -> Result<Function, EmitterError> {
+        let name = match owner {
+            Some(owner) => self.method_name(owner, func.name),
+            None => self
+                .module
+                .interner
+                .resolve(func.name)
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| format!("fn_{}", func.id.raw())),
+        };
+        let mut builder = FunctionBuilder::new(self, name, &func.params);
         builder.emit_block(&func.body, true)?;
-        if !builder.returned {
-            builder.instructions.push(Instruction::Return);
-        }
+        builder.instructions.push(Instruction::Return);
         Ok(builder.finish())
     }
 
+    fn debug_span(&mut self, span: Span) -> Option<SourceSpan> {
+        let source = self.sources.get(span.source)?;
+        let (line, column) = source.line_col(span.start as usize);
+        let file = *self.files.entry(span.source).or_insert_with(|| {
+            self.bytecode
+                .source_files
+                .push(source.path.display().to_string().into());
+            self.bytecode.source_files.len() as u32 - 1
+        });
+        Some(SourceSpan {
+            file,
+            start: span.start,
+            end: span.end,
+            line: line as u32,
+            column: column as u32,
+        })
+    }
+
+    fn add_import(&mut self, name: &str) {
+        if !self.bytecode.imports.iter().any(|import| import == name) {
+            self.bytecode.imports.push(name.into());
+        }
+    }
+
     fn add_constant(&mut self, constant: Constant) -> u32 {
         if constant == Constant::Unit {
             return self.unit_const;
@@ -112,10 +321,25 @@ impl<'a> Emitter<'a> {
         self.function_indices.get(&symbol).copied()
     }
 
+    fn global_index(&self, symbol: Symbol) -> Option<GlobalId> {
+        self.global_indices.get(&symbol).copied()
+    }
+
+    fn reserve_function(&mut self) -> FunctionId {
+        let id = self.next_function;
+        self.next_function += 1;
+        id
+    }
+
+    fn method_name(&self, owner: Symbol, method: Symbol) -> String {
+        format!("{}::{}", self.symbol_name(owner), self.symbol_name(method))
+    }
+
     fn fold_constant(&self, expr: &HirExpr) -> Option<Constant> {
         match expr {
             HirExpr::Literal(lit) => match lit {
-                HirLiteral::Int(int) => int.value.parse::<i64>().ok().map(Constant::Int),
+                HirLiteral::Int(int) => Some(Constant::Int(int.value)),
+                HirLiteral::Float(float) => parse_float(&float.value).map(Constant::Float),
                 HirLiteral::String(string) => Some(Constant::String(string.value.clone())),
                 HirLiteral::Bool(boolean) => Some(Constant::Bool(boolean.value)),
                 HirLiteral::Unit(_) => Some(Constant::Unit),
@@ -124,56 +348,109 @@ impl<'a> Emitter<'a> {
         }
     }
 
-    fn finish(self) -> BytecodeModule {
+    fn finish(mut self) -> BytecodeModule {
+        self.lambdas.sort_by_key(|(id, _)| *id);
+        for (_, function) in self.lambdas {
+            self.bytecode.add_function(function);
+        }
         self.bytecode
     }
 }
 
+#[derive(Default)]
+struct LoopContext {
+    /// Operand stack depth on entry to the loop body.
+    depth: usize,
+    break_jumps: Vec<usize>,
+    continue_jumps: Vec<usize>,
+}
+
 struct FunctionBuilder<'a, 'b> {
     emitter: &'a mut Emitter<'b>,
-    function: &'a HirFunction,
+    name: String,
+    params: u16,
     instructions: Vec<Instruction>,
     scopes: Vec<HashMap<Symbol, u16>>,
     next_local: u16,
     max_local: u16,
-    returned: bool,
+    loops: Vec<LoopContext>,
+    /// Span of the innermost statement or expression being emitted.
+    span: Option<Span>,
+    lines: Vec<LineEntry>,
+    locals: Vec<LocalVariable>,
+    /// Index into `locals` where each open scope starts.
+    scope_starts: Vec<usize>,
+    /// Operand stack depth after the instructions emitted so far.
+    depth: usize,
+    /// Stack depth at each forward jump, restored when the jump is patched.
+    jump_depths: HashMap<usize, usize>,
 }
 
 impl<'a, 'b> FunctionBuilder<'a, 'b> {
-    fn new(emitter: &'a mut Emitter<'b>, function: &'a HirFunction) -> Self {
+    fn new(emitter: &'a mut Emitter<'b>, name: String, params: &[HirParam]) -> Self {
         let mut builder = Self {
             emitter,
-            function,
+            name,
+            params: params.len() as u16,
             instructions: Vec::new(),
             scopes: Vec::new(),
             next_local: 0,
             max_local: 0,
-            returned: false,
+            loops: Vec::new(),
+            span: None,
+            lines: Vec::new(),
+            locals: Vec::new(),
+            scope_starts: Vec::new(),
+            depth: 0,
+            jump_depths: HashMap::new(),
         };
         builder.push_scope();
-        for param in &function.params {
+        for param in params {
             let slot = builder.alloc_local(param.name);
             builder.ensure_slot(slot);
         }
         builder
     }
 
-    fn finish(self) -> Function {
-        Function::new(
-            self.function_name(),
-            self.function.params.len() as u16,
-            self.max_local,
-            self.instructions,
-        )
+    fn finish(mut self) -> Function {
+        while !self.scopes.is_empty() {
+            self.pop_scope();
+        }
+        Function {
+            debug: DebugInfo {
+                lines: self.lines,
+                locals: self.locals,
+            },
+            ..Function::new(self.name, self.params, self.max_local, self.instructions)
+        }
     }
 
-    fn function_name(&self) -> String {
-        self.emitter
-            .module
-            .interner
-            .resolve(self.function.name)
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| format!("fn_{}", self.function.id.raw()))
+    /// Starts a line table entry for the current span at the next instruction.
+    fn mark_span(&mut self) {
+        let Some(span) = self.span.and_then(|span| self.emitter.debug_span(span)) else {
+            return;
+        };
+        let instruction = self.instructions.len() as u32;
+        if self
+            .lines
+            .last()
+            .is_some_and(|last| last.instruction == instruction)
+        {
+            self.lines.pop();
+        }
+        if self.lines.last().is_some_and(|last| last.span == span) {
+            return;
+        }
+        self.lines.push(LineEntry { instruction, span });
+    }
+
+    fn with_span<T>(&mut self, span: Span, emit: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = self.span.replace(span);
+        self.mark_span();
+        let result = emit(self);
+        self.span = outer;
+        self.mark_span();
+        result
     }
 
     fn ensure_slot(&mut self, slot: u16) {
@@ -187,19 +464,36 @@ impl<'a, 'b> FunctionBuilder<'a, 'b> {
 
     fn push_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.scope_starts.push(self.locals.len());
     }
 
     fn pop_scope(&mut self) {
         self.scopes.pop();
+        let start = self.scope_starts.pop().unwrap_or_default();
+        let end = self.instructions.len() as u32;
+        for local in &mut self.locals[start..] {
+            local.end = local.end.min(end);
+        }
     }
 
     fn alloc_local(&mut self, name: Symbol) -> u16 {
-        let slot = self.next_local;
-        self.next_local += 1;
-        self.ensure_slot(slot);
+        let slot = self.alloc_temp();
         if let Some(scope) = self.scopes.last_mut() {
             scope.insert(name, slot);
         }
+        self.locals.push(LocalVariable {
+            name: self.emitter.symbol_name(name).into(),
+            slot,
+            start: self.instructions.len() as u32,
+            end: u32::MAX,
+        });
+        slot
+    }
+
+    fn alloc_temp(&mut self) -> u16 {
+        let slot = self.next_local;
+        self.next_local += 1;
+        self.ensure_slot(slot);
         slot
     }
 
@@ -220,7 +514,7 @@ impl<'a, 'b> FunctionBuilder<'a, 'b> {
         if let Some(tail) = &block.tail {
             self.emit_expr(tail)?;
             if !produce_value {
-                self.instructions.push(Instruction::Pop);
+                self.push(Instruction::Pop);
             }
         } else if produce_value {
             self.push_unit();
@@ -230,61 +524,150 @@ impl<'a, 'b> FunctionBuilder<'a, 'b> {
     }
 
     fn emit_stmt(&mut self, stmt: &HirStmt) -> Result<(), EmitterError> {
+        self.with_span(stmt_span(stmt), |builder| builder.emit_stmt_kind(stmt))
+    }
+
+    fn emit_stmt_kind(&mut self, stmt: &HirStmt) -> Result<(), EmitterError> {
         match stmt {
             HirStmt::Let(binding) => {
                 self.emit_expr(&binding.value)?;
-                let slot = self.alloc_local(binding.name);
-                self.instructions.push(Instruction::StoreLocal(slot));
-            }
-            HirStmt::While(while_stmt) => {
-                let loop_start = self.instructions.len();
-                self.emit_expr(&while_stmt.condition)?;
-                let jump_out_pos = self.emit_jump_placeholder(true);
-                self.emit_block(&while_stmt.body, false)?;
-                self.instructions.push(Instruction::Jump(loop_start));
-                self.patch_jump(jump_out_pos, self.instructions.len());
+                match &binding.pattern {
+                    HirPattern::Binding(target) => {
+                        let slot = self.alloc_local(target.name);
+                        self.push(Instruction::StoreLocal(slot));
+                    }
+                    HirPattern::Wildcard(_) => self.push(Instruction::Pop),
+                    pattern => {
+                        let value = self.alloc_temp();
+                        self.push(Instruction::StoreLocal(value));
+                        self.declare_pattern_bindings(pattern);
+                        let mut fail_jumps = Vec::new();
+                        self.emit_pattern(pattern, value, &mut fail_jumps)?;
+                        if !fail_jumps.is_empty() {
+                            return Err(EmitterError::RefutablePattern { span: binding.span });
+                        }
+                    }
+                }
             }
+            HirStmt::Assign(assign) => match &assign.target {
+                HirAssignTarget::Name(target) => {
+                    let (load, store) = match self.lookup_local(target.name) {
+                        Some(slot) => (Instruction::LoadLocal(slot), Instruction::StoreLocal(slot)),
+                        None => {
+                            let global = self
+                                .emitter
+                                .global_index(target.name)
+                                .ok_or(EmitterError::UnknownName { span: target.span })?;
+                            (
+                                Instruction::LoadGlobal(global),
+                                Instruction::StoreGlobal(global),
+                            )
+                        }
+                    };
+                    if let Some(op) = assign.op {
+                        self.push(load);
+                        self.emit_expr(&assign.value)?;
+                        self.push(binary_instruction(op));
+                    } else {
+                        self.emit_expr(&assign.value)?;
+                    }
+                    self.push(store);
+                }
+                HirAssignTarget::Field(access) => {
+                    self.emit_expr(&access.base)?;
+                    if let Some(op) = assign.op {
+                        let base = self.alloc_temp();
+                        self.push(Instruction::StoreLocal(base));
+                        self.push(Instruction::LoadLocal(base));
+                        self.push(Instruction::LoadLocal(base));
+                        let load = self.field_instruction(access, false);
+                        self.push(load);
+                        self.emit_expr(&assign.value)?;
+                        self.push(binary_instruction(op));
+                    } else {
+                        self.emit_expr(&assign.value)?;
+                    }
+                    let store = self.field_instruction(access, true);
+                    self.push(store);
+                }
+                HirAssignTarget::Index(index) => {
+                    self.emit_expr(&index.base)?;
+                    self.emit_expr(&index.index)?;
+                    if let Some(op) = assign.op {
+                        let key = self.alloc_temp();
+                        self.push(Instruction::StoreLocal(key));
+                        let base = self.alloc_temp();
+                        self.push(Instruction::StoreLocal(base));
+                        self.push(Instruction::LoadLocal(base));
+                        self.push(Instruction::LoadLocal(key));
+                        self.push(Instruction::LoadLocal(base));
+                        self.push(Instruction::LoadLocal(key));
+                        self.push(Instruction::Index);
+                        self.emit_expr(&assign.value)?;
+                        self.push(binary_instruction(op));
+                    } else {
+                        self.emit_expr(&assign.value)?;
+                    }
+                    self.push(Instruction::StoreIndex);
+                }
+            },
             HirStmt::Return(ret) => {
+                let depth = self.depth;
+                self.pop_to(0);
                 if let Some(value) = &ret.value {
                     self.emit_expr(value)?;
                 } else {
                     self.push_unit();
                 }
-                self.instructions.push(Instruction::Return);
-                self.returned = true;
+                self.push(Instruction::Return);
+                self.depth = depth;
+            }
+            HirStmt::Break(brk) => {
+                let depth = self.depth;
+                let base = self.loop_depth(brk.span)?;
+                self.pop_to(base);
+                if let Some(value) = &brk.value {
+                    self.emit_expr(value)?;
+                } else {
+                    self.push_unit();
+                }
+                let jump = self.emit_jump_placeholder(false);
+                self.current_loop(brk.span)?.break_jumps.push(jump);
+                self.depth = depth;
+            }
+            HirStmt::Continue(cont) => {
+                let depth = self.depth;
+                let base = self.loop_depth(cont.span)?;
+                self.pop_to(base);
+                let jump = self.emit_jump_placeholder(false);
+                self.current_loop(cont.span)?.continue_jumps.push(jump);
+                self.depth = depth;
             }
             HirStmt::Expr(expr) => {
                 self.emit_expr(expr)?;
-                self.instructions.push(Instruction::Pop);
+                self.push(Instruction::Pop);
             }
         }
         Ok(())
     }
 
     fn emit_expr(&mut self, expr: &HirExpr) -> Result<(), EmitterError> {
+        self.with_span(expr_span(expr), |builder| builder.emit_expr_kind(expr))
+    }
+
+    fn emit_expr_kind(&mut self, expr: &HirExpr) -> Result<(), EmitterError> {
         match expr {
             HirExpr::Literal(lit) => {
-                let const_id = match lit {
-                    HirLiteral::Int(int) => {
-                        let value = int
-                            .value
-                            .parse::<i64>()
-                            .map_err(|_| EmitterError::InvalidInteger { span: int.span })?;
-                        self.emitter.add_constant(Constant::Int(value))
-                    }
-                    HirLiteral::String(string) => self
-                        .emitter
-                        .add_constant(Constant::String(string.value.clone())),
-                    HirLiteral::Bool(boolean) => {
-                        self.emitter.add_constant(Constant::Bool(boolean.value))
-                    }
-                    HirLiteral::Unit(_) => self.emitter.unit_const,
-                };
-                self.instructions.push(Instruction::LoadConst(const_id));
+                let const_id = self.literal_constant(lit)?;
+                self.push(Instruction::LoadConst(const_id));
             }
             HirExpr::Name(name) => {
                 if let Some(slot) = self.lookup_local(name.name) {
-                    self.instructions.push(Instruction::LoadLocal(slot));
+                    self.push(Instruction::LoadLocal(slot));
+                } else if let Some(global) = self.emitter.global_index(name.name) {
+                    self.push(Instruction::LoadGlobal(global));
+                } else if let Some(func_id) = self.emitter.function_index(name.name) {
+                    self.push(Instruction::MakeClosure(func_id, 0));
                 } else {
                     return Err(EmitterError::UnknownName { span: name.span });
                 }
@@ -292,19 +675,36 @@ impl<'a, 'b> FunctionBuilder<'a, 'b> {
             HirExpr::Call(call) => {
                 let callee = call.callee.as_ref();
                 let name = match callee {
-                    HirExpr::Name(name) => name,
+                    HirExpr::Name(name)
+                        if self.lookup_local(name.name).is_none()
+                            && self.emitter.global_index(name.name).is_none() =>
+                    {
+                        name
+                    }
+                    HirExpr::Path(path) => {
+                        if let Some(func_id) = self.static_method(call.id) {
+                            for arg in &call.args {
+                                self.emit_expr(arg)?;
+                            }
+                            self.push(Instruction::Call(func_id, call.args.len() as u16));
+                            return Ok(());
+                        }
+                        return self.emit_variant(path, &call.args);
+                    }
                     _ => {
-                        return Err(EmitterError::UnsupportedCallee {
-                            span: expr_span(callee),
-                        })
+                        self.emit_expr(callee)?;
+                        for arg in &call.args {
+                            self.emit_expr(arg)?;
+                        }
+                        self.push(Instruction::CallValue(call.args.len() as u16));
+                        return Ok(());
                     }
                 };
                 if let Some(func_id) = self.emitter.function_index(name.name) {
                     for arg in &call.args {
                         self.emit_expr(arg)?;
                     }
-                    self.instructions
-                        .push(Instruction::Call(func_id, call.args.len() as u16));
+                    self.push(Instruction::Call(func_id, call.args.len() as u16));
                 } else {
                     let symbol = self
                         .emitter
@@ -318,12 +718,27 @@ impl<'a, 'b> FunctionBuilder<'a, 'b> {
                     let const_id = self
                         .emitter
                         .add_constant(Constant::String(symbol.to_string()));
-                    self.instructions.push(Instruction::CallHostDynamic(
+                    self.emitter.add_import(symbol);
+                    self.push(Instruction::CallHostDynamic(
                         const_id,
                         call.args.len() as u16,
                     ));
                 }
             }
+            HirExpr::MethodCall(call) => {
+                self.emit_expr(&call.receiver)?;
+                for arg in &call.args {
+                    self.emit_expr(arg)?;
+                }
+                let arg_count = call.args.len() as u16;
+                if let Some(func_id) = self.static_method(call.id) {
+                    self.push(Instruction::Call(func_id, arg_count + 1));
+                } else {
+                    let name = self.emitter.symbol_name(call.method.name);
+                    let const_id = self.emitter.add_constant(Constant::String(name));
+                    self.push(Instruction::CallMethod(const_id, arg_count));
+                }
+            }
             HirExpr::If(if_expr) => {
                 self.emit_expr(&if_expr.condition)?;
                 let jump_false = self.emit_jump_placeholder(true);
@@ -338,53 +753,535 @@ impl<'a, 'b> FunctionBuilder<'a, 'b> {
                 }
                 self.patch_jump(jump_end, self.instructions.len());
             }
+            HirExpr::Match(match_expr) => self.emit_match(match_expr)?,
+            HirExpr::While(while_expr) => self.emit_while(while_expr)?,
+            HirExpr::For(for_expr) => self.emit_for(for_expr)?,
+            HirExpr::Range(range) => {
+                return Err(EmitterError::UnsupportedRange { span: range.span });
+            }
+            HirExpr::StructLit(lit) => self.emit_struct_literal(lit)?,
+            HirExpr::Field(access) => {
+                self.emit_expr(&access.base)?;
+                let load = self.field_instruction(access, false);
+                self.push(load);
+            }
+            HirExpr::List(list) => {
+                for element in &list.elements {
+                    self.emit_expr(element)?;
+                }
+                self.push(Instruction::MakeList(list.elements.len() as u16));
+            }
+            HirExpr::Tuple(tuple) => {
+                for element in &tuple.elements {
+                    self.emit_expr(element)?;
+                }
+                self.push(Instruction::MakeTuple(tuple.elements.len() as u16));
+            }
+            HirExpr::Index(index) => {
+                self.emit_expr(&index.base)?;
+                self.emit_expr(&index.index)?;
+                self.push(Instruction::Index);
+            }
+            HirExpr::Path(path) => self.emit_variant(path, &[])?,
+            HirExpr::Lambda(lambda) => self.emit_lambda(lambda)?,
             HirExpr::Block(block) => {
                 self.emit_block(block, true)?;
             }
-            HirExpr::Binary(bin) => {
-                self.emit_expr(&bin.lhs)?;
-                self.emit_expr(&bin.rhs)?;
-                let instr = match bin.op {
-                    HirBinaryOp::Add => Instruction::Add,
-                    HirBinaryOp::Sub => Instruction::Sub,
-                    HirBinaryOp::Mul => Instruction::Mul,
-                    HirBinaryOp::Div => Instruction::Div,
-                    HirBinaryOp::Eq => Instruction::Eq,
-                    HirBinaryOp::Ne => Instruction::Ne,
-                    HirBinaryOp::Lt => Instruction::Lt,
-                    HirBinaryOp::Le => Instruction::Le,
-                    HirBinaryOp::Gt => Instruction::Gt,
-                    HirBinaryOp::Ge => Instruction::Ge,
-                };
-                self.instructions.push(instr);
-            }
+            HirExpr::Binary(bin) => match bin.op {
+                HirBinaryOp::And | HirBinaryOp::Or => self.emit_logical(bin)?,
+                op => {
+                    self.emit_expr(&bin.lhs)?;
+                    self.emit_expr(&bin.rhs)?;
+                    self.push(binary_instruction(op));
+                }
+            },
             HirExpr::Unary(un) => {
                 self.emit_expr(&un.expr)?;
                 let instr = match un.op {
                     HirUnaryOp::Neg => Instruction::Neg,
                     HirUnaryOp::Not => Instruction::Not,
+                    HirUnaryOp::BitNot => Instruction::BitNot,
                 };
-                self.instructions.push(instr);
+                self.push(instr);
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_logical(&mut self, bin: &HirBinary) -> Result<(), EmitterError> {
+        self.emit_expr(&bin.lhs)?;
+        let short_circuit = self.emit_jump_placeholder(true);
+        if bin.op == HirBinaryOp::And {
+            self.emit_expr(&bin.rhs)?;
+            let jump_end = self.emit_jump_placeholder(false);
+            self.patch_jump(short_circuit, self.instructions.len());
+            let false_const = self.emitter.add_constant(Constant::Bool(false));
+            self.push(Instruction::LoadConst(false_const));
+            self.patch_jump(jump_end, self.instructions.len());
+        } else {
+            let true_const = self.emitter.add_constant(Constant::Bool(true));
+            self.push(Instruction::LoadConst(true_const));
+            let jump_end = self.emit_jump_placeholder(false);
+            self.patch_jump(short_circuit, self.instructions.len());
+            self.emit_expr(&bin.rhs)?;
+            self.patch_jump(jump_end, self.instructions.len());
+        }
+        Ok(())
+    }
+
+    fn literal_constant(&mut self, lit: &HirLiteral) -> Result<u32, EmitterError> {
+        let const_id = match lit {
+            HirLiteral::Int(int) => self.emitter.add_constant(Constant::Int(int.value)),
+            HirLiteral::Float(float) => {
+                let value = parse_float(&float.value)
+                    .ok_or(EmitterError::InvalidFloat { span: float.span })?;
+                self.emitter.add_constant(Constant::Float(value))
+            }
+            HirLiteral::String(string) => self
+                .emitter
+                .add_constant(Constant::String(string.value.clone())),
+            HirLiteral::Bool(boolean) => self.emitter.add_constant(Constant::Bool(boolean.value)),
+            HirLiteral::Unit(_) => self.emitter.unit_const,
+        };
+        Ok(const_id)
+    }
+
+    fn emit_struct_literal(&mut self, lit: &HirStructLiteral) -> Result<(), EmitterError> {
+        let entry =
+            self.emitter
+                .structs
+                .get(&lit.name.name)
+                .cloned()
+                .ok_or(EmitterError::UnknownName {
+                    span: lit.name.span,
+                })?;
+        let in_order = lit.fields.len() == entry.fields.len()
+            && lit
+                .fields
+                .iter()
+                .zip(&entry.fields)
+                .all(|(init, field)| init.name.name == *field);
+        if in_order {
+            for init in &lit.fields {
+                self.emit_expr(&init.value)?;
+            }
+        } else {
+            let mut slots = HashMap::new();
+            for init in &lit.fields {
+                self.emit_expr(&init.value)?;
+                let slot = self.alloc_temp();
+                self.push(Instruction::StoreLocal(slot));
+                slots.insert(init.name.name, slot);
+            }
+            for field in &entry.fields {
+                let slot = slots
+                    .get(field)
+                    .copied()
+                    .ok_or(EmitterError::MissingField { span: lit.span })?;
+                self.push(Instruction::LoadLocal(slot));
+            }
+        }
+        self.push(Instruction::MakeStruct(entry.layout));
+        Ok(())
+    }
+
+    fn emit_lambda(&mut self, lambda: &HirLambda) -> Result<(), EmitterError> {
+        let captures = self.emitter.analysis.captures(lambda.id).to_vec();
+        for capture in &captures {
+            let slot = self
+                .lookup_local(*capture)
+                .ok_or(EmitterError::UnknownName { span: lambda.span })?;
+            self.push(Instruction::LoadLocal(slot));
+        }
+        let func_id = self.emitter.reserve_function();
+        let name = format!("{}::<lambda>", self.name);
+        let mut builder = FunctionBuilder::new(&mut *self.emitter, name, &lambda.params);
+        for capture in &captures {
+            builder.alloc_local(*capture);
+        }
+        builder.emit_expr(&lambda.body)?;
+        builder.instructions.push(Instruction::Return);
+        let function = builder.finish();
+        self.emitter.lambdas.push((func_id, function));
+        self.push(Instruction::MakeClosure(func_id, captures.len() as u16));
+        Ok(())
+    }
+
+    fn static_method(&self, id: HirId) -> Option<FunctionId> {
+        let target = self.emitter.analysis.method_target(id)?;
+        self.emitter.method_indices.get(target).copied()
+    }
+
+    fn emit_variant(&mut self, path: &HirPath, args: &[HirExpr]) -> Result<(), EmitterError> {
+        let (layout, tag, arity) = self.emitter.resolve_variant(path)?;
+        if arity != args.len() {
+            return Err(EmitterError::VariantArity { span: path.span });
+        }
+        for arg in args {
+            self.emit_expr(arg)?;
+        }
+        self.push(Instruction::MakeVariant(layout, tag));
+        Ok(())
+    }
+
+    fn field_instruction(&mut self, access: &HirFieldAccess, store: bool) -> Instruction {
+        match self.emitter.analysis.field_index(access.id) {
+            Some(idx) if store => Instruction::StoreField(idx as u16),
+            Some(idx) => Instruction::LoadField(idx as u16),
+            None => {
+                let name = self.emitter.symbol_name(access.field.name);
+                let const_id = self.emitter.add_constant(Constant::String(name));
+                if store {
+                    Instruction::StoreFieldNamed(const_id)
+                } else {
+                    Instruction::LoadFieldNamed(const_id)
+                }
+            }
+        }
+    }
+
+    fn emit_while(&mut self, while_expr: &HirWhile) -> Result<(), EmitterError> {
+        let loop_start = self.instructions.len();
+        self.emit_expr(&while_expr.condition)?;
+        let exit_jump = self.emit_jump_placeholder(true);
+        let loop_ctx = self.emit_loop_body(&while_expr.body)?;
+        self.push(Instruction::Jump(loop_start));
+        for jump in loop_ctx.continue_jumps {
+            self.patch_jump(jump, loop_start);
+        }
+        self.finish_loop(exit_jump, loop_ctx.break_jumps);
+        Ok(())
+    }
+
+    fn emit_for(&mut self, for_expr: &HirFor) -> Result<(), EmitterError> {
+        let HirExpr::Range(range) = for_expr.iterable.as_ref() else {
+            return self.emit_for_list(for_expr);
+        };
+        self.emit_expr(&range.start)?;
+        let counter = self.alloc_temp();
+        self.push(Instruction::StoreLocal(counter));
+        self.emit_expr(&range.end)?;
+        let limit = self.alloc_temp();
+        self.push(Instruction::StoreLocal(limit));
+
+        let loop_start = self.instructions.len();
+        self.push(Instruction::LoadLocal(counter));
+        self.push(Instruction::LoadLocal(limit));
+        self.push(if range.inclusive {
+            Instruction::Le
+        } else {
+            Instruction::Lt
+        });
+        let exit_jump = self.emit_jump_placeholder(true);
+
+        self.push_scope();
+        self.declare_pattern_bindings(&for_expr.pattern);
+        let mut skip_jumps = Vec::new();
+        self.emit_pattern(&for_expr.pattern, counter, &mut skip_jumps)?;
+        let loop_ctx = self.emit_loop_body(&for_expr.body)?;
+        self.pop_scope();
+
+        let step = self.instructions.len();
+        for jump in skip_jumps.into_iter().chain(loop_ctx.continue_jumps) {
+            self.patch_jump(jump, step);
+        }
+        // An inclusive range stops at its end instead of stepping past it, so
+        // a range ending at `i64::MAX` never overflows the counter.
+        let last_jump = if range.inclusive {
+            self.push(Instruction::LoadLocal(counter));
+            self.push(Instruction::LoadLocal(limit));
+            self.push(Instruction::Ne);
+            Some(self.emit_jump_placeholder(true))
+        } else {
+            None
+        };
+        let one = self.emitter.add_constant(Constant::Int(1));
+        self.push(Instruction::LoadLocal(counter));
+        self.push(Instruction::LoadConst(one));
+        self.push(Instruction::Add);
+        self.push(Instruction::StoreLocal(counter));
+        self.push(Instruction::Jump(loop_start));
+        if let Some(jump) = last_jump {
+            self.patch_jump(jump, self.instructions.len());
+        }
+        self.finish_loop(exit_jump, loop_ctx.break_jumps);
+        Ok(())
+    }
+
+    /// Lowers a loop over a list to a counter over its indices. Lists never
+    /// change length, so the length is read once before the loop.
+    fn emit_for_list(&mut self, for_expr: &HirFor) -> Result<(), EmitterError> {
+        self.emit_expr(&for_expr.iterable)?;
+        let items = self.alloc_temp();
+        self.push(Instruction::StoreLocal(items));
+        self.push(Instruction::LoadLocal(items));
+        self.push(Instruction::Len);
+        let limit = self.alloc_temp();
+        self.push(Instruction::StoreLocal(limit));
+        let zero = self.emitter.add_constant(Constant::Int(0));
+        self.push(Instruction::LoadConst(zero));
+        let counter = self.alloc_temp();
+        self.push(Instruction::StoreLocal(counter));
+
+        let loop_start = self.instructions.len();
+        self.push(Instruction::LoadLocal(counter));
+        self.push(Instruction::LoadLocal(limit));
+        self.push(Instruction::Lt);
+        let exit_jump = self.emit_jump_placeholder(true);
+        self.push(Instruction::LoadLocal(items));
+        self.push(Instruction::LoadLocal(counter));
+        self.push(Instruction::Index);
+        let element = self.alloc_temp();
+        self.push(Instruction::StoreLocal(element));
+
+        self.push_scope();
+        self.declare_pattern_bindings(&for_expr.pattern);
+        let mut skip_jumps = Vec::new();
+        self.emit_pattern(&for_expr.pattern, element, &mut skip_jumps)?;
+        let loop_ctx = self.emit_loop_body(&for_expr.body)?;
+        self.pop_scope();
+
+        let step = self.instructions.len();
+        for jump in skip_jumps.into_iter().chain(loop_ctx.continue_jumps) {
+            self.patch_jump(jump, step);
+        }
+        let one = self.emitter.add_constant(Constant::Int(1));
+        self.push(Instruction::LoadLocal(counter));
+        self.push(Instruction::LoadConst(one));
+        self.push(Instruction::Add);
+        self.push(Instruction::StoreLocal(counter));
+        self.push(Instruction::Jump(loop_start));
+        self.finish_loop(exit_jump, loop_ctx.break_jumps);
+        Ok(())
+    }
+
+    fn emit_loop_body(&mut self, body: &HirBlock) -> Result<LoopContext, EmitterError> {
+        self.loops.push(LoopContext {
+            depth: self.depth,
+            ..LoopContext::default()
+        });
+        let result = self.emit_block(body, false);
+        let loop_ctx = self.loops.pop().unwrap_or_default();
+        result.map(|_| loop_ctx)
+    }
+
+    fn finish_loop(&mut self, exit_jump: usize, break_jumps: Vec<usize>) {
+        self.patch_jump(exit_jump, self.instructions.len());
+        self.push_unit();
+        let end = self.instructions.len();
+        for jump in break_jumps {
+            self.patch_jump(jump, end);
+        }
+    }
+
+    fn emit_match(&mut self, match_expr: &HirMatch) -> Result<(), EmitterError> {
+        self.emit_expr(&match_expr.scrutinee)?;
+        let scrutinee = self.alloc_temp();
+        self.push(Instruction::StoreLocal(scrutinee));
+        let mut end_jumps = Vec::new();
+        for arm in &match_expr.arms {
+            self.push_scope();
+            self.declare_pattern_bindings(&arm.pattern);
+            let mut fail_jumps = Vec::new();
+            self.emit_pattern(&arm.pattern, scrutinee, &mut fail_jumps)?;
+            if let Some(guard) = &arm.guard {
+                self.emit_expr(guard)?;
+                fail_jumps.push(self.emit_jump_placeholder(true));
+            }
+            self.emit_block(&arm.body, true)?;
+            end_jumps.push(self.emit_jump_placeholder(false));
+            let next_arm = self.instructions.len();
+            for jump in fail_jumps {
+                self.patch_jump(jump, next_arm);
+            }
+            self.pop_scope();
+        }
+        self.push_unit();
+        let end = self.instructions.len();
+        for jump in end_jumps {
+            self.patch_jump(jump, end);
+        }
+        Ok(())
+    }
+
+    fn emit_global_let(&mut self, binding: &HirLetBinding) -> Result<(), EmitterError> {
+        self.emit_expr(&binding.value)?;
+        if let HirPattern::Binding(target) = &binding.pattern {
+            let global = self
+                .emitter
+                .global_index(target.name)
+                .ok_or(EmitterError::UnknownName { span: target.span })?;
+            self.push(Instruction::StoreGlobal(global));
+            return Ok(());
+        }
+        self.push_scope();
+        let value = self.alloc_temp();
+        self.push(Instruction::StoreLocal(value));
+        self.declare_pattern_bindings(&binding.pattern);
+        let mut fail_jumps = Vec::new();
+        self.emit_pattern(&binding.pattern, value, &mut fail_jumps)?;
+        if !fail_jumps.is_empty() {
+            return Err(EmitterError::RefutablePattern { span: binding.span });
+        }
+        let mut names = Vec::new();
+        pattern_bindings(&binding.pattern, &mut names);
+        for name in names {
+            let (Some(slot), Some(global)) =
+                (self.lookup_local(name), self.emitter.global_index(name))
+            else {
+                return Err(EmitterError::UnknownName { span: binding.span });
+            };
+            self.push(Instruction::LoadLocal(slot));
+            self.push(Instruction::StoreGlobal(global));
+        }
+        self.pop_scope();
+        Ok(())
+    }
+
+    fn declare_pattern_bindings(&mut self, pattern: &HirPattern) {
+        match pattern {
+            HirPattern::Binding(binding) => {
+                let declared = self
+                    .scopes
+                    .last()
+                    .is_some_and(|scope| scope.contains_key(&binding.name));
+                if !declared {
+                    self.alloc_local(binding.name);
+                }
+            }
+            HirPattern::Or(or) => {
+                for alt in &or.alternatives {
+                    self.declare_pattern_bindings(alt);
+                }
+            }
+            HirPattern::Variant(variant) => {
+                for field in &variant.fields {
+                    self.declare_pattern_bindings(field);
+                }
+            }
+            HirPattern::Tuple(tuple) => {
+                for element in &tuple.elements {
+                    self.declare_pattern_bindings(element);
+                }
+            }
+            HirPattern::Wildcard(_) | HirPattern::Literal(_) => {}
+        }
+    }
+
+    fn emit_pattern(
+        &mut self,
+        pattern: &HirPattern,
+        value_slot: u16,
+        fail_jumps: &mut Vec<usize>,
+    ) -> Result<(), EmitterError> {
+        match pattern {
+            HirPattern::Wildcard(_) => {}
+            HirPattern::Binding(binding) => {
+                let slot = self
+                    .lookup_local(binding.name)
+                    .ok_or(EmitterError::UnknownName { span: binding.span })?;
+                self.push(Instruction::LoadLocal(value_slot));
+                self.push(Instruction::StoreLocal(slot));
+            }
+            HirPattern::Literal(lit) => {
+                let const_id = self.literal_constant(lit)?;
+                self.push(Instruction::LoadLocal(value_slot));
+                self.push(Instruction::LoadConst(const_id));
+                self.push(Instruction::Eq);
+                fail_jumps.push(self.emit_jump_placeholder(true));
+            }
+            HirPattern::Or(or) => {
+                let mut success_jumps = Vec::new();
+                let last = or.alternatives.len().saturating_sub(1);
+                for (idx, alt) in or.alternatives.iter().enumerate() {
+                    if idx == last {
+                        self.emit_pattern(alt, value_slot, fail_jumps)?;
+                        break;
+                    }
+                    let mut alt_fail = Vec::new();
+                    self.emit_pattern(alt, value_slot, &mut alt_fail)?;
+                    success_jumps.push(self.emit_jump_placeholder(false));
+                    let next_alt = self.instructions.len();
+                    for jump in alt_fail {
+                        self.patch_jump(jump, next_alt);
+                    }
+                }
+                let matched = self.instructions.len();
+                for jump in success_jumps {
+                    self.patch_jump(jump, matched);
+                }
+            }
+            HirPattern::Variant(variant) => {
+                let (layout, tag, arity) = self.emitter.resolve_variant(&variant.path)?;
+                if arity != variant.fields.len() {
+                    return Err(EmitterError::VariantArity { span: variant.span });
+                }
+                self.push(Instruction::LoadLocal(value_slot));
+                self.push(Instruction::TestVariant(layout, tag));
+                fail_jumps.push(self.emit_jump_placeholder(true));
+                for (idx, field) in variant.fields.iter().enumerate() {
+                    if matches!(field, HirPattern::Wildcard(_)) {
+                        continue;
+                    }
+                    self.push(Instruction::LoadLocal(value_slot));
+                    self.push(Instruction::LoadVariantField(idx as u16));
+                    if let HirPattern::Binding(binding) = field {
+                        let slot = self
+                            .lookup_local(binding.name)
+                            .ok_or(EmitterError::UnknownName { span: binding.span })?;
+                        self.push(Instruction::StoreLocal(slot));
+                    } else {
+                        let slot = self.alloc_temp();
+                        self.push(Instruction::StoreLocal(slot));
+                        self.emit_pattern(field, slot, fail_jumps)?;
+                    }
+                }
+            }
+            HirPattern::Tuple(tuple) => {
+                self.push(Instruction::LoadLocal(value_slot));
+                self.push(Instruction::UnpackTuple(tuple.elements.len() as u16));
+                let mut nested = Vec::new();
+                for element in tuple.elements.iter().rev() {
+                    match element {
+                        HirPattern::Wildcard(_) => self.push(Instruction::Pop),
+                        HirPattern::Binding(binding) => {
+                            let slot = self
+                                .lookup_local(binding.name)
+                                .ok_or(EmitterError::UnknownName { span: binding.span })?;
+                            self.push(Instruction::StoreLocal(slot));
+                        }
+                        _ => {
+                            let slot = self.alloc_temp();
+                            self.push(Instruction::StoreLocal(slot));
+                            nested.push((element, slot));
+                        }
+                    }
+                }
+                for (element, slot) in nested.into_iter().rev() {
+                    self.emit_pattern(element, slot, fail_jumps)?;
+                }
             }
         }
         Ok(())
     }
 
     fn push_unit(&mut self) {
-        self.instructions
-            .push(Instruction::LoadConst(self.emitter.unit_const));
+        self.push(Instruction::LoadConst(self.emitter.unit_const));
     }
 
     fn emit_jump_placeholder(&mut self, conditional: bool) -> usize {
         let pos = self.instructions.len();
         if conditional {
-            self.instructions.push(Instruction::JumpIfFalse(usize::MAX));
+            self.push(Instruction::JumpIfFalse(usize::MAX));
         } else {
-            self.instructions.push(Instruction::Jump(usize::MAX));
+            self.push(Instruction::Jump(usize::MAX));
         }
+        self.jump_depths.insert(pos, self.depth);
         pos
     }
 
+    /// Patches a jump; a forward jump to the next instruction also resumes
+    /// tracking at the depth the jump left the stack in.
     fn patch_jump(&mut self, index: usize, target: usize) {
         match &mut self.instructions[index] {
             Instruction::Jump(ref mut slot) | Instruction::JumpIfFalse(ref mut slot) => {
@@ -392,6 +1289,98 @@ impl<'a, 'b> FunctionBuilder<'a, 'b> {
             }
             _ => {}
         }
+        if target == self.instructions.len() {
+            if let Some(&depth) = self.jump_depths.get(&index) {
+                self.depth = depth;
+            }
+        }
+    }
+
+    fn push(&mut self, instruction: Instruction) {
+        let (pops, pushes) = stack_effect(&self.emitter.bytecode, &instruction);
+        self.depth = self.depth.saturating_sub(pops) + pushes;
+        self.instructions.push(instruction);
+    }
+
+    /// Discards pending operands until the stack is back at `depth`.
+    fn pop_to(&mut self, depth: usize) {
+        while self.depth > depth {
+            self.push(Instruction::Pop);
+        }
+    }
+
+    fn loop_depth(&self, span: Span) -> Result<usize, EmitterError> {
+        self.loops
+            .last()
+            .map(|ctx| ctx.depth)
+            .ok_or(EmitterError::LoopControlOutsideLoop { span })
+    }
+
+    fn current_loop(&mut self, span: Span) -> Result<&mut LoopContext, EmitterError> {
+        self.loops
+            .last_mut()
+            .ok_or(EmitterError::LoopControlOutsideLoop { span })
+    }
+}
+
+fn binary_instruction(op: HirBinaryOp) -> Instruction {
+    match op {
+        HirBinaryOp::Add => Instruction::Add,
+        HirBinaryOp::Sub => Instruction::Sub,
+        HirBinaryOp::Mul => Instruction::Mul,
+        HirBinaryOp::Div => Instruction::Div,
+        HirBinaryOp::Mod => Instruction::Mod,
+        HirBinaryOp::BitAnd => Instruction::BitAnd,
+        HirBinaryOp::BitOr => Instruction::BitOr,
+        HirBinaryOp::BitXor => Instruction::BitXor,
+        HirBinaryOp::Shl => Instruction::Shl,
+        HirBinaryOp::Shr => Instruction::Shr,
+        HirBinaryOp::Eq => Instruction::Eq,
+        HirBinaryOp::Ne => Instruction::Ne,
+        HirBinaryOp::Lt => Instruction::Lt,
+        HirBinaryOp::Le => Instruction::Le,
+        HirBinaryOp::Gt => Instruction::Gt,
+        HirBinaryOp::Ge => Instruction::Ge,
+        HirBinaryOp::And | HirBinaryOp::Or => {
+            unreachable!("logical operators are emitted as jumps")
+        }
+    }
+}
+
+fn pattern_bindings(pattern: &HirPattern, out: &mut Vec<Symbol>) {
+    match pattern {
+        HirPattern::Binding(binding) => out.push(binding.name),
+        HirPattern::Or(or) => {
+            if let Some(first) = or.alternatives.first() {
+                pattern_bindings(first, out);
+            }
+        }
+        HirPattern::Variant(variant) => {
+            for field in &variant.fields {
+                pattern_bindings(field, out);
+            }
+        }
+        HirPattern::Tuple(tuple) => {
+            for element in &tuple.elements {
+                pattern_bindings(element, out);
+            }
+        }
+        HirPattern::Wildcard(_) | HirPattern::Literal(_) => {}
+    }
+}
+
+fn parse_float(value: &str) -> Option<f64> {
+    value.replace('_', "").parse::<f64>().ok()
+}
+
+fn stmt_span(stmt: &HirStmt) -> Span {
+    match stmt {
+        HirStmt::Let(binding) => binding.span,
+        HirStmt::Assign(assign) => assign.span,
+        HirStmt::Return(ret) => ret.span,
+        HirStmt::Break(brk) => brk.span,
+        HirStmt::Continue(cont) => cont.span,
+        HirStmt::Expr(expr) => expr_span(expr),
     }
 }
 
@@ -399,13 +1388,26 @@ fn expr_span(expr: &HirExpr) -> Span {
     match expr {
         HirExpr::Literal(lit) => match lit {
             HirLiteral::Int(int) => int.span,
+            HirLiteral::Float(float) => float.span,
             HirLiteral::String(string) => string.span,
             HirLiteral::Bool(boolean) => boolean.span,
             HirLiteral::Unit(unit) => unit.span,
         },
         HirExpr::Name(name) => name.span,
         HirExpr::Call(call) => call.span,
+        HirExpr::MethodCall(call) => call.span,
         HirExpr::If(if_expr) => if_expr.span,
+        HirExpr::Match(match_expr) => match_expr.span,
+        HirExpr::While(while_expr) => while_expr.span,
+        HirExpr::For(for_expr) => for_expr.span,
+        HirExpr::Range(range) => range.span,
+        HirExpr::StructLit(lit) => lit.span,
+        HirExpr::Field(access) => access.span,
+        HirExpr::List(list) => list.span,
+        HirExpr::Tuple(tuple) => tuple.span,
+        HirExpr::Index(index) => index.span,
+        HirExpr::Path(path) => path.span,
+        HirExpr::Lambda(lambda) => lambda.span,
         HirExpr::Block(block) => block.span,
         HirExpr::Binary(bin) => bin.span,
         HirExpr::Unary(un) => un.span,
@@ -11,27 +11,119 @@ pub struct Module {
 pub enum Item {
     Let(LetStatement),
     Function(Function),
+    Struct(StructDecl),
+    Enum(EnumDecl),
+    Impl(ImplBlock),
+    Use(UseDecl),
+}
+
+#[derive(Debug, Clone)]
+pub struct UseDecl {
+    pub span: Span,
+    pub path: Vec<NameRef>,
+    pub alias: Option<NameRef>,
 }
 
 #[derive(Debug, Clone)]
 pub struct LetStatement {
     pub span: Span,
-    pub name: SmolStr,
+    pub public: bool,
+    pub pattern: Pattern,
+    pub mutable: bool,
+    pub ty: Option<TypeExpr>,
     pub value: Expr,
 }
 
 #[derive(Debug, Clone)]
 pub struct Function {
     pub span: Span,
+    pub public: bool,
     pub name: SmolStr,
+    pub type_params: Vec<NameRef>,
     pub params: Vec<Parameter>,
+    pub return_ty: Option<TypeExpr>,
     pub body: Block,
 }
 
+#[derive(Debug, Clone)]
+pub struct StructDecl {
+    pub span: Span,
+    pub public: bool,
+    pub name: SmolStr,
+    pub type_params: Vec<NameRef>,
+    pub fields: Vec<FieldDecl>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldDecl {
+    pub span: Span,
+    pub name: SmolStr,
+    pub ty: TypeExpr,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDecl {
+    pub span: Span,
+    pub public: bool,
+    pub name: SmolStr,
+    pub variants: Vec<VariantDecl>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantDecl {
+    pub span: Span,
+    pub name: SmolStr,
+    pub fields: Vec<VariantField>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantField {
+    pub span: Span,
+    pub name: SmolStr,
+    pub ty: Option<TypeExpr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImplBlock {
+    pub span: Span,
+    pub ty: NameRef,
+    pub methods: Vec<Function>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeExpr {
+    Name(NameRef),
+    Generic(GenericType),
+    Tuple(TupleType),
+    Function(FunctionType),
+    Unit(Span),
+}
+
+#[derive(Debug, Clone)]
+pub struct GenericType {
+    pub span: Span,
+    pub name: NameRef,
+    pub args: Vec<TypeExpr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TupleType {
+    pub span: Span,
+    pub elements: Vec<TypeExpr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionType {
+    pub span: Span,
+    pub params: Vec<TypeExpr>,
+    pub return_ty: Option<Box<TypeExpr>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Parameter {
     pub span: Span,
     pub name: SmolStr,
+    pub ty: Option<TypeExpr>,
 }
 
 #[derive(Debug, Clone)]
@@ -44,11 +136,28 @@ pub struct Block {
 #[derive(Debug, Clone)]
 pub enum Stmt {
     Let(LetStatement),
+    Assign(AssignStatement),
     Return(ReturnStatement),
-    While(WhileStatement),
+    Break(BreakStatement),
+    Continue(ContinueStatement),
     Expr(Expr),
 }
 
+#[derive(Debug, Clone)]
+pub struct AssignStatement {
+    pub span: Span,
+    pub target: AssignTarget,
+    pub op: Option<BinaryOp>,
+    pub value: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub enum AssignTarget {
+    Name(NameRef),
+    Field(FieldExpr),
+    Index(IndexExpr),
+}
+
 #[derive(Debug, Clone)]
 pub struct ReturnStatement {
     pub span: Span,
@@ -56,18 +165,133 @@ pub struct ReturnStatement {
 }
 
 #[derive(Debug, Clone)]
-pub struct WhileStatement {
+pub struct BreakStatement {
+    pub span: Span,
+    pub value: Option<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContinueStatement {
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct WhileExpr {
     pub span: Span,
     pub condition: Expr,
     pub body: Box<Block>,
 }
 
+#[derive(Debug, Clone)]
+pub struct ForExpr {
+    pub span: Span,
+    pub pattern: Pattern,
+    pub iterable: Expr,
+    pub body: Box<Block>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IfLetExpr {
+    pub span: Span,
+    pub pattern: Pattern,
+    pub value: Box<Expr>,
+    pub then_branch: Box<Block>,
+    pub else_branch: Option<Box<Block>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PathExpr {
+    pub span: Span,
+    pub segments: Vec<NameRef>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LambdaExpr {
+    pub span: Span,
+    pub params: Vec<Parameter>,
+    pub body: Box<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructLiteral {
+    pub span: Span,
+    pub name: NameRef,
+    pub fields: Vec<FieldInit>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldInit {
+    pub span: Span,
+    pub name: NameRef,
+    pub value: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldExpr {
+    pub span: Span,
+    pub base: Box<Expr>,
+    pub field: NameRef,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListExpr {
+    pub span: Span,
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TupleExpr {
+    pub span: Span,
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexExpr {
+    pub span: Span,
+    pub base: Box<Expr>,
+    pub index: Box<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FStringExpr {
+    pub span: Span,
+    pub segments: Vec<FStringSegment>,
+}
+
+#[derive(Debug, Clone)]
+pub enum FStringSegment {
+    Text(SmolStr),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone)]
+pub struct RangeExpr {
+    pub span: Span,
+    pub start: Box<Expr>,
+    pub end: Box<Expr>,
+    pub inclusive: bool,
+}
+
 #[derive(Debug, Clone)]
 pub enum Expr {
     Literal(Literal),
+    FString(FStringExpr),
     Name(NameRef),
     Call(CallExpr),
+    MethodCall(MethodCallExpr),
     If(Box<IfExpr>),
+    IfLet(Box<IfLetExpr>),
+    Match(Box<MatchExpr>),
+    While(Box<WhileExpr>),
+    For(Box<ForExpr>),
+    Range(RangeExpr),
+    StructLit(StructLiteral),
+    Field(FieldExpr),
+    List(ListExpr),
+    Tuple(TupleExpr),
+    Index(IndexExpr),
+    Path(PathExpr),
+    Lambda(LambdaExpr),
     Block(Box<Block>),
     Paren(Box<Expr>),
     Binary(BinaryExpr),
@@ -83,6 +307,7 @@ pub struct NameRef {
 #[derive(Debug, Clone)]
 pub enum Literal {
     Int(IntLiteral),
+    Float(FloatLiteral),
     String(StringLiteral),
     Bool(BoolLiteral),
     Unit(UnitLiteral),
@@ -94,6 +319,12 @@ pub struct IntLiteral {
     pub value: SmolStr,
 }
 
+#[derive(Debug, Clone)]
+pub struct FloatLiteral {
+    pub span: Span,
+    pub value: SmolStr,
+}
+
 #[derive(Debug, Clone)]
 pub struct StringLiteral {
     pub span: Span,
@@ -118,6 +349,14 @@ pub struct CallExpr {
     pub args: Vec<Expr>,
 }
 
+#[derive(Debug, Clone)]
+pub struct MethodCallExpr {
+    pub span: Span,
+    pub receiver: Box<Expr>,
+    pub method: NameRef,
+    pub args: Vec<Expr>,
+}
+
 #[derive(Debug, Clone)]
 pub struct IfExpr {
     pub span: Span,
@@ -126,6 +365,61 @@ pub struct IfExpr {
     pub else_branch: Option<Box<Block>>,
 }
 
+#[derive(Debug, Clone)]
+pub struct MatchExpr {
+    pub span: Span,
+    pub scrutinee: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub span: Span,
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard(WildcardPattern),
+    Binding(BindingPattern),
+    Literal(Literal),
+    Or(OrPattern),
+    Variant(VariantPattern),
+    Tuple(TuplePattern),
+}
+
+#[derive(Debug, Clone)]
+pub struct WildcardPattern {
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct BindingPattern {
+    pub span: Span,
+    pub name: SmolStr,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrPattern {
+    pub span: Span,
+    pub alternatives: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantPattern {
+    pub span: Span,
+    pub path: PathExpr,
+    pub fields: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TuplePattern {
+    pub span: Span,
+    pub elements: Vec<Pattern>,
+}
+
 #[derive(Debug, Clone)]
 pub struct BinaryExpr {
     pub span: Span,
@@ -147,18 +441,27 @@ pub enum BinaryOp {
     Sub,
     Mul,
     Div,
+    Mod,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
     Eq,
     Ne,
     Lt,
     Le,
     Gt,
     Ge,
+    And,
+    Or,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOp {
     Neg,
     Not,
+    BitNot,
 }
 
 impl Module {
@@ -170,18 +473,39 @@ impl Module {
     }
 }
 
+impl Literal {
+    pub fn span(&self) -> Span {
+        match self {
+            Literal::Int(l) => l.span,
+            Literal::Float(l) => l.span,
+            Literal::String(l) => l.span,
+            Literal::Bool(l) => l.span,
+            Literal::Unit(l) => l.span,
+        }
+    }
+}
+
 impl Expr {
     pub fn span(&self) -> Span {
         match self {
-            Expr::Literal(lit) => match lit {
-                Literal::Int(l) => l.span,
-                Literal::String(l) => l.span,
-                Literal::Bool(l) => l.span,
-                Literal::Unit(l) => l.span,
-            },
+            Expr::Literal(lit) => lit.span(),
+            Expr::FString(fstring) => fstring.span,
             Expr::Name(name) => name.span,
             Expr::Call(call) => call.span,
+            Expr::MethodCall(call) => call.span,
             Expr::If(if_expr) => if_expr.span,
+            Expr::IfLet(if_let) => if_let.span,
+            Expr::Match(match_expr) => match_expr.span,
+            Expr::While(while_expr) => while_expr.span,
+            Expr::For(for_expr) => for_expr.span,
+            Expr::Range(range) => range.span,
+            Expr::StructLit(lit) => lit.span,
+            Expr::Field(field) => field.span,
+            Expr::List(list) => list.span,
+            Expr::Tuple(tuple) => tuple.span,
+            Expr::Index(index) => index.span,
+            Expr::Path(path) => path.span,
+            Expr::Lambda(lambda) => lambda.span,
             Expr::Block(block) => block.span,
             Expr::Paren(expr) => expr.span(),
             Expr::Binary(bin) => bin.span,
@@ -190,12 +514,49 @@ impl Expr {
     }
 }
 
+impl TypeExpr {
+    pub fn span(&self) -> Span {
+        match self {
+            TypeExpr::Name(name) => name.span,
+            TypeExpr::Generic(generic) => generic.span,
+            TypeExpr::Tuple(tuple) => tuple.span,
+            TypeExpr::Function(function) => function.span,
+            TypeExpr::Unit(span) => *span,
+        }
+    }
+}
+
+impl AssignTarget {
+    pub fn span(&self) -> Span {
+        match self {
+            AssignTarget::Name(name) => name.span,
+            AssignTarget::Field(field) => field.span,
+            AssignTarget::Index(index) => index.span,
+        }
+    }
+}
+
+impl Pattern {
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Wildcard(pat) => pat.span,
+            Pattern::Binding(pat) => pat.span,
+            Pattern::Literal(lit) => lit.span(),
+            Pattern::Or(pat) => pat.span,
+            Pattern::Variant(pat) => pat.span,
+            Pattern::Tuple(pat) => pat.span,
+        }
+    }
+}
+
 impl Stmt {
     pub fn span(&self) -> Span {
         match self {
             Stmt::Let(let_stmt) => let_stmt.span,
+            Stmt::Assign(assign) => assign.span,
             Stmt::Return(ret) => ret.span,
-            Stmt::While(while_stmt) => while_stmt.span,
+            Stmt::Break(brk) => brk.span,
+            Stmt::Continue(cont) => cont.span,
             Stmt::Expr(expr) => expr.span(),
         }
     }
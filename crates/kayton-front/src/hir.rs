@@ -12,12 +12,17 @@ pub struct HirModule {
 pub enum HirItem {
     Let(HirLetBinding),
     Function(HirFunction),
+    Struct(HirStruct),
+    Enum(HirEnum),
+    Impl(HirImpl),
 }
 
 #[derive(Debug, Clone)]
 pub struct HirLetBinding {
     pub id: HirId,
-    pub name: Symbol,
+    pub pattern: HirPattern,
+    pub mutable: bool,
+    pub ty: Option<HirType>,
     pub value: HirExpr,
     pub span: Span,
 }
@@ -26,15 +31,95 @@ pub struct HirLetBinding {
 pub struct HirFunction {
     pub id: HirId,
     pub name: Symbol,
+    pub type_params: Vec<HirNameRef>,
     pub params: Vec<HirParam>,
+    pub return_ty: Option<HirType>,
     pub body: HirBlock,
     pub span: Span,
 }
 
+#[derive(Debug, Clone)]
+pub struct HirStruct {
+    pub id: HirId,
+    pub name: Symbol,
+    pub type_params: Vec<HirNameRef>,
+    pub fields: Vec<HirFieldDecl>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirFieldDecl {
+    pub id: HirId,
+    pub name: Symbol,
+    pub ty: HirType,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirEnum {
+    pub id: HirId,
+    pub name: Symbol,
+    pub variants: Vec<HirVariantDecl>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirVariantDecl {
+    pub id: HirId,
+    pub name: Symbol,
+    pub fields: Vec<HirVariantField>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirVariantField {
+    pub id: HirId,
+    pub name: Symbol,
+    pub ty: Option<HirType>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirImpl {
+    pub id: HirId,
+    pub ty: HirNameRef,
+    pub methods: Vec<HirFunction>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum HirType {
+    Named(HirNamedType),
+    Tuple(HirTupleType),
+    Function(HirFunctionType),
+    Unit(Span),
+}
+
+#[derive(Debug, Clone)]
+pub struct HirNamedType {
+    pub name: Symbol,
+    pub args: Vec<HirType>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirTupleType {
+    pub elements: Vec<HirType>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirFunctionType {
+    pub params: Vec<HirType>,
+    pub return_ty: Option<Box<HirType>>,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub struct HirParam {
     pub id: HirId,
     pub name: Symbol,
+    pub ty: Option<HirType>,
     pub span: Span,
 }
 
@@ -49,11 +134,29 @@ pub struct HirBlock {
 #[derive(Debug, Clone)]
 pub enum HirStmt {
     Let(HirLetBinding),
-    While(HirWhile),
+    Assign(HirAssign),
     Return(HirReturn),
+    Break(HirBreak),
+    Continue(HirContinue),
     Expr(HirExpr),
 }
 
+#[derive(Debug, Clone)]
+pub struct HirAssign {
+    pub id: HirId,
+    pub target: HirAssignTarget,
+    pub op: Option<HirBinaryOp>,
+    pub value: Box<HirExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum HirAssignTarget {
+    Name(HirNameRef),
+    Field(HirFieldAccess),
+    Index(HirIndex),
+}
+
 #[derive(Debug, Clone)]
 pub struct HirReturn {
     pub id: HirId,
@@ -61,6 +164,19 @@ pub struct HirReturn {
     pub span: Span,
 }
 
+#[derive(Debug, Clone)]
+pub struct HirBreak {
+    pub id: HirId,
+    pub value: Option<Box<HirExpr>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirContinue {
+    pub id: HirId,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub struct HirWhile {
     pub id: HirId,
@@ -69,12 +185,103 @@ pub struct HirWhile {
     pub span: Span,
 }
 
+#[derive(Debug, Clone)]
+pub struct HirFor {
+    pub id: HirId,
+    pub pattern: HirPattern,
+    pub iterable: Box<HirExpr>,
+    pub body: Box<HirBlock>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirStructLiteral {
+    pub id: HirId,
+    pub name: HirNameRef,
+    pub fields: Vec<HirFieldInit>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirFieldInit {
+    pub id: HirId,
+    pub name: HirNameRef,
+    pub value: HirExpr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirFieldAccess {
+    pub id: HirId,
+    pub base: Box<HirExpr>,
+    pub field: HirNameRef,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirList {
+    pub id: HirId,
+    pub elements: Vec<HirExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirTuple {
+    pub id: HirId,
+    pub elements: Vec<HirExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirIndex {
+    pub id: HirId,
+    pub base: Box<HirExpr>,
+    pub index: Box<HirExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirPath {
+    pub id: HirId,
+    pub segments: Vec<HirNameRef>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirLambda {
+    pub id: HirId,
+    pub params: Vec<HirParam>,
+    pub body: Box<HirExpr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirRange {
+    pub id: HirId,
+    pub start: Box<HirExpr>,
+    pub end: Box<HirExpr>,
+    pub inclusive: bool,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub enum HirExpr {
     Literal(HirLiteral),
     Name(HirNameRef),
     Call(HirCall),
+    MethodCall(HirMethodCall),
     If(Box<HirIf>),
+    Match(Box<HirMatch>),
+    While(Box<HirWhile>),
+    For(Box<HirFor>),
+    Range(HirRange),
+    StructLit(HirStructLiteral),
+    Field(HirFieldAccess),
+    List(HirList),
+    Tuple(HirTuple),
+    Index(HirIndex),
+    Path(HirPath),
+    Lambda(HirLambda),
     Block(Box<HirBlock>),
     Binary(HirBinary),
     Unary(HirUnary),
@@ -90,6 +297,7 @@ pub struct HirNameRef {
 #[derive(Debug, Clone)]
 pub enum HirLiteral {
     Int(HirIntLiteral),
+    Float(HirFloatLiteral),
     String(HirStringLiteral),
     Bool(HirBoolLiteral),
     Unit(HirUnitLiteral),
@@ -97,6 +305,13 @@ pub enum HirLiteral {
 
 #[derive(Debug, Clone)]
 pub struct HirIntLiteral {
+    pub id: HirId,
+    pub value: i64,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirFloatLiteral {
     pub id: HirId,
     pub value: String,
     pub span: Span,
@@ -130,6 +345,15 @@ pub struct HirCall {
     pub span: Span,
 }
 
+#[derive(Debug, Clone)]
+pub struct HirMethodCall {
+    pub id: HirId,
+    pub receiver: Box<HirExpr>,
+    pub method: HirNameRef,
+    pub args: Vec<HirExpr>,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub struct HirIf {
     pub id: HirId,
@@ -139,6 +363,75 @@ pub struct HirIf {
     pub span: Span,
 }
 
+#[derive(Debug, Clone)]
+pub struct HirMatch {
+    pub id: HirId,
+    pub scrutinee: Box<HirExpr>,
+    pub arms: Vec<HirMatchArm>,
+    pub source: HirMatchSource,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HirMatchSource {
+    Match,
+    IfLet,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirMatchArm {
+    pub id: HirId,
+    pub pattern: HirPattern,
+    pub guard: Option<Box<HirExpr>>,
+    pub body: HirBlock,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum HirPattern {
+    Wildcard(HirWildcardPattern),
+    Binding(HirBindingPattern),
+    Literal(HirLiteral),
+    Or(HirOrPattern),
+    Variant(HirVariantPattern),
+    Tuple(HirTuplePattern),
+}
+
+#[derive(Debug, Clone)]
+pub struct HirWildcardPattern {
+    pub id: HirId,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirBindingPattern {
+    pub id: HirId,
+    pub name: Symbol,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirOrPattern {
+    pub id: HirId,
+    pub alternatives: Vec<HirPattern>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirVariantPattern {
+    pub id: HirId,
+    pub path: HirPath,
+    pub fields: Vec<HirPattern>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct HirTuplePattern {
+    pub id: HirId,
+    pub elements: Vec<HirPattern>,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub struct HirBinary {
     pub id: HirId,
@@ -162,18 +455,27 @@ pub enum HirBinaryOp {
     Sub,
     Mul,
     Div,
+    Mod,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
     Eq,
     Ne,
     Lt,
     Le,
     Gt,
     Ge,
+    And,
+    Or,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HirUnaryOp {
     Neg,
     Not,
+    BitNot,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
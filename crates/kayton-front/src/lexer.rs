@@ -2,7 +2,7 @@ use crate::diagnostics::Diagnostic;
 use crate::span::{SourceId, Span};
 use smol_str::SmolStr;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
@@ -12,7 +12,9 @@ pub struct Token {
 pub enum TokenKind {
     Identifier(SmolStr),
     Int(SmolStr),
+    Float(SmolStr),
     String(SmolStr),
+    FString(Vec<FStringPart>),
     Keyword(Keyword),
     LParen,
     RParen,
@@ -23,16 +25,30 @@ pub enum TokenKind {
     Comma,
     Colon,
     Dot,
+    DotDot,
+    DotDotEq,
     Question,
     DoubleColon,
     Arrow,
     FatArrow,
     Equal,
+    PlusEq,
+    MinusEq,
+    StarEq,
+    SlashEq,
     Plus,
     Minus,
     Star,
     Slash,
     Percent,
+    Pipe,
+    PipePipe,
+    Amp,
+    AmpAmp,
+    Caret,
+    Tilde,
+    Shl,
+    Shr,
     Bang,
     EqEq,
     BangEq,
@@ -46,20 +62,30 @@ pub enum TokenKind {
     Eof,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FStringPart {
+    Text(SmolStr),
+    Expr(Vec<Token>),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Keyword {
     Fn,
     Let,
+    Mut,
     Return,
     If,
     Elif,
     Else,
     While,
+    Match,
     True,
     False,
     Use,
+    Pub,
     Struct,
     Enum,
+    Impl,
     For,
     In,
     Break,
@@ -112,6 +138,10 @@ impl<'a> Lexer<'a> {
                 continue;
             }
 
+            if self.line_start && self.nesting > 0 {
+                self.line_start = false;
+            }
+
             if self.line_start {
                 if let Some(token) = self.consume_indentation() {
                     self.tokens.push(token);
@@ -160,6 +190,13 @@ impl<'a> Lexer<'a> {
                 continue;
             }
 
+            if matches!(ch, 'r' | 'f') && self.char_at(self.pos + 1) == Some('"') {
+                if let Some(token) = self.lex_string() {
+                    self.tokens.push(token);
+                }
+                continue;
+            }
+
             if let Some(token) = self.lex_number_or_identifier() {
                 self.tokens.push(token);
                 continue;
@@ -221,7 +258,17 @@ impl<'a> Lexer<'a> {
                 }
                 '.' => {
                     self.pos += 1;
-                    TokenKind::Dot
+                    if self.peek_char() == Some('.') {
+                        self.pos += 1;
+                        if self.peek_char() == Some('=') {
+                            self.pos += 1;
+                            TokenKind::DotDotEq
+                        } else {
+                            TokenKind::DotDot
+                        }
+                    } else {
+                        TokenKind::Dot
+                    }
                 }
                 '?' => {
                     self.pos += 1;
@@ -248,26 +295,70 @@ impl<'a> Lexer<'a> {
                     if self.peek_char() == Some('>') {
                         self.pos += 1;
                         TokenKind::Arrow
+                    } else if self.peek_char() == Some('=') {
+                        self.pos += 1;
+                        TokenKind::MinusEq
                     } else {
                         TokenKind::Minus
                     }
                 }
                 '+' => {
                     self.pos += 1;
-                    TokenKind::Plus
+                    if self.peek_char() == Some('=') {
+                        self.pos += 1;
+                        TokenKind::PlusEq
+                    } else {
+                        TokenKind::Plus
+                    }
                 }
                 '*' => {
                     self.pos += 1;
-                    TokenKind::Star
+                    if self.peek_char() == Some('=') {
+                        self.pos += 1;
+                        TokenKind::StarEq
+                    } else {
+                        TokenKind::Star
+                    }
                 }
                 '/' => {
                     self.pos += 1;
-                    TokenKind::Slash
+                    if self.peek_char() == Some('=') {
+                        self.pos += 1;
+                        TokenKind::SlashEq
+                    } else {
+                        TokenKind::Slash
+                    }
                 }
                 '%' => {
                     self.pos += 1;
                     TokenKind::Percent
                 }
+                '|' => {
+                    self.pos += 1;
+                    if self.peek_char() == Some('|') {
+                        self.pos += 1;
+                        TokenKind::PipePipe
+                    } else {
+                        TokenKind::Pipe
+                    }
+                }
+                '&' => {
+                    self.pos += 1;
+                    if self.peek_char() == Some('&') {
+                        self.pos += 1;
+                        TokenKind::AmpAmp
+                    } else {
+                        TokenKind::Amp
+                    }
+                }
+                '^' => {
+                    self.pos += 1;
+                    TokenKind::Caret
+                }
+                '~' => {
+                    self.pos += 1;
+                    TokenKind::Tilde
+                }
                 '!' => {
                     self.pos += 1;
                     if self.peek_char() == Some('=') {
@@ -282,6 +373,9 @@ impl<'a> Lexer<'a> {
                     if self.peek_char() == Some('=') {
                         self.pos += 1;
                         TokenKind::Le
+                    } else if self.peek_char() == Some('<') {
+                        self.pos += 1;
+                        TokenKind::Shl
                     } else {
                         TokenKind::Lt
                     }
@@ -291,6 +385,9 @@ impl<'a> Lexer<'a> {
                     if self.peek_char() == Some('=') {
                         self.pos += 1;
                         TokenKind::Ge
+                    } else if self.peek_char() == Some('>') {
+                        self.pos += 1;
+                        TokenKind::Shr
                     } else {
                         TokenKind::Gt
                     }
@@ -450,63 +547,233 @@ impl<'a> Lexer<'a> {
 
     fn lex_number(&mut self) -> Token {
         let start = self.pos;
-        self.pos += 1;
-        while self.pos < self.bytes.len() {
-            let ch = self.bytes[self.pos] as char;
-            if ch.is_ascii_digit() || ch == '_' {
+        if self.current_char() == '0' && matches!(self.char_at(self.pos + 1), Some('x' | 'o' | 'b'))
+        {
+            self.pos += 2;
+            while self
+                .peek_char()
+                .is_some_and(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+            {
                 self.pos += 1;
-            } else {
-                break;
             }
+            self.follower = false;
+            return Token {
+                kind: TokenKind::Int(SmolStr::new(&self.src[start..self.pos])),
+                span: Span::new(self.source_id, start, self.pos),
+            };
         }
-        let slice = &self.src[start..self.pos];
+        self.pos += 1;
+        self.skip_digits();
+        let mut is_float = false;
+        if self.char_at(self.pos) == Some('.')
+            && self
+                .char_at(self.pos + 1)
+                .is_some_and(|ch| ch.is_ascii_digit())
+        {
+            is_float = true;
+            self.pos += 1;
+            self.skip_digits();
+        }
+        if matches!(self.peek_char(), Some('e' | 'E')) {
+            let digits_at = match self.char_at(self.pos + 1) {
+                Some('+' | '-') => self.pos + 2,
+                _ => self.pos + 1,
+            };
+            if self
+                .char_at(digits_at)
+                .is_some_and(|ch| ch.is_ascii_digit())
+            {
+                is_float = true;
+                self.pos = digits_at;
+                self.skip_digits();
+            }
+        }
+        let slice = SmolStr::new(&self.src[start..self.pos]);
         let span = Span::new(self.source_id, start, self.pos);
         self.follower = false;
-        Token {
-            kind: TokenKind::Int(SmolStr::new(slice)),
-            span,
+        let kind = if is_float {
+            TokenKind::Float(slice)
+        } else {
+            TokenKind::Int(slice)
+        };
+        Token { kind, span }
+    }
+
+    fn skip_digits(&mut self) {
+        while self
+            .peek_char()
+            .is_some_and(|ch| ch.is_ascii_digit() || ch == '_')
+        {
+            self.pos += 1;
         }
     }
 
     fn lex_string(&mut self) -> Option<Token> {
         let start = self.pos;
-        self.pos += 1;
+        let prefix = self.current_char();
+        if prefix != '"' {
+            self.pos += 1;
+        }
+        let triple = self.src[self.pos..].starts_with("\"\"\"");
+        let quote_len = if triple { 3 } else { 1 };
+        self.pos += quote_len;
+        let mut parts = Vec::new();
         let mut value = String::new();
-        while self.pos < self.bytes.len() {
-            let ch = self.bytes[self.pos] as char;
-            if ch == '"' {
-                self.pos += 1;
+        loop {
+            let Some(ch) = self.src[self.pos..].chars().next() else {
                 let span = Span::new(self.source_id, start, self.pos);
-                self.follower = false;
-                return Some(Token {
-                    kind: TokenKind::String(SmolStr::from(value)),
-                    span,
-                });
+                self.diagnostics
+                    .push(Diagnostic::error("unterminated string literal", span));
+                return None;
+            };
+            if ch == '"' && (!triple || self.src[self.pos..].starts_with("\"\"\"")) {
+                self.pos += quote_len;
+                break;
             }
-            if ch == '\\' {
-                self.pos += 1;
-                if self.pos >= self.bytes.len() {
-                    break;
-                }
-                let escape = self.bytes[self.pos] as char;
-                value.push(match escape {
-                    'n' => '\n',
-                    't' => '\t',
-                    'r' => '\r',
-                    '"' => '"',
-                    '\\' => '\\',
-                    other => other,
-                });
-                self.pos += 1;
-                continue;
+            match ch {
+                '\\' if prefix != 'r' => self.lex_escape(&mut value),
+                '{' if prefix == 'f' => {
+                    if self.char_at(self.pos + 1) == Some('{') {
+                        value.push('{');
+                        self.pos += 2;
+                        continue;
+                    }
+                    if !value.is_empty() {
+                        parts.push(FStringPart::Text(SmolStr::from(std::mem::take(&mut value))));
+                    }
+                    if let Some(tokens) = self.lex_interpolation() {
+                        parts.push(FStringPart::Expr(tokens));
+                    }
+                }
+                '}' if prefix == 'f' => {
+                    if self.char_at(self.pos + 1) == Some('}') {
+                        value.push('}');
+                        self.pos += 2;
+                        continue;
+                    }
+                    let span = Span::new(self.source_id, self.pos, self.pos + 1);
+                    self.diagnostics.push(
+                        Diagnostic::error("single `}` in f-string", span)
+                            .with_note("use `}}` for a literal brace"),
+                    );
+                    self.pos += 1;
+                }
+                _ => {
+                    value.push(ch);
+                    self.pos += ch.len_utf8();
+                }
             }
-            value.push(ch);
-            self.pos += 1;
         }
         let span = Span::new(self.source_id, start, self.pos);
-        self.diagnostics
-            .push(Diagnostic::error("unterminated string literal", span));
-        None
+        self.follower = false;
+        let kind = if prefix == 'f' {
+            if !value.is_empty() {
+                parts.push(FStringPart::Text(SmolStr::from(value)));
+            }
+            TokenKind::FString(parts)
+        } else {
+            TokenKind::String(SmolStr::from(value))
+        };
+        Some(Token { kind, span })
+    }
+
+    fn lex_escape(&mut self, value: &mut String) {
+        let start = self.pos;
+        self.pos += 1;
+        let Some(escape) = self.src[self.pos..].chars().next() else {
+            return;
+        };
+        self.pos += escape.len_utf8();
+        let ch = match escape {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '"' => '"',
+            '\'' => '\'',
+            '\\' => '\\',
+            'u' => match self.lex_unicode_escape() {
+                Some(ch) => ch,
+                None => {
+                    let span = Span::new(self.source_id, start, self.pos);
+                    self.diagnostics.push(
+                        Diagnostic::error("invalid unicode escape", span)
+                            .with_note("expected `\\u{...}` with 1 to 6 hex digits"),
+                    );
+                    return;
+                }
+            },
+            other => {
+                let span = Span::new(self.source_id, start, self.pos);
+                self.diagnostics.push(
+                    Diagnostic::error(format!("unknown escape sequence `\\{other}`"), span)
+                        .with_note("use `\\\\` for a literal backslash"),
+                );
+                return;
+            }
+        };
+        value.push(ch);
+    }
+
+    fn lex_unicode_escape(&mut self) -> Option<char> {
+        if self.peek_char() != Some('{') {
+            return None;
+        }
+        let digits_start = self.pos + 1;
+        let mut end = digits_start;
+        while self.char_at(end).is_some_and(|ch| ch.is_ascii_hexdigit()) {
+            end += 1;
+        }
+        if self.char_at(end) != Some('}') {
+            return None;
+        }
+        self.pos = end + 1;
+        let digits = &self.src[digits_start..end];
+        if digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+
+    fn lex_interpolation(&mut self) -> Option<Vec<Token>> {
+        let open = self.pos;
+        self.pos += 1;
+        let expr_start = self.pos;
+        let mut depth = 0usize;
+        loop {
+            match self.peek_char() {
+                Some('{') => depth += 1,
+                Some('}') if depth == 0 => break,
+                Some('}') => depth -= 1,
+                Some('"') | None => {
+                    let span = Span::new(self.source_id, open, self.pos);
+                    self.diagnostics.push(
+                        Diagnostic::error("unterminated interpolation in f-string", span)
+                            .with_note("expected `}`"),
+                    );
+                    return None;
+                }
+                Some(_) => {}
+            }
+            self.pos += 1;
+        }
+        let expr_end = self.pos;
+        self.pos += 1;
+        if self.src[expr_start..expr_end].trim().is_empty() {
+            let span = Span::new(self.source_id, open, self.pos);
+            self.diagnostics
+                .push(Diagnostic::error("empty interpolation in f-string", span));
+            return None;
+        }
+        let mut inner = Lexer::new(&self.src[..expr_end], self.source_id);
+        inner.pos = expr_start;
+        inner.nesting = 1;
+        inner.line_start = false;
+        let (tokens, diagnostics) = inner.lex();
+        self.diagnostics.extend(diagnostics);
+        Some(tokens)
     }
 
     fn skip_comment(&mut self) {
@@ -532,7 +799,11 @@ impl<'a> Lexer<'a> {
     }
 
     fn peek_char(&self) -> Option<char> {
-        self.bytes.get(self.pos + 1).map(|b| *b as char)
+        self.bytes.get(self.pos).map(|b| *b as char)
+    }
+
+    fn char_at(&self, pos: usize) -> Option<char> {
+        self.bytes.get(pos).map(|b| *b as char)
     }
 }
 
@@ -548,16 +819,20 @@ fn keyword(text: &str) -> Option<Keyword> {
     match text {
         "fn" => Some(Keyword::Fn),
         "let" => Some(Keyword::Let),
+        "mut" => Some(Keyword::Mut),
         "return" => Some(Keyword::Return),
         "if" => Some(Keyword::If),
         "elif" => Some(Keyword::Elif),
         "else" => Some(Keyword::Else),
         "while" => Some(Keyword::While),
+        "match" => Some(Keyword::Match),
         "true" => Some(Keyword::True),
         "false" => Some(Keyword::False),
         "use" => Some(Keyword::Use),
+        "pub" => Some(Keyword::Pub),
         "struct" => Some(Keyword::Struct),
         "enum" => Some(Keyword::Enum),
+        "impl" => Some(Keyword::Impl),
         "for" => Some(Keyword::For),
         "in" => Some(Keyword::In),
         "break" => Some(Keyword::Break),
@@ -573,14 +848,27 @@ fn is_follower(kind: &TokenKind) -> bool {
     matches!(
         kind,
         TokenKind::Dot
+            | TokenKind::DotDot
+            | TokenKind::DotDotEq
             | TokenKind::Question
             | TokenKind::DoubleColon
             | TokenKind::Equal
+            | TokenKind::PlusEq
+            | TokenKind::MinusEq
+            | TokenKind::StarEq
+            | TokenKind::SlashEq
             | TokenKind::Plus
             | TokenKind::Minus
             | TokenKind::Star
             | TokenKind::Slash
             | TokenKind::Percent
+            | TokenKind::Pipe
+            | TokenKind::PipePipe
+            | TokenKind::Amp
+            | TokenKind::AmpAmp
+            | TokenKind::Caret
+            | TokenKind::Shl
+            | TokenKind::Shr
             | TokenKind::Arrow
             | TokenKind::FatArrow
     )
@@ -4,11 +4,12 @@ pub mod hir;
 pub mod interner;
 pub mod lexer;
 pub mod lowering;
+pub mod modules;
 pub mod parser;
 pub mod source;
 pub mod span;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use diagnostics::Diagnostic;
 use hir::HirModule;
@@ -28,45 +29,35 @@ pub enum FrontendError {
 }
 
 pub fn parse_to_hir(path: &Path) -> Result<ParseOutput, FrontendError> {
-    let mut source_map = SourceMap::new();
-    let text = std::fs::read_to_string(path)?;
-    let source_id = source_map.add_source(path.to_path_buf(), text.clone());
-    let mut diagnostics = Vec::new();
-    let (tokens, mut lex_diags) = lexer::lex(&text, source_id);
-    diagnostics.append(&mut lex_diags);
-    let mut parser = parser::Parser::new(tokens, source_id);
-    let ast_module = parser.parse_module();
-    diagnostics.extend(parser.into_diagnostics());
-    let mut hir_builder = lowering::LoweringContext::new(source_map.clone());
-    let module = hir_builder.lower_module(ast_module);
-    diagnostics.extend(hir_builder.into_diagnostics());
-    Ok(ParseOutput {
-        module,
-        diagnostics,
-        source_map,
+    parse_program(path, &[])
+}
+
+/// Like [`parse_to_hir`], but also resolves `use` declarations against the
+/// given source roots.
+pub fn parse_program(path: &Path, source_roots: &[PathBuf]) -> Result<ParseOutput, FrontendError> {
+    modules::load_program(path, source_roots, &mut |path| {
+        std::fs::read_to_string(path)
     })
 }
 
 pub mod tests_support {
     use super::*;
-    use std::path::PathBuf;
+    use std::io;
 
     pub fn parse_str(name: &str, source: &str) -> ParseOutput {
-        let mut source_map = SourceMap::new();
-        let source_id = source_map.add_source(PathBuf::from(name), source.to_string());
-        let (tokens, mut lex_diags) = crate::lexer::lex(source, source_id);
-        let mut diagnostics = Vec::new();
-        diagnostics.append(&mut lex_diags);
-        let mut parser = crate::parser::Parser::new(tokens, source_id);
-        let ast_module = parser.parse_module();
-        diagnostics.extend(parser.into_diagnostics());
-        let mut lowering = crate::lowering::LoweringContext::new(source_map.clone());
-        let module = lowering.lower_module(ast_module);
-        diagnostics.extend(lowering.into_diagnostics());
-        ParseOutput {
-            module,
-            diagnostics,
-            source_map,
-        }
+        parse_files(name, &[(name, source)])
+    }
+
+    /// Parses `root` from in-memory `(file name, source)` pairs, resolving
+    /// `use` declarations against the other entries.
+    pub fn parse_files(root: &str, files: &[(&str, &str)]) -> ParseOutput {
+        let mut read = |path: &Path| {
+            files
+                .iter()
+                .find(|(name, _)| Path::new(name) == path)
+                .map(|(_, source)| source.to_string())
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        };
+        modules::load_program(Path::new(root), &[], &mut read).expect("root source is provided")
     }
 }
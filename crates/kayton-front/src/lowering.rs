@@ -2,13 +2,18 @@ use crate::ast::*;
 use crate::diagnostics::Diagnostic;
 use crate::hir::*;
 use crate::interner::{Symbol, SymbolInterner};
+use crate::modules::ModuleScope;
 use crate::source::SourceMap;
+use crate::span::Span;
 use smol_str::SmolStr;
 
 pub struct LoweringContext {
     interner: SymbolInterner,
     ids: HirIdAllocator,
     diagnostics: Vec<Diagnostic>,
+    scope: ModuleScope,
+    locals: Vec<Vec<Symbol>>,
+    type_params: Vec<SmolStr>,
     _source_map: SourceMap,
 }
 
@@ -18,18 +23,31 @@ impl LoweringContext {
             interner: SymbolInterner::new(),
             ids: HirIdAllocator::new(),
             diagnostics: Vec::new(),
+            scope: ModuleScope::default(),
+            locals: Vec::new(),
+            type_params: Vec::new(),
             _source_map: source_map,
         }
     }
 
     pub fn lower_module(&mut self, module: Module) -> HirModule {
+        self.lower_program(vec![(module, ModuleScope::default())])
+    }
+
+    /// Lowers several source modules into one HIR module. Items of modules
+    /// with a prefix are renamed to `prefix::name`.
+    pub fn lower_program(&mut self, modules: Vec<(Module, ModuleScope)>) -> HirModule {
         let module_id = self.ids.alloc();
         let mut items = Vec::new();
-        for item in module.items {
-            if let Some(hir_item) = self.lower_item(item) {
-                items.push(hir_item);
+        for (module, scope) in modules {
+            self.scope = scope;
+            for item in module.items {
+                if let Some(hir_item) = self.lower_item(item) {
+                    items.push(hir_item);
+                }
             }
         }
+        self.scope = ModuleScope::default();
         let interner = std::mem::take(&mut self.interner);
         HirModule {
             id: module_id,
@@ -44,40 +62,185 @@ impl LoweringContext {
 
     fn lower_item(&mut self, item: Item) -> Option<HirItem> {
         match item {
-            Item::Let(let_stmt) => Some(HirItem::Let(self.lower_let(let_stmt))),
-            Item::Function(func) => Some(HirItem::Function(self.lower_function(func))),
+            Item::Let(let_stmt) => {
+                let mut binding = self.lower_let(let_stmt);
+                self.qualify_pattern(&mut binding.pattern);
+                Some(HirItem::Let(binding))
+            }
+            Item::Function(func) => {
+                let mut func = self.lower_function(func);
+                func.name = self.qualify(func.name);
+                Some(HirItem::Function(func))
+            }
+            Item::Struct(decl) => {
+                let mut decl = self.lower_struct(decl);
+                decl.name = self.qualify(decl.name);
+                Some(HirItem::Struct(decl))
+            }
+            Item::Enum(decl) => {
+                let mut decl = self.lower_enum(decl);
+                decl.name = self.qualify(decl.name);
+                Some(HirItem::Enum(decl))
+            }
+            Item::Use(_) => None,
+            Item::Impl(block) => Some(HirItem::Impl(HirImpl {
+                id: self.ids.alloc(),
+                ty: {
+                    let ty = self.resolve_type_name(block.ty);
+                    self.lower_name(ty)
+                },
+                methods: block
+                    .methods
+                    .into_iter()
+                    .map(|method| self.lower_function(method))
+                    .collect(),
+                span: block.span,
+            })),
         }
     }
 
     fn lower_function(&mut self, func: Function) -> HirFunction {
         let id = self.ids.alloc();
         let name = self.intern(func.name);
-        let params = func
-            .params
+        let type_params = self.lower_type_params(func.type_params);
+        let params = self.lower_params(func.params);
+        let return_ty = func.return_ty.map(|ty| self.lower_type(ty));
+        self.locals
+            .push(params.iter().map(|param| param.name).collect());
+        let body = self.lower_block(func.body);
+        self.locals.pop();
+        self.type_params
+            .truncate(self.type_params.len() - type_params.len());
+        HirFunction {
+            id,
+            name,
+            type_params,
+            params,
+            return_ty,
+            body,
+            span: func.span,
+        }
+    }
+
+    fn lower_type_params(&mut self, params: Vec<NameRef>) -> Vec<HirNameRef> {
+        self.type_params
+            .extend(params.iter().map(|param| param.name.clone()));
+        params
+            .into_iter()
+            .map(|param| self.lower_name(param))
+            .collect()
+    }
+
+    fn lower_params(&mut self, params: Vec<Parameter>) -> Vec<HirParam> {
+        params
             .into_iter()
             .map(|param| HirParam {
                 id: self.ids.alloc(),
                 name: self.intern(param.name),
+                ty: param.ty.map(|ty| self.lower_type(ty)),
                 span: param.span,
             })
+            .collect()
+    }
+
+    fn lower_struct(&mut self, decl: StructDecl) -> HirStruct {
+        let id = self.ids.alloc();
+        let name = self.intern(decl.name);
+        let type_params = self.lower_type_params(decl.type_params);
+        let fields = decl
+            .fields
+            .into_iter()
+            .map(|field| HirFieldDecl {
+                id: self.ids.alloc(),
+                name: self.intern(field.name),
+                ty: self.lower_type(field.ty),
+                span: field.span,
+            })
             .collect();
-        let body = self.lower_block(func.body);
-        HirFunction {
+        self.type_params
+            .truncate(self.type_params.len() - type_params.len());
+        HirStruct {
             id,
             name,
-            params,
-            body,
-            span: func.span,
+            type_params,
+            fields,
+            span: decl.span,
+        }
+    }
+
+    fn lower_enum(&mut self, decl: EnumDecl) -> HirEnum {
+        let id = self.ids.alloc();
+        let name = self.intern(decl.name);
+        let variants = decl
+            .variants
+            .into_iter()
+            .map(|variant| HirVariantDecl {
+                id: self.ids.alloc(),
+                name: self.intern(variant.name),
+                fields: variant
+                    .fields
+                    .into_iter()
+                    .map(|field| HirVariantField {
+                        id: self.ids.alloc(),
+                        name: self.intern(field.name),
+                        ty: field.ty.map(|ty| self.lower_type(ty)),
+                        span: field.span,
+                    })
+                    .collect(),
+                span: variant.span,
+            })
+            .collect();
+        HirEnum {
+            id,
+            name,
+            variants,
+            span: decl.span,
+        }
+    }
+
+    fn lower_type(&mut self, ty: TypeExpr) -> HirType {
+        match ty {
+            TypeExpr::Name(name) => {
+                let name = self.resolve_type_name(name);
+                HirType::Named(HirNamedType {
+                    name: self.intern(name.name),
+                    args: Vec::new(),
+                    span: name.span,
+                })
+            }
+            TypeExpr::Generic(generic) => HirType::Named(HirNamedType {
+                name: self.intern(self.resolve_type_name(generic.name).name),
+                args: self.lower_types(generic.args),
+                span: generic.span,
+            }),
+            TypeExpr::Tuple(tuple) => HirType::Tuple(HirTupleType {
+                elements: self.lower_types(tuple.elements),
+                span: tuple.span,
+            }),
+            TypeExpr::Function(function) => HirType::Function(HirFunctionType {
+                params: self.lower_types(function.params),
+                return_ty: function.return_ty.map(|ty| Box::new(self.lower_type(*ty))),
+                span: function.span,
+            }),
+            TypeExpr::Unit(span) => HirType::Unit(span),
         }
     }
 
+    fn lower_types(&mut self, types: Vec<TypeExpr>) -> Vec<HirType> {
+        types.into_iter().map(|ty| self.lower_type(ty)).collect()
+    }
+
     fn lower_let(&mut self, let_stmt: LetStatement) -> HirLetBinding {
         let id = self.ids.alloc();
-        let name = self.intern(let_stmt.name);
+        let pattern = self.lower_pattern(let_stmt.pattern);
+        let ty = let_stmt.ty.map(|ty| self.lower_type(ty));
         let value = self.lower_expr(let_stmt.value);
+        self.declare_pattern(&pattern);
         HirLetBinding {
             id,
-            name,
+            pattern,
+            mutable: let_stmt.mutable,
+            ty,
             value,
             span: let_stmt.span,
         }
@@ -85,12 +248,14 @@ impl LoweringContext {
 
     fn lower_block(&mut self, block: Block) -> HirBlock {
         let id = self.ids.alloc();
+        self.locals.push(Vec::new());
         let statements = block
             .statements
             .into_iter()
             .map(|stmt| self.lower_stmt(stmt))
             .collect();
         let tail = block.tail.map(|expr| Box::new(self.lower_expr(*expr)));
+        self.locals.pop();
         HirBlock {
             id,
             statements,
@@ -102,12 +267,40 @@ impl LoweringContext {
     fn lower_stmt(&mut self, stmt: Stmt) -> HirStmt {
         match stmt {
             Stmt::Let(let_stmt) => HirStmt::Let(self.lower_let(let_stmt)),
+            Stmt::Assign(assign) => HirStmt::Assign(self.lower_assign(assign)),
             Stmt::Return(ret_stmt) => HirStmt::Return(self.lower_return(ret_stmt)),
-            Stmt::While(while_stmt) => HirStmt::While(self.lower_while(while_stmt)),
+            Stmt::Break(break_stmt) => HirStmt::Break(HirBreak {
+                id: self.ids.alloc(),
+                value: break_stmt.value.map(|expr| Box::new(self.lower_expr(expr))),
+                span: break_stmt.span,
+            }),
+            Stmt::Continue(cont) => HirStmt::Continue(HirContinue {
+                id: self.ids.alloc(),
+                span: cont.span,
+            }),
             Stmt::Expr(expr) => HirStmt::Expr(self.lower_expr(expr)),
         }
     }
 
+    fn lower_assign(&mut self, assign: AssignStatement) -> HirAssign {
+        let id = self.ids.alloc();
+        let target = match assign.target {
+            AssignTarget::Name(name) => {
+                let name = self.resolve_name(name);
+                HirAssignTarget::Name(self.lower_name(name))
+            }
+            AssignTarget::Field(field) => HirAssignTarget::Field(self.lower_field(field)),
+            AssignTarget::Index(index) => HirAssignTarget::Index(self.lower_index(index)),
+        };
+        HirAssign {
+            id,
+            target,
+            op: assign.op.map(lower_binary_op),
+            value: Box::new(self.lower_expr(assign.value)),
+            span: assign.span,
+        }
+    }
+
     fn lower_return(&mut self, ret: ReturnStatement) -> HirReturn {
         let id = self.ids.alloc();
         let value = ret.value.map(|expr| Box::new(self.lower_expr(expr)));
@@ -118,28 +311,181 @@ impl LoweringContext {
         }
     }
 
-    fn lower_while(&mut self, while_stmt: WhileStatement) -> HirWhile {
+    fn lower_while(&mut self, while_expr: WhileExpr) -> HirWhile {
         let id = self.ids.alloc();
-        let condition = Box::new(self.lower_expr(while_stmt.condition));
-        let body = Box::new(self.lower_block(*while_stmt.body));
+        let condition = Box::new(self.lower_expr(while_expr.condition));
+        let body = Box::new(self.lower_block(*while_expr.body));
         HirWhile {
             id,
             condition,
             body,
-            span: while_stmt.span,
+            span: while_expr.span,
+        }
+    }
+
+    fn lower_for(&mut self, for_expr: ForExpr) -> HirFor {
+        let id = self.ids.alloc();
+        let pattern = self.lower_pattern(for_expr.pattern);
+        let iterable = Box::new(self.lower_expr(for_expr.iterable));
+        self.locals.push(Vec::new());
+        self.declare_pattern(&pattern);
+        let body = Box::new(self.lower_block(*for_expr.body));
+        self.locals.pop();
+        HirFor {
+            id,
+            pattern,
+            iterable,
+            body,
+            span: for_expr.span,
+        }
+    }
+
+    fn lower_struct_literal(&mut self, lit: StructLiteral) -> HirStructLiteral {
+        HirStructLiteral {
+            id: self.ids.alloc(),
+            name: {
+                let name = self.resolve_type_name(lit.name);
+                self.lower_name(name)
+            },
+            fields: lit
+                .fields
+                .into_iter()
+                .map(|field| HirFieldInit {
+                    id: self.ids.alloc(),
+                    name: self.lower_name(field.name),
+                    value: self.lower_expr(field.value),
+                    span: field.span,
+                })
+                .collect(),
+            span: lit.span,
+        }
+    }
+
+    fn lower_field(&mut self, field: FieldExpr) -> HirFieldAccess {
+        HirFieldAccess {
+            id: self.ids.alloc(),
+            base: Box::new(self.lower_expr(*field.base)),
+            field: self.lower_name(field.field),
+            span: field.span,
+        }
+    }
+
+    fn lower_index(&mut self, index: IndexExpr) -> HirIndex {
+        HirIndex {
+            id: self.ids.alloc(),
+            base: Box::new(self.lower_expr(*index.base)),
+            index: Box::new(self.lower_expr(*index.index)),
+            span: index.span,
+        }
+    }
+
+    fn lower_path(&mut self, path: PathExpr) -> HirPath {
+        let id = self.ids.alloc();
+        let segments = self.resolve_path(path.segments);
+        HirPath {
+            id,
+            segments: segments
+                .into_iter()
+                .map(|segment| self.lower_name(segment))
+                .collect(),
+            span: path.span,
+        }
+    }
+
+    fn lower_range(&mut self, range: RangeExpr) -> HirRange {
+        HirRange {
+            id: self.ids.alloc(),
+            start: Box::new(self.lower_expr(*range.start)),
+            end: Box::new(self.lower_expr(*range.end)),
+            inclusive: range.inclusive,
+            span: range.span,
         }
     }
 
     fn lower_expr(&mut self, expr: Expr) -> HirExpr {
         match expr {
             Expr::Literal(lit) => HirExpr::Literal(self.lower_literal(lit)),
-            Expr::Name(name) => HirExpr::Name(self.lower_name(name)),
+            Expr::FString(fstring) => self.lower_fstring(fstring),
+            Expr::Name(name) => {
+                let name = self.resolve_name(name);
+                HirExpr::Name(self.lower_name(name))
+            }
             Expr::Call(call) => HirExpr::Call(self.lower_call(call)),
+            Expr::MethodCall(call) => HirExpr::MethodCall(HirMethodCall {
+                id: self.ids.alloc(),
+                receiver: Box::new(self.lower_expr(*call.receiver)),
+                method: self.lower_name(call.method),
+                args: call
+                    .args
+                    .into_iter()
+                    .map(|arg| self.lower_expr(arg))
+                    .collect(),
+                span: call.span,
+            }),
             Expr::If(if_expr) => HirExpr::If(Box::new(self.lower_if(*if_expr))),
+            Expr::IfLet(if_let) => HirExpr::Match(Box::new(self.lower_if_let(*if_let))),
+            Expr::Match(match_expr) => HirExpr::Match(Box::new(self.lower_match(*match_expr))),
+            Expr::While(while_expr) => HirExpr::While(Box::new(self.lower_while(*while_expr))),
+            Expr::For(for_expr) => HirExpr::For(Box::new(self.lower_for(*for_expr))),
+            Expr::Range(range) => HirExpr::Range(self.lower_range(range)),
+            Expr::StructLit(lit) => HirExpr::StructLit(self.lower_struct_literal(lit)),
+            Expr::Field(field) => HirExpr::Field(self.lower_field(field)),
+            Expr::List(list) => HirExpr::List(HirList {
+                id: self.ids.alloc(),
+                elements: list
+                    .elements
+                    .into_iter()
+                    .map(|element| self.lower_expr(element))
+                    .collect(),
+                span: list.span,
+            }),
+            Expr::Tuple(tuple) => HirExpr::Tuple(HirTuple {
+                id: self.ids.alloc(),
+                elements: tuple
+                    .elements
+                    .into_iter()
+                    .map(|element| self.lower_expr(element))
+                    .collect(),
+                span: tuple.span,
+            }),
+            Expr::Index(index) => HirExpr::Index(self.lower_index(index)),
+            Expr::Path(path) => {
+                let mut path = self.lower_path(path);
+                match path.segments.len() {
+                    1 => HirExpr::Name(path.segments.remove(0)),
+                    _ => HirExpr::Path(path),
+                }
+            }
+            Expr::Lambda(lambda) => {
+                let id = self.ids.alloc();
+                let params = self.lower_params(lambda.params);
+                self.locals
+                    .push(params.iter().map(|param| param.name).collect());
+                let body = Box::new(self.lower_expr(*lambda.body));
+                self.locals.pop();
+                HirExpr::Lambda(HirLambda {
+                    id,
+                    params,
+                    body,
+                    span: lambda.span,
+                })
+            }
             Expr::Block(block) => HirExpr::Block(Box::new(self.lower_block(*block))),
             Expr::Paren(inner) => self.lower_expr(*inner),
             Expr::Binary(bin) => HirExpr::Binary(self.lower_binary(bin)),
-            Expr::Unary(unary) => HirExpr::Unary(self.lower_unary(unary)),
+            Expr::Unary(unary) => match (unary.op, *unary.expr) {
+                (UnaryOp::Neg, Expr::Literal(Literal::Int(int))) => {
+                    HirExpr::Literal(self.lower_literal(Literal::Int(IntLiteral {
+                        span: unary.span,
+                        value: SmolStr::from(format!("-{}", int.value)),
+                    })))
+                }
+                (op, expr) => HirExpr::Unary(self.lower_unary(UnaryExpr {
+                    span: unary.span,
+                    op,
+                    expr: Box::new(expr),
+                })),
+            },
         }
     }
 
@@ -147,9 +493,20 @@ impl LoweringContext {
         match literal {
             Literal::Int(int) => HirLiteral::Int(HirIntLiteral {
                 id: self.ids.alloc(),
-                value: int.value.to_string(),
+                value: match parse_int_literal(&int.value, int.span) {
+                    Ok(value) => value,
+                    Err(diag) => {
+                        self.diagnostics.push(diag);
+                        0
+                    }
+                },
                 span: int.span,
             }),
+            Literal::Float(float) => HirLiteral::Float(HirFloatLiteral {
+                id: self.ids.alloc(),
+                value: float.value.to_string(),
+                span: float.span,
+            }),
             Literal::String(string) => HirLiteral::String(HirStringLiteral {
                 id: self.ids.alloc(),
                 value: string.value.to_string(),
@@ -200,33 +557,182 @@ impl LoweringContext {
         }
     }
 
+    fn lower_match(&mut self, match_expr: MatchExpr) -> HirMatch {
+        HirMatch {
+            id: self.ids.alloc(),
+            scrutinee: Box::new(self.lower_expr(*match_expr.scrutinee)),
+            arms: match_expr
+                .arms
+                .into_iter()
+                .map(|arm| self.lower_match_arm(arm))
+                .collect(),
+            source: HirMatchSource::Match,
+            span: match_expr.span,
+        }
+    }
+
+    fn lower_if_let(&mut self, if_let: IfLetExpr) -> HirMatch {
+        let id = self.ids.alloc();
+        let scrutinee = Box::new(self.lower_expr(*if_let.value));
+        let arm_id = self.ids.alloc();
+        let pattern = self.lower_pattern(if_let.pattern);
+        self.locals.push(Vec::new());
+        self.declare_pattern(&pattern);
+        let body = self.lower_block(*if_let.then_branch);
+        self.locals.pop();
+        let then_arm = HirMatchArm {
+            id: arm_id,
+            pattern,
+            guard: None,
+            body,
+            span: if_let.span,
+        };
+        let else_body = match if_let.else_branch {
+            Some(block) => self.lower_block(*block),
+            None => HirBlock {
+                id: self.ids.alloc(),
+                statements: Vec::new(),
+                tail: None,
+                span: if_let.span,
+            },
+        };
+        let else_arm = HirMatchArm {
+            id: self.ids.alloc(),
+            pattern: HirPattern::Wildcard(HirWildcardPattern {
+                id: self.ids.alloc(),
+                span: else_body.span,
+            }),
+            guard: None,
+            span: else_body.span,
+            body: else_body,
+        };
+        HirMatch {
+            id,
+            scrutinee,
+            arms: vec![then_arm, else_arm],
+            source: HirMatchSource::IfLet,
+            span: if_let.span,
+        }
+    }
+
+    fn lower_match_arm(&mut self, arm: MatchArm) -> HirMatchArm {
+        let id = self.ids.alloc();
+        let pattern = self.lower_pattern(arm.pattern);
+        self.locals.push(Vec::new());
+        self.declare_pattern(&pattern);
+        let guard = arm.guard.map(|guard| Box::new(self.lower_expr(guard)));
+        let body = self.lower_block(arm.body);
+        self.locals.pop();
+        HirMatchArm {
+            id,
+            pattern,
+            guard,
+            body,
+            span: arm.span,
+        }
+    }
+
+    fn lower_pattern(&mut self, pattern: Pattern) -> HirPattern {
+        match pattern {
+            Pattern::Wildcard(wildcard) => HirPattern::Wildcard(HirWildcardPattern {
+                id: self.ids.alloc(),
+                span: wildcard.span,
+            }),
+            Pattern::Binding(binding) => HirPattern::Binding(HirBindingPattern {
+                id: self.ids.alloc(),
+                name: self.intern(binding.name),
+                span: binding.span,
+            }),
+            Pattern::Literal(lit) => HirPattern::Literal(self.lower_literal(lit)),
+            Pattern::Or(or) => HirPattern::Or(HirOrPattern {
+                id: self.ids.alloc(),
+                alternatives: or
+                    .alternatives
+                    .into_iter()
+                    .map(|alt| self.lower_pattern(alt))
+                    .collect(),
+                span: or.span,
+            }),
+            Pattern::Variant(variant) => HirPattern::Variant(HirVariantPattern {
+                id: self.ids.alloc(),
+                path: self.lower_path(variant.path),
+                fields: variant
+                    .fields
+                    .into_iter()
+                    .map(|field| self.lower_pattern(field))
+                    .collect(),
+                span: variant.span,
+            }),
+            Pattern::Tuple(tuple) => HirPattern::Tuple(HirTuplePattern {
+                id: self.ids.alloc(),
+                elements: tuple
+                    .elements
+                    .into_iter()
+                    .map(|element| self.lower_pattern(element))
+                    .collect(),
+                span: tuple.span,
+            }),
+        }
+    }
+
     fn lower_binary(&mut self, bin: BinaryExpr) -> HirBinary {
         HirBinary {
             id: self.ids.alloc(),
-            op: match bin.op {
-                BinaryOp::Add => HirBinaryOp::Add,
-                BinaryOp::Sub => HirBinaryOp::Sub,
-                BinaryOp::Mul => HirBinaryOp::Mul,
-                BinaryOp::Div => HirBinaryOp::Div,
-                BinaryOp::Eq => HirBinaryOp::Eq,
-                BinaryOp::Ne => HirBinaryOp::Ne,
-                BinaryOp::Lt => HirBinaryOp::Lt,
-                BinaryOp::Le => HirBinaryOp::Le,
-                BinaryOp::Gt => HirBinaryOp::Gt,
-                BinaryOp::Ge => HirBinaryOp::Ge,
-            },
+            op: lower_binary_op(bin.op),
             lhs: Box::new(self.lower_expr(*bin.lhs)),
             rhs: Box::new(self.lower_expr(*bin.rhs)),
             span: bin.span,
         }
     }
 
+    fn lower_fstring(&mut self, fstring: FStringExpr) -> HirExpr {
+        let span = fstring.span;
+        let mut pieces = fstring.segments.into_iter().map(|segment| match segment {
+            FStringSegment::Text(text) => HirExpr::Literal(HirLiteral::String(HirStringLiteral {
+                id: self.ids.alloc(),
+                value: text.to_string(),
+                span,
+            })),
+            FStringSegment::Expr(expr) => {
+                let expr_span = expr.span();
+                HirExpr::Call(HirCall {
+                    id: self.ids.alloc(),
+                    callee: Box::new(HirExpr::Name(HirNameRef {
+                        id: self.ids.alloc(),
+                        name: self.interner.intern(SmolStr::new_static("str")),
+                        span: expr_span,
+                    })),
+                    args: vec![self.lower_expr(expr)],
+                    span: expr_span,
+                })
+            }
+        });
+        let Some(first) = pieces.next() else {
+            return HirExpr::Literal(HirLiteral::String(HirStringLiteral {
+                id: self.ids.alloc(),
+                value: String::new(),
+                span,
+            }));
+        };
+        let rest: Vec<HirExpr> = pieces.collect();
+        rest.into_iter().fold(first, |lhs, rhs| {
+            HirExpr::Binary(HirBinary {
+                id: self.ids.alloc(),
+                op: HirBinaryOp::Add,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                span,
+            })
+        })
+    }
+
     fn lower_unary(&mut self, unary: UnaryExpr) -> HirUnary {
         HirUnary {
             id: self.ids.alloc(),
             op: match unary.op {
                 UnaryOp::Neg => HirUnaryOp::Neg,
                 UnaryOp::Not => HirUnaryOp::Not,
+                UnaryOp::BitNot => HirUnaryOp::BitNot,
             },
             expr: Box::new(self.lower_expr(*unary.expr)),
             span: unary.span,
@@ -236,4 +742,185 @@ impl LoweringContext {
     fn intern(&mut self, name: SmolStr) -> Symbol {
         self.interner.intern(name)
     }
+
+    fn qualify(&mut self, name: Symbol) -> Symbol {
+        let Some(prefix) = &self.scope.prefix else {
+            return name;
+        };
+        let text = self.interner.resolve(name).cloned().unwrap_or_default();
+        let qualified = SmolStr::from(format!("{prefix}::{text}"));
+        self.intern(qualified)
+    }
+
+    fn qualify_pattern(&mut self, pattern: &mut HirPattern) {
+        match pattern {
+            HirPattern::Binding(binding) => binding.name = self.qualify(binding.name),
+            HirPattern::Tuple(tuple) => {
+                for element in &mut tuple.elements {
+                    self.qualify_pattern(element);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn declare_pattern(&mut self, pattern: &HirPattern) {
+        match pattern {
+            HirPattern::Binding(binding) => {
+                if let Some(scope) = self.locals.last_mut() {
+                    scope.push(binding.name);
+                }
+            }
+            HirPattern::Or(or) => {
+                if let Some(first) = or.alternatives.first() {
+                    self.declare_pattern(first);
+                }
+            }
+            HirPattern::Variant(variant) => {
+                for field in &variant.fields {
+                    self.declare_pattern(field);
+                }
+            }
+            HirPattern::Tuple(tuple) => {
+                for element in &tuple.elements {
+                    self.declare_pattern(element);
+                }
+            }
+            HirPattern::Wildcard(_) | HirPattern::Literal(_) => {}
+        }
+    }
+
+    /// Rewrites a value name that refers to a module item to its qualified
+    /// name, unless a local binding shadows it.
+    fn resolve_name(&mut self, name: NameRef) -> NameRef {
+        let Some(qualified) = self.scope.aliases.get(&name.name).cloned() else {
+            return name;
+        };
+        let symbol = self.intern(name.name.clone());
+        if self.locals.iter().any(|scope| scope.contains(&symbol)) {
+            return name;
+        }
+        NameRef {
+            span: name.span,
+            name: qualified,
+        }
+    }
+
+    fn resolve_type_name(&self, name: NameRef) -> NameRef {
+        if self.type_params.contains(&name.name) {
+            return name;
+        }
+        match self.scope.aliases.get(&name.name) {
+            Some(qualified) => NameRef {
+                span: name.span,
+                name: qualified.clone(),
+            },
+            None => name,
+        }
+    }
+
+    fn resolve_path(&mut self, mut segments: Vec<NameRef>) -> Vec<NameRef> {
+        let module = match segments.first() {
+            Some(first) if segments.len() > 1 => self.scope.modules.get(&first.name).cloned(),
+            _ => None,
+        };
+        let Some(module) = module else {
+            return match segments.first() {
+                Some(_) => {
+                    let first = segments.remove(0);
+                    let mut resolved = vec![self.resolve_type_name(first)];
+                    resolved.extend(segments);
+                    resolved
+                }
+                None => segments,
+            };
+        };
+        let mut rest = segments.split_off(1);
+        let item = rest.remove(0);
+        match module.items.get(&item.name) {
+            None => self.diagnostics.push(Diagnostic::error(
+                format!("module `{}` has no item `{}`", module.name, item.name),
+                item.span,
+            )),
+            Some(false) => self.diagnostics.push(
+                Diagnostic::error(
+                    format!("`{}` is private to module `{}`", item.name, module.name),
+                    item.span,
+                )
+                .with_note("declare it with `pub` to use it from other modules"),
+            ),
+            Some(true) => {}
+        }
+        let mut resolved = vec![NameRef {
+            span: segments[0].span.merge(item.span),
+            name: module.qualify(&item.name),
+        }];
+        resolved.extend(rest);
+        resolved
+    }
+}
+
+fn parse_int_literal(text: &str, span: Span) -> Result<i64, Diagnostic> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (radix, kind, digits) = match text.get(..2) {
+        Some("0x") => (16, "hexadecimal", &text[2..]),
+        Some("0o") => (8, "octal", &text[2..]),
+        Some("0b") => (2, "binary", &text[2..]),
+        _ => (10, "decimal", text),
+    };
+    let digits: String = digits.chars().filter(|ch| *ch != '_').collect();
+    if digits.is_empty() {
+        return Err(Diagnostic::error(
+            format!("missing digits in {kind} literal"),
+            span,
+        ));
+    }
+    if let Some(bad) = digits.chars().find(|ch| !ch.is_digit(radix)) {
+        return Err(Diagnostic::error(
+            format!("invalid digit `{bad}` in {kind} literal"),
+            span,
+        ));
+    }
+    u64::from_str_radix(&digits, radix)
+        .ok()
+        .and_then(|magnitude| {
+            if negative {
+                0_i64.checked_sub_unsigned(magnitude)
+            } else {
+                i64::try_from(magnitude).ok()
+            }
+        })
+        .ok_or_else(|| {
+            Diagnostic::error("integer literal is out of range for `Int`", span).with_note(format!(
+                "`Int` ranges from {} to {}",
+                i64::MIN,
+                i64::MAX
+            ))
+        })
+}
+
+fn lower_binary_op(op: BinaryOp) -> HirBinaryOp {
+    match op {
+        BinaryOp::Add => HirBinaryOp::Add,
+        BinaryOp::Sub => HirBinaryOp::Sub,
+        BinaryOp::Mul => HirBinaryOp::Mul,
+        BinaryOp::Div => HirBinaryOp::Div,
+        BinaryOp::Mod => HirBinaryOp::Mod,
+        BinaryOp::BitAnd => HirBinaryOp::BitAnd,
+        BinaryOp::BitOr => HirBinaryOp::BitOr,
+        BinaryOp::BitXor => HirBinaryOp::BitXor,
+        BinaryOp::Shl => HirBinaryOp::Shl,
+        BinaryOp::Shr => HirBinaryOp::Shr,
+        BinaryOp::Eq => HirBinaryOp::Eq,
+        BinaryOp::Ne => HirBinaryOp::Ne,
+        BinaryOp::Lt => HirBinaryOp::Lt,
+        BinaryOp::Le => HirBinaryOp::Le,
+        BinaryOp::Gt => HirBinaryOp::Gt,
+        BinaryOp::Ge => HirBinaryOp::Ge,
+        BinaryOp::And => HirBinaryOp::And,
+        BinaryOp::Or => HirBinaryOp::Or,
+    }
 }
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use smol_str::SmolStr;
+
+use crate::ast::{Item, Module, NameRef, Pattern, UseDecl};
+use crate::diagnostics::Diagnostic;
+use crate::lowering::LoweringContext;
+use crate::source::SourceMap;
+use crate::{lexer, parser, FrontendError, ParseOutput};
+
+/// Names visible at the top level of one source module while lowering it.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleScope {
+    /// Prefix for the module's own items; `None` for the root module.
+    pub prefix: Option<SmolStr>,
+    /// Unqualified names mapped to the qualified item they refer to.
+    pub aliases: HashMap<SmolStr, SmolStr>,
+    /// Modules brought in with `use name`, keyed by the name used in paths.
+    pub modules: HashMap<SmolStr, ModuleExports>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModuleExports {
+    pub name: SmolStr,
+    pub prefix: Option<SmolStr>,
+    /// Top-level items and whether they are `pub`.
+    pub items: HashMap<SmolStr, bool>,
+}
+
+impl ModuleExports {
+    pub fn qualify(&self, item: &str) -> SmolStr {
+        match &self.prefix {
+            Some(prefix) => format!("{prefix}::{item}").into(),
+            None => item.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VisitState {
+    Unvisited,
+    Visiting,
+    Done,
+}
+
+struct SourceModule {
+    name: SmolStr,
+    ast: Module,
+    state: VisitState,
+}
+
+struct ModuleGraph<'r> {
+    dirs: Vec<PathBuf>,
+    read: &'r mut dyn FnMut(&Path) -> io::Result<String>,
+    modules: Vec<SourceModule>,
+    by_name: HashMap<SmolStr, usize>,
+    order: Vec<usize>,
+    source_map: SourceMap,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Loads `root` and every module it reaches through `use`, then lowers them
+/// all into one HIR module. Modules are looked up next to `root` first and
+/// then in each of `source_roots`. Only a failure to read `root` itself is an
+/// error; everything else is reported as a diagnostic.
+pub fn load_program(
+    root: &Path,
+    source_roots: &[PathBuf],
+    read: &mut dyn FnMut(&Path) -> io::Result<String>,
+) -> Result<ParseOutput, FrontendError> {
+    let text = read(root)?;
+    let mut dirs = vec![root.parent().map(Path::to_path_buf).unwrap_or_default()];
+    for dir in source_roots {
+        if !dirs.contains(dir) {
+            dirs.push(dir.clone());
+        }
+    }
+    let mut graph = ModuleGraph {
+        dirs,
+        read,
+        modules: Vec::new(),
+        by_name: HashMap::new(),
+        order: Vec::new(),
+        source_map: SourceMap::new(),
+        diagnostics: Vec::new(),
+    };
+    let name = root
+        .file_stem()
+        .map(|stem| SmolStr::from(stem.to_string_lossy()))
+        .unwrap_or_else(|| SmolStr::new("main"));
+    graph.add_module(name, root.to_path_buf(), text);
+    graph.visit(0, &mut Vec::new());
+    Ok(graph.lower())
+}
+
+impl ModuleGraph<'_> {
+    fn add_module(&mut self, name: SmolStr, path: PathBuf, text: String) -> usize {
+        let source_id = self.source_map.add_source(path, text.clone());
+        let (tokens, lex_diags) = lexer::lex(&text, source_id);
+        self.diagnostics.extend(lex_diags);
+        let mut parser = parser::Parser::new(tokens, source_id);
+        let ast = parser.parse_module();
+        self.diagnostics.extend(parser.into_diagnostics());
+        let index = self.modules.len();
+        self.by_name.insert(name.clone(), index);
+        self.modules.push(SourceModule {
+            name,
+            ast,
+            state: VisitState::Unvisited,
+        });
+        index
+    }
+
+    fn load(&mut self, name: &NameRef) -> Option<usize> {
+        if let Some(index) = self.by_name.get(&name.name) {
+            return Some(*index);
+        }
+        let file = format!("{}.ktn", name.name);
+        let candidates: Vec<PathBuf> = self.dirs.iter().map(|dir| dir.join(&file)).collect();
+        for path in &candidates {
+            if let Ok(text) = (self.read)(path) {
+                return Some(self.add_module(name.name.clone(), path.clone(), text));
+            }
+        }
+        let searched: Vec<String> = candidates
+            .iter()
+            .map(|path| format!("`{}`", path.display()))
+            .collect();
+        self.diagnostics.push(
+            Diagnostic::error(format!("cannot find module `{}`", name.name), name.span)
+                .with_note(format!("expected a file at {}", searched.join(" or "))),
+        );
+        None
+    }
+
+    fn visit(&mut self, index: usize, stack: &mut Vec<usize>) {
+        self.modules[index].state = VisitState::Visiting;
+        stack.push(index);
+        let dependencies: Vec<NameRef> = uses(&self.modules[index].ast)
+            .filter_map(|decl| decl.path.first().cloned())
+            .collect();
+        for dependency in dependencies {
+            let Some(target) = self.load(&dependency) else {
+                continue;
+            };
+            match self.modules[target].state {
+                VisitState::Unvisited => self.visit(target, stack),
+                VisitState::Visiting => {
+                    let start = stack.iter().position(|m| *m == target).unwrap_or(0);
+                    let cycle: Vec<&str> = stack[start..]
+                        .iter()
+                        .chain([&target])
+                        .map(|m| self.modules[*m].name.as_str())
+                        .collect();
+                    self.diagnostics.push(
+                        Diagnostic::error("cyclic `use` between modules", dependency.span)
+                            .with_note(format!("cycle: {}", cycle.join(" -> "))),
+                    );
+                }
+                VisitState::Done => {}
+            }
+        }
+        stack.pop();
+        self.modules[index].state = VisitState::Done;
+        self.order.push(index);
+    }
+
+    fn lower(mut self) -> ParseOutput {
+        let exports: Vec<ModuleExports> = self
+            .modules
+            .iter()
+            .enumerate()
+            .map(|(index, module)| ModuleExports {
+                name: module.name.clone(),
+                prefix: (index != 0).then(|| module.name.clone()),
+                items: item_names(&module.ast),
+            })
+            .collect();
+        let mut units = Vec::new();
+        for index in self.order.clone() {
+            let scope = self.scope(index, &exports);
+            let module = &mut self.modules[index].ast;
+            let items = std::mem::take(&mut module.items);
+            units.push((
+                Module {
+                    items,
+                    span: module.span,
+                },
+                scope,
+            ));
+        }
+        let mut lowering = LoweringContext::new(self.source_map.clone());
+        let module = lowering.lower_program(units);
+        self.diagnostics.extend(lowering.into_diagnostics());
+        ParseOutput {
+            module,
+            diagnostics: self.diagnostics,
+            source_map: self.source_map,
+        }
+    }
+
+    fn scope(&mut self, index: usize, exports: &[ModuleExports]) -> ModuleScope {
+        let own = &exports[index];
+        let mut scope = ModuleScope {
+            prefix: own.prefix.clone(),
+            ..ModuleScope::default()
+        };
+        if own.prefix.is_some() {
+            for item in own.items.keys() {
+                scope.aliases.insert(item.clone(), own.qualify(item));
+            }
+        }
+        let mut imported: Vec<SmolStr> = Vec::new();
+        let decls: Vec<UseDecl> = uses(&self.modules[index].ast).cloned().collect();
+        for decl in decls {
+            let Some(target) = decl
+                .path
+                .first()
+                .and_then(|module| self.by_name.get(&module.name))
+                .map(|target| &exports[*target])
+            else {
+                continue;
+            };
+            let local = match decl.path.as_slice() {
+                [module] => {
+                    let local = decl.alias.as_ref().unwrap_or(module);
+                    scope.modules.insert(local.name.clone(), target.clone());
+                    local
+                }
+                [_, item] => {
+                    match target.items.get(&item.name) {
+                        None => {
+                            self.diagnostics.push(Diagnostic::error(
+                                format!("module `{}` has no item `{}`", target.name, item.name),
+                                item.span,
+                            ));
+                            continue;
+                        }
+                        Some(false) => self.diagnostics.push(
+                            Diagnostic::error(
+                                format!("`{}` is private to module `{}`", item.name, target.name),
+                                item.span,
+                            )
+                            .with_note("declare it with `pub` to use it from other modules"),
+                        ),
+                        Some(true) => {}
+                    }
+                    let local = decl.alias.as_ref().unwrap_or(item);
+                    scope
+                        .aliases
+                        .insert(local.name.clone(), target.qualify(&item.name));
+                    local
+                }
+                _ => {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            "`use` can only name a module or one of its items",
+                            decl.span,
+                        )
+                        .with_note("write `use module` or `use module::item`"),
+                    );
+                    continue;
+                }
+            };
+            if own.items.contains_key(&local.name) || imported.contains(&local.name) {
+                self.diagnostics.push(Diagnostic::error(
+                    format!("`{}` is already defined in this module", local.name),
+                    local.span,
+                ));
+            }
+            imported.push(local.name.clone());
+        }
+        scope
+    }
+}
+
+fn uses(module: &Module) -> impl Iterator<Item = &UseDecl> {
+    module.items.iter().filter_map(|item| match item {
+        Item::Use(decl) => Some(decl),
+        _ => None,
+    })
+}
+
+fn item_names(module: &Module) -> HashMap<SmolStr, bool> {
+    let mut names = HashMap::new();
+    for item in &module.items {
+        match item {
+            Item::Function(func) => {
+                names.insert(func.name.clone(), func.public);
+            }
+            Item::Struct(decl) => {
+                names.insert(decl.name.clone(), decl.public);
+            }
+            Item::Enum(decl) => {
+                names.insert(decl.name.clone(), decl.public);
+            }
+            Item::Let(let_stmt) => pattern_names(&let_stmt.pattern, let_stmt.public, &mut names),
+            Item::Impl(_) | Item::Use(_) => {}
+        }
+    }
+    names
+}
+
+fn pattern_names(pattern: &Pattern, public: bool, names: &mut HashMap<SmolStr, bool>) {
+    match pattern {
+        Pattern::Binding(name) => {
+            names.insert(name.name.clone(), public);
+        }
+        Pattern::Tuple(tuple) => {
+            for element in &tuple.elements {
+                pattern_names(element, public, names);
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,6 +1,6 @@
 use crate::ast::*;
 use crate::diagnostics::Diagnostic;
-use crate::lexer::{Keyword, Token, TokenKind};
+use crate::lexer::{FStringPart, Keyword, Token, TokenKind};
 use crate::span::Span;
 use smol_str::SmolStr;
 
@@ -9,6 +9,7 @@ pub struct Parser {
     pos: usize,
     diagnostics: Vec<Diagnostic>,
     last_span: Option<Span>,
+    no_struct_literal: bool,
 }
 
 impl Parser {
@@ -18,6 +19,7 @@ impl Parser {
             pos: 0,
             diagnostics: Vec::new(),
             last_span: None,
+            no_struct_literal: false,
         }
     }
 
@@ -47,33 +49,367 @@ impl Parser {
     }
 
     fn parse_item(&mut self) -> Option<Item> {
+        let pub_span = self.peek_span();
+        let public = self.consume_keyword(Keyword::Pub);
         match self.peek_kind() {
-            TokenKind::Keyword(Keyword::Fn) => self.parse_function().map(Item::Function),
-            TokenKind::Keyword(Keyword::Let) => self.parse_let_statement().map(Item::Let),
+            TokenKind::Keyword(Keyword::Fn) => self
+                .parse_function()
+                .map(|func| Item::Function(Function { public, ..func })),
+            TokenKind::Keyword(Keyword::Let) => self
+                .parse_let_statement()
+                .map(|binding| Item::Let(LetStatement { public, ..binding })),
+            TokenKind::Keyword(Keyword::Struct) => self
+                .parse_struct()
+                .map(|decl| Item::Struct(StructDecl { public, ..decl })),
+            TokenKind::Keyword(Keyword::Enum) => self
+                .parse_enum()
+                .map(|decl| Item::Enum(EnumDecl { public, ..decl })),
+            TokenKind::Keyword(Keyword::Impl | Keyword::Use) if public => {
+                self.error(
+                    "`pub` is only allowed on `fn`, `struct`, `enum` and `let` items",
+                    pub_span,
+                );
+                None
+            }
+            TokenKind::Keyword(Keyword::Impl) => self.parse_impl().map(Item::Impl),
+            TokenKind::Keyword(Keyword::Use) => self.parse_use().map(Item::Use),
             _ => {
                 let span = self.peek_span();
-                self.error("expected `fn` or `let`", span);
+                self.error(
+                    "expected `fn`, `struct`, `enum`, `impl`, `let` or `use`",
+                    span,
+                );
                 None
             }
         }
     }
 
+    fn parse_use(&mut self) -> Option<UseDecl> {
+        let use_token = self.bump();
+        let (name, span) = self.expect_identifier("module name")?;
+        let path = self.parse_path(NameRef { span, name })?;
+        let alias = if self.consume_keyword(Keyword::As) {
+            let (name, span) = self.expect_identifier("import alias")?;
+            Some(NameRef { span, name })
+        } else {
+            None
+        };
+        let end = alias.as_ref().map_or(path.span, |alias| alias.span);
+        Some(UseDecl {
+            span: use_token.span.merge(end),
+            path: path.segments,
+            alias,
+        })
+    }
+
     fn parse_function(&mut self) -> Option<Function> {
         let fn_token = self.bump();
         let (name, _name_span) = self.expect_identifier("function name")?;
+        let type_params = self.parse_type_params()?;
         self.expect_lparen()?;
         let params = self.parse_parameters()?;
         self.expect_rparen()?;
+        let return_ty = if matches!(self.peek_kind(), TokenKind::Arrow) {
+            self.bump();
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
         let body = self.parse_block("function body")?;
         let span = fn_token.span.merge(body.span);
         Some(Function {
             span,
+            public: false,
             name,
+            type_params,
             params,
+            return_ty,
             body,
         })
     }
 
+    fn parse_type_params(&mut self) -> Option<Vec<NameRef>> {
+        if !matches!(self.peek_kind(), TokenKind::LBracket) {
+            return Some(Vec::new());
+        }
+        let open = self.bump();
+        let mut params = Vec::new();
+        while !matches!(self.peek_kind(), TokenKind::RBracket) {
+            let (name, span) = self.expect_identifier("type parameter")?;
+            params.push(NameRef { span, name });
+            if !matches!(self.peek_kind(), TokenKind::Comma) {
+                break;
+            }
+            self.bump();
+        }
+        let close = self.expect_rbracket()?;
+        if params.is_empty() {
+            self.error(
+                "expected at least one type parameter",
+                open.span.merge(close.span),
+            );
+        }
+        Some(params)
+    }
+
+    fn parse_struct(&mut self) -> Option<StructDecl> {
+        let struct_token = self.bump();
+        let (name, name_span) = self.expect_identifier("struct name")?;
+        let type_params = self.parse_type_params()?;
+        let (fields, end) =
+            self.parse_member_list("struct", Self::parse_field_decl, |field| field.span)?;
+        if fields.is_empty() {
+            self.error("struct must declare at least one field", name_span);
+        }
+        Some(StructDecl {
+            span: struct_token.span.merge(end),
+            public: false,
+            name,
+            type_params,
+            fields,
+        })
+    }
+
+    fn parse_enum(&mut self) -> Option<EnumDecl> {
+        let enum_token = self.bump();
+        let (name, name_span) = self.expect_identifier("enum name")?;
+        let (variants, end) =
+            self.parse_member_list("enum", Self::parse_variant_decl, |variant| variant.span)?;
+        if variants.is_empty() {
+            self.error("enum must declare at least one variant", name_span);
+        }
+        Some(EnumDecl {
+            span: enum_token.span.merge(end),
+            public: false,
+            name,
+            variants,
+        })
+    }
+
+    fn parse_impl(&mut self) -> Option<ImplBlock> {
+        let impl_token = self.bump();
+        let (name, name_span) = self.expect_identifier("type name")?;
+        self.expect_colon()?;
+        if !self.eat_newline() {
+            let span = self.peek_span();
+            self.error("expected an indented block of methods after `impl`", span);
+            return None;
+        }
+        self.expect_indent()?;
+        let mut methods = Vec::new();
+        while !self.at_eof() && !matches!(self.peek_kind(), TokenKind::Dedent) {
+            if self.eat_trivia_line() {
+                continue;
+            }
+            if !matches!(self.peek_kind(), TokenKind::Keyword(Keyword::Fn)) {
+                let span = self.peek_span();
+                self.error("expected `fn` in impl block", span);
+                self.recover_to_line_end();
+                continue;
+            }
+            if let Some(method) = self.parse_function() {
+                methods.push(method);
+            } else {
+                self.recover_to_line_end();
+            }
+        }
+        let end = self.expect_dedent()?.span;
+        Some(ImplBlock {
+            span: impl_token.span.merge(end),
+            ty: NameRef {
+                span: name_span,
+                name,
+            },
+            methods,
+        })
+    }
+
+    fn parse_member_list<T>(
+        &mut self,
+        context: &str,
+        mut parse_member: impl FnMut(&mut Self) -> Option<T>,
+        span_of: impl Fn(&T) -> Span,
+    ) -> Option<(Vec<T>, Span)> {
+        let mut members = Vec::new();
+        let end = match self.peek_kind() {
+            TokenKind::LBrace => {
+                self.bump();
+                while !self.at_eof() && !matches!(self.peek_kind(), TokenKind::RBrace) {
+                    if self.eat_trivia_line() {
+                        continue;
+                    }
+                    members.push(parse_member(self)?);
+                    if !matches!(self.peek_kind(), TokenKind::Comma) {
+                        self.eat_newline();
+                        break;
+                    }
+                    self.bump();
+                }
+                self.expect_rbrace()?.span
+            }
+            TokenKind::Colon => {
+                let colon = self.bump().span;
+                if self.eat_newline() {
+                    self.expect_indent()?;
+                    while !self.at_eof() && !matches!(self.peek_kind(), TokenKind::Dedent) {
+                        if self.eat_trivia_line() {
+                            continue;
+                        }
+                        members.push(parse_member(self)?);
+                        if matches!(self.peek_kind(), TokenKind::Comma) {
+                            self.bump();
+                        }
+                        if matches!(self.peek_kind(), TokenKind::Dedent) {
+                            break;
+                        }
+                        self.eat_newline();
+                    }
+                    self.expect_dedent()?.span
+                } else {
+                    loop {
+                        members.push(parse_member(self)?);
+                        if !matches!(self.peek_kind(), TokenKind::Comma) {
+                            break;
+                        }
+                        self.bump();
+                    }
+                    members.last().map(&span_of).unwrap_or(colon)
+                }
+            }
+            _ => {
+                let span = self.peek_span();
+                self.error(format!("expected `:` or `{{` after {context} name"), span);
+                return None;
+            }
+        };
+        Some((members, end))
+    }
+
+    fn parse_field_decl(&mut self) -> Option<FieldDecl> {
+        let (name, span) = self.expect_identifier("field name")?;
+        self.expect_colon()?;
+        let ty = self.parse_type()?;
+        Some(FieldDecl {
+            span: span.merge(ty.span()),
+            name,
+            ty,
+        })
+    }
+
+    fn parse_variant_decl(&mut self) -> Option<VariantDecl> {
+        let (name, span) = self.expect_identifier("variant name")?;
+        let mut fields = Vec::new();
+        let mut end = span;
+        if matches!(self.peek_kind(), TokenKind::LParen) {
+            self.bump();
+            while !matches!(self.peek_kind(), TokenKind::RParen) {
+                let (field, field_span) = self.expect_identifier("variant field")?;
+                let ty = if matches!(self.peek_kind(), TokenKind::Colon) {
+                    self.bump();
+                    Some(self.parse_type()?)
+                } else {
+                    None
+                };
+                fields.push(VariantField {
+                    span: ty
+                        .as_ref()
+                        .map_or(field_span, |ty| field_span.merge(ty.span())),
+                    name: field,
+                    ty,
+                });
+                if !matches!(self.peek_kind(), TokenKind::Comma) {
+                    break;
+                }
+                self.bump();
+            }
+            end = self.expect_rparen()?.span;
+        }
+        Some(VariantDecl {
+            span: span.merge(end),
+            name,
+            fields,
+        })
+    }
+
+    fn parse_type(&mut self) -> Option<TypeExpr> {
+        let token = self.peek().clone();
+        match token.kind {
+            TokenKind::Identifier(name) => {
+                self.bump();
+                let name = NameRef {
+                    span: token.span,
+                    name,
+                };
+                if !matches!(self.peek_kind(), TokenKind::LBracket) {
+                    return Some(TypeExpr::Name(name));
+                }
+                self.bump();
+                let (args, _) = self.parse_type_list(TokenKind::RBracket)?;
+                let end = self.expect_rbracket()?.span;
+                Some(TypeExpr::Generic(GenericType {
+                    span: token.span.merge(end),
+                    name,
+                    args,
+                }))
+            }
+            TokenKind::LParen => {
+                self.bump();
+                let (mut elements, trailing_comma) = self.parse_type_list(TokenKind::RParen)?;
+                let span = token.span.merge(self.expect_rparen()?.span);
+                match elements.len() {
+                    0 => Some(TypeExpr::Unit(span)),
+                    1 if !trailing_comma => elements.pop(),
+                    _ => Some(TypeExpr::Tuple(TupleType { span, elements })),
+                }
+            }
+            TokenKind::Keyword(Keyword::Fn) => {
+                self.bump();
+                self.expect_lparen()?;
+                let (params, _) = self.parse_type_list(TokenKind::RParen)?;
+                let mut span = token.span.merge(self.expect_rparen()?.span);
+                let return_ty = if matches!(self.peek_kind(), TokenKind::Arrow) {
+                    self.bump();
+                    let ty = self.parse_type()?;
+                    span = span.merge(ty.span());
+                    Some(Box::new(ty))
+                } else {
+                    None
+                };
+                Some(TypeExpr::Function(FunctionType {
+                    span,
+                    params,
+                    return_ty,
+                }))
+            }
+            _ => {
+                self.error("expected type", token.span);
+                None
+            }
+        }
+    }
+
+    fn parse_type_list(&mut self, close: TokenKind) -> Option<(Vec<TypeExpr>, bool)> {
+        let mut types = Vec::new();
+        let mut trailing_comma = false;
+        while *self.peek_kind() != close {
+            types.push(self.parse_type()?);
+            trailing_comma = matches!(self.peek_kind(), TokenKind::Comma);
+            if !trailing_comma {
+                break;
+            }
+            self.bump();
+        }
+        Some((types, trailing_comma))
+    }
+
+    fn parse_type_annotation(&mut self) -> Option<Option<TypeExpr>> {
+        if !matches!(self.peek_kind(), TokenKind::Colon) {
+            return Some(None);
+        }
+        self.bump();
+        self.parse_type().map(Some)
+    }
+
     fn parse_parameters(&mut self) -> Option<Vec<Parameter>> {
         let mut params = Vec::new();
         if matches!(self.peek_kind(), TokenKind::RParen) {
@@ -81,7 +417,8 @@ impl Parser {
         }
         loop {
             let (name, span) = self.expect_identifier("parameter")?;
-            params.push(Parameter { span, name });
+            let ty = self.parse_type_annotation()?;
+            params.push(Parameter { span, name, ty });
             if matches!(self.peek_kind(), TokenKind::Comma) {
                 self.bump();
                 if matches!(self.peek_kind(), TokenKind::RParen) {
@@ -162,14 +499,47 @@ impl Parser {
         match self.peek_kind() {
             TokenKind::Keyword(Keyword::Let) => self.parse_let_statement().map(Stmt::Let),
             TokenKind::Keyword(Keyword::Return) => self.parse_return_stmt().map(Stmt::Return),
-            TokenKind::Keyword(Keyword::While) => self.parse_while_stmt().map(Stmt::While),
-            _ => self.parse_expr().map(Stmt::Expr),
+            TokenKind::Keyword(Keyword::Break) => self.parse_break_stmt().map(Stmt::Break),
+            TokenKind::Keyword(Keyword::Continue) => {
+                let token = self.bump();
+                Some(Stmt::Continue(ContinueStatement { span: token.span }))
+            }
+            _ => {
+                let expr = self.parse_expr()?;
+                match self.peek_assign_op() {
+                    Some(op) => self.parse_assignment(expr, op).map(Stmt::Assign),
+                    None => Some(Stmt::Expr(expr)),
+                }
+            }
         }
     }
 
+    fn parse_assignment(&mut self, target: Expr, op: Option<BinaryOp>) -> Option<AssignStatement> {
+        self.bump();
+        let target = match target {
+            Expr::Name(name) => AssignTarget::Name(name),
+            Expr::Field(field) => AssignTarget::Field(field),
+            Expr::Index(index) => AssignTarget::Index(index),
+            other => {
+                self.error("invalid assignment target", other.span());
+                return None;
+            }
+        };
+        let value = self.parse_expr()?;
+        let span = target.span().merge(value.span());
+        Some(AssignStatement {
+            span,
+            target,
+            op,
+            value,
+        })
+    }
+
     fn parse_let_statement(&mut self) -> Option<LetStatement> {
         let let_token = self.bump();
-        let (name, _) = self.expect_identifier("binding name")?;
+        let mutable = self.consume_keyword(Keyword::Mut);
+        let pattern = self.parse_pattern()?;
+        let ty = self.parse_type_annotation()?;
         if !matches!(self.peek_kind(), TokenKind::Equal) {
             let span = self.peek_span();
             self.error("expected `=` in let binding", span);
@@ -178,7 +548,14 @@ impl Parser {
         self.bump();
         let value = self.parse_expr()?;
         let span = let_token.span.merge(value.span());
-        Some(LetStatement { span, name, value })
+        Some(LetStatement {
+            span,
+            public: false,
+            pattern,
+            mutable,
+            ty,
+            value,
+        })
     }
 
     fn parse_return_stmt(&mut self) -> Option<ReturnStatement> {
@@ -200,16 +577,55 @@ impl Parser {
         })
     }
 
-    fn parse_while_stmt(&mut self) -> Option<WhileStatement> {
+    fn parse_break_stmt(&mut self) -> Option<BreakStatement> {
+        let break_token = self.bump();
+        if matches!(
+            self.peek_kind(),
+            TokenKind::Newline | TokenKind::Dedent | TokenKind::RBrace | TokenKind::Eof
+        ) {
+            return Some(BreakStatement {
+                span: break_token.span,
+                value: None,
+            });
+        }
+        let value = self.parse_expr()?;
+        let span = break_token.span.merge(value.span());
+        Some(BreakStatement {
+            span,
+            value: Some(value),
+        })
+    }
+
+    fn parse_while_expr(&mut self) -> Option<Expr> {
         let while_token = self.bump();
-        let condition = self.parse_expr()?;
+        let condition = self.with_struct_literals(false, Self::parse_expr)?;
         let body = self.parse_block("while body")?;
         let span = while_token.span.merge(body.span);
-        Some(WhileStatement {
+        Some(Expr::While(Box::new(WhileExpr {
             span,
             condition,
             body: Box::new(body),
-        })
+        })))
+    }
+
+    fn parse_for_expr(&mut self) -> Option<Expr> {
+        let for_token = self.bump();
+        let pattern = self.parse_pattern()?;
+        if !matches!(self.peek_kind(), TokenKind::Keyword(Keyword::In)) {
+            let span = self.peek_span();
+            self.error("expected `in` after for loop pattern", span);
+            return None;
+        }
+        self.bump();
+        let iterable = self.with_struct_literals(false, Self::parse_range_expr)?;
+        let body = self.parse_block("for body")?;
+        let span = for_token.span.merge(body.span);
+        Some(Expr::For(Box::new(ForExpr {
+            span,
+            pattern,
+            iterable,
+            body: Box::new(body),
+        })))
     }
 
     fn parse_expr(&mut self) -> Option<Expr> {
@@ -218,9 +634,21 @@ impl Parser {
 
     fn parse_if_expr(&mut self) -> Option<Expr> {
         if !matches!(self.peek_kind(), TokenKind::Keyword(Keyword::If)) {
-            return self.parse_binary_expr(0);
+            return self.parse_range_expr();
         }
         let if_token = self.bump();
+        let pattern = if self.consume_keyword(Keyword::Let) {
+            let pattern = self.parse_pattern()?;
+            if !matches!(self.peek_kind(), TokenKind::Equal) {
+                let span = self.peek_span();
+                self.error("expected `=` after `if let` pattern", span);
+                return None;
+            }
+            self.bump();
+            Some(pattern)
+        } else {
+            None
+        };
         let condition = self.parse_binary_expr(0)?;
         let colon = self.expect_colon()?.span;
         let then_branch = if self.eat_newline() {
@@ -237,6 +665,15 @@ impl Parser {
         };
         let else_branch = self.parse_else_branch()?;
         let span = if_token.span.merge(then_branch.span);
+        if let Some(pattern) = pattern {
+            return Some(Expr::IfLet(Box::new(IfLetExpr {
+                span,
+                pattern,
+                value: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch,
+            })));
+        }
         Some(Expr::If(Box::new(IfExpr {
             span,
             condition: Box::new(condition),
@@ -245,8 +682,29 @@ impl Parser {
         })))
     }
 
+    fn parse_range_expr(&mut self) -> Option<Expr> {
+        let start = self.parse_binary_expr(0)?;
+        let inclusive = match self.peek_kind() {
+            TokenKind::DotDot => false,
+            TokenKind::DotDotEq => true,
+            _ => return Some(start),
+        };
+        self.bump();
+        let end = self.parse_binary_expr(0)?;
+        let span = start.span().merge(end.span());
+        Some(Expr::Range(RangeExpr {
+            span,
+            start: Box::new(start),
+            end: Box::new(end),
+            inclusive,
+        }))
+    }
+
     fn parse_binary_expr(&mut self, min_prec: u8) -> Option<Expr> {
         let mut left = self.parse_prefix_expr()?;
+        if self.ended_suite() {
+            return Some(left);
+        }
         while let Some((op, prec)) = self.peek_binary_op() {
             if prec < min_prec {
                 break;
@@ -286,12 +744,277 @@ impl Parser {
                     expr: Box::new(operand),
                 }))
             }
+            TokenKind::Tilde => {
+                let op_token = self.bump();
+                let operand = self.parse_prefix_expr()?;
+                let span = op_token.span.merge(operand.span());
+                Some(Expr::Unary(UnaryExpr {
+                    span,
+                    op: UnaryOp::BitNot,
+                    expr: Box::new(operand),
+                }))
+            }
             TokenKind::Keyword(Keyword::If) => self.parse_if_expr(),
+            TokenKind::Keyword(Keyword::Match) => self.parse_match_expr(),
+            TokenKind::Keyword(Keyword::While) => self.parse_while_expr(),
+            TokenKind::Keyword(Keyword::For) => self.parse_for_expr(),
             TokenKind::LBrace => self.parse_block_expr(),
+            TokenKind::Pipe | TokenKind::PipePipe => self.parse_lambda(),
             _ => self.parse_postfix_expr(),
         }
     }
 
+    fn parse_lambda(&mut self) -> Option<Expr> {
+        let start = self.bump();
+        let mut params = Vec::new();
+        if matches!(start.kind, TokenKind::Pipe) {
+            while !matches!(self.peek_kind(), TokenKind::Pipe) {
+                let (name, span) = self.expect_identifier("lambda parameter")?;
+                let ty = self.parse_type_annotation()?;
+                params.push(Parameter { span, name, ty });
+                if !matches!(self.peek_kind(), TokenKind::Comma) {
+                    break;
+                }
+                self.bump();
+            }
+            if !matches!(self.peek_kind(), TokenKind::Pipe) {
+                let span = self.peek_span();
+                self.error("expected `|` after lambda parameters", span);
+                return None;
+            }
+            self.bump();
+        }
+        let body = self.parse_expr()?;
+        Some(Expr::Lambda(LambdaExpr {
+            span: start.span.merge(body.span()),
+            params,
+            body: Box::new(body),
+        }))
+    }
+
+    fn parse_match_expr(&mut self) -> Option<Expr> {
+        let match_token = self.bump();
+        let scrutinee = self.with_struct_literals(false, |parser| parser.parse_binary_expr(0))?;
+        let mut arms = Vec::new();
+        let end = match self.peek_kind() {
+            TokenKind::Colon => {
+                self.bump();
+                if !self.eat_newline() {
+                    let span = self.peek_span();
+                    self.error("expected newline after `:` for match arms", span);
+                    return None;
+                }
+                self.expect_indent()?;
+                while !self.at_eof() && !matches!(self.peek_kind(), TokenKind::Dedent) {
+                    if self.eat_trivia_line() {
+                        continue;
+                    }
+                    match self.parse_match_arm() {
+                        Some(arm) => arms.push(arm),
+                        None => self.recover_to_line_end(),
+                    }
+                    if matches!(self.peek_kind(), TokenKind::Comma) {
+                        self.bump();
+                    }
+                    if matches!(self.peek_kind(), TokenKind::Dedent) {
+                        break;
+                    }
+                    self.eat_newline();
+                }
+                self.expect_dedent()?.span
+            }
+            TokenKind::LBrace => {
+                self.bump();
+                while !self.at_eof() && !matches!(self.peek_kind(), TokenKind::RBrace) {
+                    if self.eat_trivia_line() {
+                        continue;
+                    }
+                    match self.parse_match_arm() {
+                        Some(arm) => arms.push(arm),
+                        None => self.recover_in_block(),
+                    }
+                    if matches!(self.peek_kind(), TokenKind::Comma) {
+                        self.bump();
+                    }
+                }
+                self.expect_rbrace()?.span
+            }
+            _ => {
+                let span = self.peek_span();
+                self.error("expected `:` or `{` after match scrutinee", span);
+                return None;
+            }
+        };
+        if arms.is_empty() {
+            self.error(
+                "match expression must have at least one arm",
+                match_token.span,
+            );
+        }
+        let span = match_token.span.merge(end);
+        Some(Expr::Match(Box::new(MatchExpr {
+            span,
+            scrutinee: Box::new(scrutinee),
+            arms,
+        })))
+    }
+
+    fn parse_match_arm(&mut self) -> Option<MatchArm> {
+        let pattern = self.parse_pattern()?;
+        let guard = if self.consume_keyword(Keyword::If) {
+            Some(self.parse_binary_expr(0)?)
+        } else {
+            None
+        };
+        if !matches!(self.peek_kind(), TokenKind::FatArrow) {
+            let span = self.peek_span();
+            self.error("expected `=>` after match pattern", span);
+            return None;
+        }
+        self.bump();
+        let body = match self.peek_kind() {
+            TokenKind::Colon => self.parse_suite_block("match arm")?,
+            TokenKind::LBrace => self.parse_braced_block()?,
+            _ => {
+                let expr = self.parse_expr()?;
+                Block {
+                    span: expr.span(),
+                    statements: Vec::new(),
+                    tail: Some(Box::new(expr)),
+                }
+            }
+        };
+        let span = pattern.span().merge(body.span);
+        Some(MatchArm {
+            span,
+            pattern,
+            guard,
+            body,
+        })
+    }
+
+    fn parse_pattern(&mut self) -> Option<Pattern> {
+        let first = self.parse_pattern_atom()?;
+        if !matches!(self.peek_kind(), TokenKind::Pipe) {
+            return Some(first);
+        }
+        let mut alternatives = vec![first];
+        while matches!(self.peek_kind(), TokenKind::Pipe) {
+            self.bump();
+            alternatives.push(self.parse_pattern_atom()?);
+        }
+        let span = alternatives[0]
+            .span()
+            .merge(alternatives[alternatives.len() - 1].span());
+        Some(Pattern::Or(OrPattern { span, alternatives }))
+    }
+
+    fn parse_pattern_atom(&mut self) -> Option<Pattern> {
+        let token = self.peek().clone();
+        match token.kind {
+            TokenKind::Identifier(name) => {
+                self.bump();
+                if matches!(self.peek_kind(), TokenKind::DoubleColon) {
+                    let path = self.parse_path(NameRef {
+                        span: token.span,
+                        name,
+                    })?;
+                    return self.parse_variant_pattern(path);
+                }
+                if name == "_" {
+                    Some(Pattern::Wildcard(WildcardPattern { span: token.span }))
+                } else {
+                    Some(Pattern::Binding(BindingPattern {
+                        span: token.span,
+                        name,
+                    }))
+                }
+            }
+            TokenKind::Minus => {
+                self.bump();
+                let next = self.peek().clone();
+                if let TokenKind::Int(value) = next.kind {
+                    self.bump();
+                    Some(Pattern::Literal(Literal::Int(IntLiteral {
+                        span: token.span.merge(next.span),
+                        value: SmolStr::from(format!("-{value}")),
+                    })))
+                } else {
+                    self.error("expected integer literal after `-` in pattern", next.span);
+                    None
+                }
+            }
+            TokenKind::LParen => {
+                let start = self.bump().span;
+                if matches!(self.peek_kind(), TokenKind::RParen) {
+                    let end = self.bump().span;
+                    let span = start.merge(end);
+                    return Some(Pattern::Literal(Literal::Unit(UnitLiteral { span })));
+                }
+                let first = self.parse_pattern()?;
+                if !matches!(self.peek_kind(), TokenKind::Comma) {
+                    self.expect_rparen()?;
+                    return Some(first);
+                }
+                let mut elements = vec![first];
+                while matches!(self.peek_kind(), TokenKind::Comma) {
+                    self.bump();
+                    if matches!(self.peek_kind(), TokenKind::RParen) {
+                        break;
+                    }
+                    elements.push(self.parse_pattern()?);
+                }
+                let end = self.expect_rparen()?.span;
+                Some(Pattern::Tuple(TuplePattern {
+                    span: start.merge(end),
+                    elements,
+                }))
+            }
+            TokenKind::Int(_)
+            | TokenKind::String(_)
+            | TokenKind::Keyword(Keyword::True)
+            | TokenKind::Keyword(Keyword::False) => match self.parse_primary()? {
+                Expr::Literal(lit) => Some(Pattern::Literal(lit)),
+                other => {
+                    self.error("expected pattern", other.span());
+                    None
+                }
+            },
+            _ => {
+                self.error("expected pattern", token.span);
+                None
+            }
+        }
+    }
+
+    fn parse_variant_pattern(&mut self, path: PathExpr) -> Option<Pattern> {
+        let mut fields = Vec::new();
+        let mut span = path.span;
+        if matches!(self.peek_kind(), TokenKind::LParen) {
+            self.bump();
+            while !matches!(self.peek_kind(), TokenKind::RParen) {
+                fields.push(self.parse_pattern()?);
+                if !matches!(self.peek_kind(), TokenKind::Comma) {
+                    break;
+                }
+                self.bump();
+            }
+            span = span.merge(self.expect_rparen()?.span);
+        }
+        Some(Pattern::Variant(VariantPattern { span, path, fields }))
+    }
+
+    fn parse_path(&mut self, first: NameRef) -> Option<PathExpr> {
+        let mut segments = vec![first];
+        while matches!(self.peek_kind(), TokenKind::DoubleColon) {
+            self.bump();
+            let (name, span) = self.expect_identifier("path segment")?;
+            segments.push(NameRef { span, name });
+        }
+        let span = segments[0].span.merge(segments[segments.len() - 1].span);
+        Some(PathExpr { span, segments })
+    }
+
     fn parse_block_expr(&mut self) -> Option<Expr> {
         let block = self.parse_braced_block()?;
         Some(Expr::Block(Box::new(block)))
@@ -299,32 +1022,134 @@ impl Parser {
 
     fn parse_postfix_expr(&mut self) -> Option<Expr> {
         let mut expr = self.parse_primary()?;
-        while let &TokenKind::LParen = self.peek_kind() {
-            let start_span = expr.span();
-            self.bump();
-            let args = self.parse_argument_list()?;
-            let end = self.expect_rparen()?.span;
-            let span = start_span.merge(end);
-            expr = Expr::Call(CallExpr {
-                span,
-                callee: Box::new(expr),
-                args,
-            });
+        loop {
+            match self.peek_kind() {
+                TokenKind::LParen => {
+                    let start_span = expr.span();
+                    self.bump();
+                    let args = self.parse_expr_list(TokenKind::RParen)?;
+                    let end = self.expect_rparen()?.span;
+                    let span = start_span.merge(end);
+                    expr = Expr::Call(CallExpr {
+                        span,
+                        callee: Box::new(expr),
+                        args,
+                    });
+                }
+                TokenKind::LBracket => {
+                    self.bump();
+                    let index = self.with_struct_literals(true, Self::parse_expr)?;
+                    let end = self.expect_rbracket()?.span;
+                    expr = Expr::Index(IndexExpr {
+                        span: expr.span().merge(end),
+                        base: Box::new(expr),
+                        index: Box::new(index),
+                    });
+                }
+                TokenKind::Dot => {
+                    self.bump();
+                    let (name, field_span) = self.expect_identifier("field name")?;
+                    if matches!(self.peek_kind(), TokenKind::LParen) {
+                        self.bump();
+                        let args = self.parse_expr_list(TokenKind::RParen)?;
+                        let end = self.expect_rparen()?.span;
+                        expr = Expr::MethodCall(MethodCallExpr {
+                            span: expr.span().merge(end),
+                            receiver: Box::new(expr),
+                            method: NameRef {
+                                span: field_span,
+                                name,
+                            },
+                            args,
+                        });
+                        continue;
+                    }
+                    let span = expr.span().merge(field_span);
+                    expr = Expr::Field(FieldExpr {
+                        span,
+                        base: Box::new(expr),
+                        field: NameRef {
+                            span: field_span,
+                            name,
+                        },
+                    });
+                }
+                _ => break,
+            }
         }
         Some(expr)
     }
 
-    fn parse_argument_list(&mut self) -> Option<Vec<Expr>> {
+    fn parse_struct_literal(&mut self, name: NameRef) -> Option<Expr> {
+        self.expect_lbrace()?;
+        let mut fields = Vec::new();
+        while !self.at_eof() && !matches!(self.peek_kind(), TokenKind::RBrace) {
+            if self.eat_trivia_line() {
+                continue;
+            }
+            let (field, field_span) = self.expect_identifier("field name")?;
+            self.expect_colon()?;
+            let value = self.with_struct_literals(true, Self::parse_expr)?;
+            fields.push(FieldInit {
+                span: field_span.merge(value.span()),
+                name: NameRef {
+                    span: field_span,
+                    name: field,
+                },
+                value,
+            });
+            if !matches!(self.peek_kind(), TokenKind::Comma) {
+                self.eat_newline();
+                break;
+            }
+            self.bump();
+        }
+        let end = self.expect_rbrace()?.span;
+        Some(Expr::StructLit(StructLiteral {
+            span: name.span.merge(end),
+            name,
+            fields,
+        }))
+    }
+
+    fn at_struct_literal(&self) -> bool {
+        if self.no_struct_literal || !matches!(self.peek_kind(), TokenKind::LBrace) {
+            return false;
+        }
+        let mut rest = self.tokens[self.pos + 1..]
+            .iter()
+            .filter(|token| !matches!(token.kind, TokenKind::Newline));
+        match rest.next().map(|token| &token.kind) {
+            Some(TokenKind::RBrace) => true,
+            Some(TokenKind::Identifier(_)) => {
+                matches!(rest.next().map(|token| &token.kind), Some(TokenKind::Colon))
+            }
+            _ => false,
+        }
+    }
+
+    fn with_struct_literals<T>(
+        &mut self,
+        allowed: bool,
+        parse: impl FnOnce(&mut Self) -> Option<T>,
+    ) -> Option<T> {
+        let saved = std::mem::replace(&mut self.no_struct_literal, !allowed);
+        let result = parse(self);
+        self.no_struct_literal = saved;
+        result
+    }
+
+    fn parse_expr_list(&mut self, close: TokenKind) -> Option<Vec<Expr>> {
         let mut args = Vec::new();
-        if matches!(self.peek_kind(), TokenKind::RParen) {
+        if *self.peek_kind() == close {
             return Some(args);
         }
         loop {
-            let expr = self.parse_expr()?;
+            let expr = self.with_struct_literals(true, Self::parse_expr)?;
             args.push(expr);
             if matches!(self.peek_kind(), TokenKind::Comma) {
                 self.bump();
-                if matches!(self.peek_kind(), TokenKind::RParen) {
+                if *self.peek_kind() == close {
                     break;
                 }
                 continue;
@@ -334,15 +1159,33 @@ impl Parser {
         Some(args)
     }
 
+    fn parse_interpolation(&mut self, tokens: Vec<Token>) -> Option<Expr> {
+        let mut parser = Parser::new(tokens, self.peek_span().source);
+        let expr = parser.parse_expr();
+        if expr.is_some() && !parser.at_eof() {
+            let span = parser.peek_span();
+            parser.error("expected `}` after interpolated expression", span);
+        }
+        self.diagnostics.append(&mut parser.diagnostics);
+        expr
+    }
+
     fn parse_primary(&mut self) -> Option<Expr> {
         let token = self.peek().clone();
         match token.kind {
             TokenKind::Identifier(name) => {
                 self.bump();
-                Some(Expr::Name(NameRef {
+                let name = NameRef {
                     span: token.span,
                     name,
-                }))
+                };
+                if matches!(self.peek_kind(), TokenKind::DoubleColon) {
+                    return self.parse_path(name).map(Expr::Path);
+                }
+                if self.at_struct_literal() {
+                    return self.parse_struct_literal(name);
+                }
+                Some(Expr::Name(name))
             }
             TokenKind::Int(value) => {
                 self.bump();
@@ -351,6 +1194,13 @@ impl Parser {
                     value,
                 })))
             }
+            TokenKind::Float(value) => {
+                self.bump();
+                Some(Expr::Literal(Literal::Float(FloatLiteral {
+                    span: token.span,
+                    value,
+                })))
+            }
             TokenKind::String(value) => {
                 self.bump();
                 Some(Expr::Literal(Literal::String(StringLiteral {
@@ -358,6 +1208,22 @@ impl Parser {
                     value,
                 })))
             }
+            TokenKind::FString(parts) => {
+                self.bump();
+                let segments = parts
+                    .into_iter()
+                    .filter_map(|part| match part {
+                        FStringPart::Text(text) => Some(FStringSegment::Text(text)),
+                        FStringPart::Expr(tokens) => {
+                            self.parse_interpolation(tokens).map(FStringSegment::Expr)
+                        }
+                    })
+                    .collect();
+                Some(Expr::FString(FStringExpr {
+                    span: token.span,
+                    segments,
+                }))
+            }
             TokenKind::Keyword(Keyword::True) => {
                 self.bump();
                 Some(Expr::Literal(Literal::Bool(BoolLiteral {
@@ -379,10 +1245,29 @@ impl Parser {
                     let span = start.merge(end);
                     return Some(Expr::Literal(Literal::Unit(UnitLiteral { span })));
                 }
-                let expr = self.parse_expr()?;
+                let expr = self.with_struct_literals(true, Self::parse_expr)?;
+                if matches!(self.peek_kind(), TokenKind::Comma) {
+                    self.bump();
+                    let mut elements = vec![expr];
+                    elements.extend(self.parse_expr_list(TokenKind::RParen)?);
+                    let end = self.expect_rparen()?.span;
+                    return Some(Expr::Tuple(TupleExpr {
+                        span: start.merge(end),
+                        elements,
+                    }));
+                }
                 self.expect_rparen()?;
                 Some(Expr::Paren(Box::new(expr)))
             }
+            TokenKind::LBracket => {
+                let start = self.bump().span;
+                let elements = self.parse_expr_list(TokenKind::RBracket)?;
+                let end = self.expect_rbracket()?.span;
+                Some(Expr::List(ListExpr {
+                    span: start.merge(end),
+                    elements,
+                }))
+            }
             TokenKind::LBrace => self.parse_block_expr(),
             _ => {
                 self.error("expected expression", token.span);
@@ -391,18 +1276,37 @@ impl Parser {
         }
     }
 
+    fn peek_assign_op(&self) -> Option<Option<BinaryOp>> {
+        match self.peek_kind() {
+            TokenKind::Equal => Some(None),
+            TokenKind::PlusEq => Some(Some(BinaryOp::Add)),
+            TokenKind::MinusEq => Some(Some(BinaryOp::Sub)),
+            TokenKind::StarEq => Some(Some(BinaryOp::Mul)),
+            TokenKind::SlashEq => Some(Some(BinaryOp::Div)),
+            _ => None,
+        }
+    }
+
     fn peek_binary_op(&self) -> Option<(BinaryOp, u8)> {
         match self.peek_kind() {
-            TokenKind::Plus => Some((BinaryOp::Add, 10)),
-            TokenKind::Minus => Some((BinaryOp::Sub, 10)),
-            TokenKind::Star => Some((BinaryOp::Mul, 20)),
-            TokenKind::Slash => Some((BinaryOp::Div, 20)),
+            TokenKind::Plus => Some((BinaryOp::Add, 11)),
+            TokenKind::Minus => Some((BinaryOp::Sub, 11)),
+            TokenKind::Star => Some((BinaryOp::Mul, 12)),
+            TokenKind::Slash => Some((BinaryOp::Div, 12)),
+            TokenKind::Percent => Some((BinaryOp::Mod, 12)),
+            TokenKind::Shl => Some((BinaryOp::Shl, 10)),
+            TokenKind::Shr => Some((BinaryOp::Shr, 10)),
+            TokenKind::Amp => Some((BinaryOp::BitAnd, 9)),
+            TokenKind::Caret => Some((BinaryOp::BitXor, 8)),
+            TokenKind::Pipe => Some((BinaryOp::BitOr, 7)),
             TokenKind::EqEq => Some((BinaryOp::Eq, 5)),
             TokenKind::BangEq => Some((BinaryOp::Ne, 5)),
             TokenKind::Lt => Some((BinaryOp::Lt, 6)),
             TokenKind::Gt => Some((BinaryOp::Gt, 6)),
             TokenKind::Le => Some((BinaryOp::Le, 6)),
             TokenKind::Ge => Some((BinaryOp::Ge, 6)),
+            TokenKind::AmpAmp => Some((BinaryOp::And, 4)),
+            TokenKind::PipePipe => Some((BinaryOp::Or, 3)),
             _ => None,
         }
     }
@@ -477,6 +1381,16 @@ impl Parser {
         }
     }
 
+    fn expect_rbracket(&mut self) -> Option<Token> {
+        if matches!(self.peek_kind(), TokenKind::RBracket) {
+            Some(self.bump())
+        } else {
+            let span = self.peek_span();
+            self.error("expected `]`", span);
+            None
+        }
+    }
+
     fn expect_lbrace(&mut self) -> Option<Token> {
         if matches!(self.peek_kind(), TokenKind::LBrace) {
             Some(self.bump())
@@ -618,6 +1532,10 @@ impl Parser {
         self.peek().span
     }
 
+    fn ended_suite(&self) -> bool {
+        self.pos > 0 && matches!(self.tokens[self.pos - 1].kind, TokenKind::Dedent)
+    }
+
     fn bump(&mut self) -> Token {
         let token = self.tokens[self.pos].clone();
         self.pos += 1;
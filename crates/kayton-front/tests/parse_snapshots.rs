@@ -1,5 +1,5 @@
 use insta::assert_snapshot;
-use kayton_front::tests_support::parse_str;
+use kayton_front::tests_support::{parse_files, parse_str};
 
 #[test]
 fn parse_simple_function() {
@@ -63,3 +63,288 @@ fn parse_while_loop() {
         format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
     );
 }
+
+#[test]
+fn parse_match_expression() {
+    let source = r#"fn describe(n):
+    match n:
+        0 => "zero"
+        1 | 2 if n > 0 => "small"
+        _ =>:
+            "many"
+"#;
+    let output = parse_str("describe.ktn", source);
+    assert_snapshot!(
+        "match_expression",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_for_loop() {
+    let source = r#"fn main():
+    for i in 0..10:
+        if i == 3:
+            continue
+        let found = while true:
+            break i
+    for _ in 1..=n { break }
+"#;
+    let output = parse_str("for.ktn", source);
+    assert_snapshot!(
+        "for_loop",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_assignment() {
+    let source = r#"fn main():
+    let mut count = 0
+    count = count + 1
+    count += 2
+    1 = count
+"#;
+    let output = parse_str("assign.ktn", source);
+    assert_snapshot!(
+        "assignment",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_struct_declarations() {
+    let source = r#"struct Point:
+    x: Int
+    y: Int
+
+struct Pair { left: Point, right: Point }
+
+struct Unit: value: ()
+
+fn main():
+    let p = Point { x: 1, y: 2 }
+    p.x = p.y
+    if p.x == 2: p else: Point { x: 0, y: 0 }
+"#;
+    let output = parse_str("structs.ktn", source);
+    assert_snapshot!(
+        "struct_declarations",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_enum_declarations() {
+    let source = r#"enum Shape: Circle(r: Int), Rect(w, h)
+
+enum Token { Eof, Word(text: String) }
+
+fn main():
+    let s = Shape::Circle(2)
+    if let Shape::Rect(w, _) = s: w else: 0
+    match Token::Eof:
+        Token::Word(t) => 1
+        Token::Eof => 0
+"#;
+    let output = parse_str("enums.ktn", source);
+    assert_snapshot!(
+        "enum_declarations",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_impl_block_and_method_calls() {
+    let source = r#"struct Counter:
+    count: Int
+
+impl Counter:
+    fn new():
+        Counter { count: 0 }
+
+    fn bump(self, by):
+        self.count += by
+        self
+
+fn main():
+    Counter::new().bump(2).bump(3).count
+"#;
+    let output = parse_str("impl.ktn", source);
+    assert_snapshot!(
+        "impl_block",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_lambdas() {
+    let source = r#"fn main():
+    let add = |a, b| a + b
+    let thunk = || add(1, 2)
+    let adder = |k| |x| x + k
+    adder(1)(thunk())
+"#;
+    let output = parse_str("lambdas.ktn", source);
+    assert_snapshot!(
+        "lambdas",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_list_literals_and_indexing() {
+    let source = r#"fn main():
+    let grid = [[1, 2], [3, 4],]
+    grid[1][0] += 10
+    let counts = map()
+    counts["a"] = len(grid)
+    grid[0][1] + counts["a"]
+"#;
+    let output = parse_str("collections.ktn", source);
+    assert_snapshot!(
+        "collections",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_tuples_and_let_patterns() {
+    let source = r#"fn divmod(a, b):
+    (a / b, a % b)
+
+fn main():
+    let (q, r) = divmod(7, 2)
+    let _ = (q,)
+    match (q, r):
+        ((), _) => 0
+        (x, y) => x + y
+"#;
+    let output = parse_str("tuples.ktn", source);
+    assert_snapshot!(
+        "tuples",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_float_literals() {
+    let source = r#"fn main():
+    let ratio = 1.5 * 2e3 - 6.25E-2
+    for i in 0..3:
+        ratio += 1_000.0
+    -ratio
+"#;
+    let output = parse_str("floats.ktn", source);
+    assert_snapshot!(
+        "floats",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_string_escapes_and_interpolation() {
+    let source = r#"fn main():
+    let plain = "tab\there \"quoted\" \u{263A}"
+    let raw = r"C:\dir\n"
+    let block = """two
+lines"""
+    f"{plain}: {len(raw) + 1} {{literal}}"
+"#;
+    let output = parse_str("strings.ktn", source);
+    assert_snapshot!(
+        "strings",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_integer_literal_forms() {
+    let source = r#"fn main():
+    let ok = (0xFF, 0o17, 0b1010_0101, 1_000_000, -9223372036854775808)
+    let bad = (9223372036854775808, 0b102, 0x, 0xfff_ffff_ffff_ffff_f)
+    ok
+"#;
+    let output = parse_str("integers.ktn", source);
+    assert_snapshot!(
+        "integers",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_type_annotations() {
+    let source = r#"fn apply(f: fn(Int) -> Int, pair: (Int, String), xs: List[Int]) -> (Int,):
+    let (a, _): (Int, String) = pair
+    let n: Int = f(a)
+    let g = |x: Float| x
+    (n,)
+"#;
+    let output = parse_str("annotations.ktn", source);
+    assert_snapshot!(
+        "annotations",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_module_imports() {
+    let main = r#"use util
+use shapes::Point as P
+
+fn main(double):
+    let p = P { x: util::double(1), y: 2 }
+    double(util::secret(p.x)) + util::missing
+"#;
+    let util = r#"use shapes
+
+pub fn double(x):
+    helper(x) * 2
+
+fn helper(x):
+    x
+
+fn secret(x):
+    shapes::origin(x)
+"#;
+    let shapes = r#"use util
+
+pub struct Point:
+    x: Int
+    y: Int
+
+fn origin(x):
+    Point { x: x, y: 0 }
+"#;
+    let output = parse_files(
+        "main.ktn",
+        &[
+            ("main.ktn", main),
+            ("util.ktn", util),
+            ("shapes.ktn", shapes),
+        ],
+    );
+    assert_snapshot!(
+        "module_imports",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
+
+#[test]
+fn parse_generic_items() {
+    let source = r#"struct Pair[A, B]:
+    left: A
+    right: B
+
+fn first[T](xs: List[T]) -> T:
+    xs[0]
+
+fn empty[]():
+    ()
+"#;
+    let output = parse_str("generics.ktn", source);
+    assert_snapshot!(
+        "generic_items",
+        format!("{:#?}\n---\n{:#?}", output.module, output.diagnostics)
+    );
+}
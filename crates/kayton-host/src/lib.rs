@@ -9,6 +9,10 @@ use kayton_abi::{
 use kayton_api::{KayCtx, KayExtension, KayResult};
 use thiserror::Error;
 
+mod plugin;
+
+pub use plugin::{load_plugin, PluginError};
+
 static CONTEXTS: OnceLock<Mutex<HashMap<KayContextId, Arc<ContextInner>>>> = OnceLock::new();
 static NEXT_CONTEXT_ID: AtomicU64 = AtomicU64::new(1);
 
@@ -71,8 +75,17 @@ struct ContextInner {
 
 impl ContextInner {
     fn alloc_value(&self, value: StoredValue) -> KayAbiResult<KayRawHandle> {
-        let handle = self.next_handle.fetch_add(1, Ordering::SeqCst);
         let mut handles = self.handles.lock().unwrap();
+        let children = value.children();
+        if children.iter().any(|child| !handles.contains_key(child)) {
+            return Err(error(KayErrorCode::NotFound, "element handle not found"));
+        }
+        for child in children {
+            if let Some(entry) = handles.get_mut(&child) {
+                entry.refs += 1;
+            }
+        }
+        let handle = self.next_handle.fetch_add(1, Ordering::SeqCst);
         handles.insert(handle, HandleEntry { value, refs: 1 });
         Ok(handle)
     }
@@ -88,18 +101,23 @@ impl ContextInner {
 
     fn dec_ref(&self, handle: KayRawHandle) -> KayAbiResult<()> {
         let mut handles = self.handles.lock().unwrap();
-        let entry = handles
-            .get_mut(&handle)
-            .ok_or_else(|| error(KayErrorCode::NotFound, "handle not found"))?;
-        if entry.refs == 0 {
-            return Err(error(
-                KayErrorCode::GeneralFailure,
-                "invalid refcount state",
-            ));
-        }
-        entry.refs -= 1;
-        if entry.refs == 0 {
-            handles.remove(&handle);
+        let mut pending = vec![handle];
+        while let Some(handle) = pending.pop() {
+            let entry = handles
+                .get_mut(&handle)
+                .ok_or_else(|| error(KayErrorCode::NotFound, "handle not found"))?;
+            if entry.refs == 0 {
+                return Err(error(
+                    KayErrorCode::GeneralFailure,
+                    "invalid refcount state",
+                ));
+            }
+            entry.refs -= 1;
+            if entry.refs == 0 {
+                if let Some(entry) = handles.remove(&handle) {
+                    pending.extend(entry.value.children());
+                }
+            }
         }
         Ok(())
     }
@@ -167,9 +185,12 @@ struct HandleEntry {
 #[derive(Clone)]
 enum StoredValue {
     Int(i64),
+    Float(f64),
     Bool(bool),
     String(Arc<str>),
     Bytes(Arc<[u8]>),
+    List(Arc<[KayRawHandle]>),
+    Map(Arc<[(KayRawHandle, KayRawHandle)]>),
     Unit,
     Capsule {
         tag: &'static str,
@@ -181,19 +202,37 @@ impl StoredValue {
     fn describe(&self) -> KayValueKind {
         match self {
             StoredValue::Int(value) => KayValueKind::Int(*value),
+            StoredValue::Float(value) => KayValueKind::Float(*value),
             StoredValue::Bool(value) => KayValueKind::Bool(*value),
             StoredValue::String(value) => KayValueKind::String(value.clone()),
             StoredValue::Bytes(value) => KayValueKind::Bytes(value.clone()),
+            StoredValue::List(items) => KayValueKind::List(items.clone()),
+            StoredValue::Map(entries) => KayValueKind::Map(entries.clone()),
             StoredValue::Unit => KayValueKind::Unit,
             StoredValue::Capsule { tag, .. } => KayValueKind::Capsule { tag },
         }
     }
+
+    fn children(&self) -> Vec<KayRawHandle> {
+        match self {
+            StoredValue::List(items) => items.to_vec(),
+            StoredValue::Map(entries) => entries
+                .iter()
+                .flat_map(|(key, value)| [*key, *value])
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
 }
 
 fn alloc_int(id: KayContextId, value: i64) -> KayAbiResult<KayRawHandle> {
     with_context(id, |ctx| ctx.alloc_value(StoredValue::Int(value)))
 }
 
+fn alloc_float(id: KayContextId, value: f64) -> KayAbiResult<KayRawHandle> {
+    with_context(id, |ctx| ctx.alloc_value(StoredValue::Float(value)))
+}
+
 fn alloc_bool(id: KayContextId, value: bool) -> KayAbiResult<KayRawHandle> {
     with_context(id, |ctx| ctx.alloc_value(StoredValue::Bool(value)))
 }
@@ -206,6 +245,17 @@ fn alloc_bytes(id: KayContextId, value: Arc<[u8]>) -> KayAbiResult<KayRawHandle>
     with_context(id, |ctx| ctx.alloc_value(StoredValue::Bytes(value)))
 }
 
+fn alloc_list(id: KayContextId, items: Vec<KayRawHandle>) -> KayAbiResult<KayRawHandle> {
+    with_context(id, |ctx| ctx.alloc_value(StoredValue::List(items.into())))
+}
+
+fn alloc_map(
+    id: KayContextId,
+    entries: Vec<(KayRawHandle, KayRawHandle)>,
+) -> KayAbiResult<KayRawHandle> {
+    with_context(id, |ctx| ctx.alloc_value(StoredValue::Map(entries.into())))
+}
+
 fn alloc_unit(id: KayContextId) -> KayAbiResult<KayRawHandle> {
     with_context(id, |ctx| ctx.alloc_value(StoredValue::Unit))
 }
@@ -280,9 +330,12 @@ fn invoke_extension(
 
 static VTABLE: KayContextVTable = KayContextVTable {
     alloc_int,
+    alloc_float,
     alloc_bool,
     alloc_string,
     alloc_bytes,
+    alloc_list,
+    alloc_map,
     alloc_unit,
     inc_ref,
     dec_ref,
@@ -335,6 +388,14 @@ impl KayHost {
         Ok(())
     }
 
+    pub fn extension_names(&self) -> Vec<String> {
+        with_context(self.context.id, |ctx| {
+            let exts = ctx.extensions.lock().unwrap();
+            Ok(exts.iter().map(|ext| ext.name.to_string()).collect())
+        })
+        .unwrap_or_default()
+    }
+
     pub fn resolve(&self, name: &str) -> Option<KayHostSlot> {
         with_context(self.context.id, |ctx| {
             let names = ctx.name_to_slot.lock().unwrap();
@@ -373,6 +434,7 @@ mod tests {
         let host = KayHost::new();
         host.register_extension(sample_extension())
             .expect("register");
+        assert_eq!(host.extension_names(), ["test.add_one"]);
         let ctx = host.api_ctx();
         let input = 41_i64.to_kay(&ctx).expect("alloc");
         let raw = call_host(ctx.raw().id, 0, vec![input.raw()]).expect("call");
@@ -380,4 +442,23 @@ mod tests {
         let value = i64::from_kay(&ctx, &result).expect("from_kay");
         assert_eq!(value, 42);
     }
+
+    #[test]
+    fn list_handles_keep_elements_alive() {
+        let host = KayHost::new();
+        let ctx = host.api_ctx();
+        let item = 7_i64.to_kay(&ctx).expect("alloc");
+        let raw_item = item.raw();
+        let list = ctx.alloc_list(&[item]).expect("alloc list");
+        let KayValueKind::List(items) = list.describe().expect("describe") else {
+            panic!("expected a list");
+        };
+        assert_eq!(&*items, &[raw_item]);
+        assert!(matches!(
+            ctx.inspect(raw_item).expect("element alive").kind,
+            KayValueKind::Int(7)
+        ));
+        drop(list);
+        assert!(ctx.inspect(raw_item).is_err());
+    }
 }
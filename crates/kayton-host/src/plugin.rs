@@ -0,0 +1,46 @@
+//! Loading of native plugin libraries. This is the only place the host needs
+//! `unsafe`: opening a library runs its initializers, and the entry point's
+//! signature cannot be checked.
+#![allow(unsafe_code)]
+
+use std::path::{Path, PathBuf};
+
+use kayton_api::{KayExtension, KayPluginEntry, PLUGIN_ENTRY};
+use libloading::Library;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("cannot load plugin `{}`: {source}", path.display())]
+    Load {
+        path: PathBuf,
+        source: libloading::Error,
+    },
+    #[error("plugin `{}` does not export `{PLUGIN_ENTRY}`", path.display())]
+    MissingEntry {
+        path: PathBuf,
+        source: libloading::Error,
+    },
+}
+
+/// Loads the plugin library at `path` and returns the extensions it exports
+/// through [`kayton_api::export_extensions!`].
+///
+/// The library is never unloaded: the extensions and the capsules they create
+/// point into its code and may be used until the process exits.
+pub fn load_plugin(path: &Path) -> Result<&'static [KayExtension], PluginError> {
+    // SAFETY: plugins are trusted code named by the project manifest; their
+    // initializers run as part of loading them.
+    let library = unsafe { Library::new(path) }.map_err(|source| PluginError::Load {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    // SAFETY: `export_extensions!` defines the entry with exactly this type.
+    let entry: KayPluginEntry = *unsafe { library.get::<KayPluginEntry>(PLUGIN_ENTRY.as_bytes()) }
+        .map_err(|source| PluginError::MissingEntry {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    std::mem::forget(library);
+    Ok(entry())
+}
@@ -0,0 +1,16 @@
+//! Example plugin, built as a shared library and loaded by listing it under
+//! `plugins` in a project's `kayton.toml`.
+
+use kayton_api::{export_extensions, KayCtx, KayExtension, KayResult};
+use kayton_plugin_macros::kayton_extension;
+
+#[kayton_extension(name = "triple", doc = "Multiply an int by three.")]
+pub fn triple(_ctx: &KayCtx, value: i64) -> KayResult<i64> {
+    Ok(value * 3)
+}
+
+pub fn extensions() -> &'static [KayExtension] {
+    &[TRIPLE_EXTENSION]
+}
+
+export_extensions!(extensions);
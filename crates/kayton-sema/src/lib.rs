@@ -1,9 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 use kayton_front::diagnostics::Diagnostic;
 use kayton_front::hir::*;
-use kayton_front::interner::Symbol;
+use kayton_front::interner::{Symbol, SymbolInterner};
 use kayton_front::span::Span;
+use smol_str::SmolStr;
 
 pub mod fast {
     use super::*;
@@ -11,23 +13,123 @@ pub mod fast {
     #[derive(Debug, Clone, PartialEq, Eq)]
     pub enum FastType {
         Int,
+        Float,
         Bool,
         String,
         Unit,
+        Struct(SmolStr, Vec<FastType>),
+        Enum(SmolStr),
+        List(Box<FastType>),
+        Map(Box<FastType>, Box<FastType>),
+        Tuple(Vec<FastType>),
         Function {
-            arity: usize,
+            params: Vec<FastType>,
             return_ty: Box<FastType>,
         },
+        Var(u32),
+        /// A type parameter of the generic item being checked. It only
+        /// unifies with itself; uses of the item replace it with a fresh
+        /// variable.
+        Param(SmolStr),
         Unknown,
     }
 
     impl FastType {
-        fn unify(&self, other: &FastType) -> FastType {
-            match (self, other) {
-                (FastType::Unknown, _) => other.clone(),
-                (_, FastType::Unknown) => self.clone(),
-                _ if self == other => self.clone(),
-                _ => FastType::Unknown,
+        fn free_vars(&self, out: &mut Vec<u32>) {
+            match self {
+                FastType::Var(var) if !out.contains(var) => out.push(*var),
+                FastType::List(elem) => elem.free_vars(out),
+                FastType::Map(key, value) => {
+                    key.free_vars(out);
+                    value.free_vars(out);
+                }
+                FastType::Tuple(elements) | FastType::Struct(_, elements) => {
+                    for element in elements {
+                        element.free_vars(out);
+                    }
+                }
+                FastType::Function { params, return_ty } => {
+                    for param in params {
+                        param.free_vars(out);
+                    }
+                    return_ty.free_vars(out);
+                }
+                _ => {}
+            }
+        }
+
+        fn substitute(&self, mapping: &HashMap<u32, FastType>) -> FastType {
+            self.map_leaves(&|ty| match ty {
+                FastType::Var(var) => mapping.get(var).cloned(),
+                _ => None,
+            })
+        }
+
+        fn replace_params(&self, mapping: &HashMap<SmolStr, FastType>) -> FastType {
+            self.map_leaves(&|ty| match ty {
+                FastType::Param(name) => mapping.get(name).cloned(),
+                _ => None,
+            })
+        }
+
+        fn map_leaves(&self, f: &dyn Fn(&FastType) -> Option<FastType>) -> FastType {
+            if let Some(ty) = f(self) {
+                return ty;
+            }
+            match self {
+                FastType::List(elem) => FastType::List(Box::new(elem.map_leaves(f))),
+                FastType::Map(key, value) => {
+                    FastType::Map(Box::new(key.map_leaves(f)), Box::new(value.map_leaves(f)))
+                }
+                FastType::Tuple(elements) => FastType::Tuple(
+                    elements
+                        .iter()
+                        .map(|element| element.map_leaves(f))
+                        .collect(),
+                ),
+                FastType::Struct(name, args) => FastType::Struct(
+                    name.clone(),
+                    args.iter().map(|arg| arg.map_leaves(f)).collect(),
+                ),
+                FastType::Function { params, return_ty } => FastType::Function {
+                    params: params.iter().map(|param| param.map_leaves(f)).collect(),
+                    return_ty: Box::new(return_ty.map_leaves(f)),
+                },
+                _ => self.clone(),
+            }
+        }
+    }
+
+    impl fmt::Display for FastType {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                FastType::Int => write!(f, "Int"),
+                FastType::Float => write!(f, "Float"),
+                FastType::Bool => write!(f, "Bool"),
+                FastType::String => write!(f, "String"),
+                FastType::Unit => write!(f, "()"),
+                FastType::Struct(name, args) if !args.is_empty() => {
+                    let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+                    write!(f, "{name}[{}]", args.join(", "))
+                }
+                FastType::Struct(name, _) | FastType::Enum(name) => write!(f, "{name}"),
+                FastType::List(elem) => write!(f, "List[{elem}]"),
+                FastType::Map(key, value) => write!(f, "Map[{key}, {value}]"),
+                FastType::Tuple(elements) => {
+                    let elements: Vec<String> = elements.iter().map(ToString::to_string).collect();
+                    if elements.len() == 1 {
+                        write!(f, "({},)", elements[0])
+                    } else {
+                        write!(f, "({})", elements.join(", "))
+                    }
+                }
+                FastType::Function { params, return_ty } => {
+                    let params: Vec<String> = params.iter().map(ToString::to_string).collect();
+                    write!(f, "fn({}) -> {return_ty}", params.join(", "))
+                }
+                FastType::Var(var) => write!(f, "?{var}"),
+                FastType::Param(name) => write!(f, "{name}"),
+                FastType::Unknown => write!(f, "_"),
             }
         }
     }
@@ -35,6 +137,9 @@ pub mod fast {
     #[derive(Debug, Default)]
     pub struct FastAnalysis {
         pub types: HashMap<HirId, FastType>,
+        pub field_indices: HashMap<HirId, usize>,
+        pub method_targets: HashMap<HirId, String>,
+        pub captures: HashMap<HirId, Vec<Symbol>>,
         pub diagnostics: Vec<Diagnostic>,
     }
 
@@ -42,13 +147,81 @@ pub mod fast {
         pub fn type_of(&self, id: HirId) -> Option<&FastType> {
             self.types.get(&id)
         }
+
+        pub fn field_index(&self, id: HirId) -> Option<usize> {
+            self.field_indices.get(&id).copied()
+        }
+
+        pub fn method_target(&self, id: HirId) -> Option<&str> {
+            self.method_targets.get(&id).map(String::as_str)
+        }
+
+        pub fn captures(&self, id: HirId) -> &[Symbol] {
+            self.captures.get(&id).map(Vec::as_slice).unwrap_or(&[])
+        }
+    }
+
+    /// The known signature of a host function. Calls to a host function
+    /// without one are dynamically typed.
+    #[derive(Debug, Clone)]
+    pub struct Builtin {
+        pub name: &'static str,
+        /// Type parameters of `ty`, replaced by fresh variables at each use.
+        pub type_params: &'static [&'static str],
+        pub ty: FastType,
+    }
+
+    /// Signatures of the standard library's conversions and `map`.
+    pub fn stdlib_builtins() -> Vec<Builtin> {
+        let function = |params: Vec<FastType>, return_ty| FastType::Function {
+            params,
+            return_ty: Box::new(return_ty),
+        };
+        let param = |name: &str| Box::new(FastType::Param(name.into()));
+        vec![
+            Builtin {
+                name: "int",
+                type_params: &[],
+                ty: function(vec![FastType::Unknown], FastType::Int),
+            },
+            Builtin {
+                name: "float",
+                type_params: &[],
+                ty: function(vec![FastType::Unknown], FastType::Float),
+            },
+            Builtin {
+                name: "str",
+                type_params: &[],
+                ty: function(vec![FastType::Unknown], FastType::String),
+            },
+            Builtin {
+                name: "map",
+                type_params: &["K", "V"],
+                ty: function(Vec::new(), FastType::Map(param("K"), param("V"))),
+            },
+        ]
     }
 
-    pub fn analyze(module: &HirModule) -> FastAnalysis {
-        let mut ctx = Context::new();
+    /// Analyzes `module`. `host_functions` names the extensions registered
+    /// with the host, which resolve as dynamically typed callees unless
+    /// `builtins` gives them a signature.
+    pub fn analyze(
+        module: &HirModule,
+        host_functions: &[String],
+        builtins: &[Builtin],
+    ) -> FastAnalysis {
+        let mut ctx = Context::new(&module.interner, host_functions, builtins);
         ctx.analyze_module(module);
+        let types = ctx
+            .types
+            .iter()
+            .map(|(id, ty)| (*id, ctx.resolve(ty)))
+            .collect();
         FastAnalysis {
-            types: ctx.types,
+            types,
+            field_indices: ctx.field_indices,
+            method_targets: ctx.method_targets,
+            captures: ctx.captures,
             diagnostics: ctx.diagnostics,
         }
     }
@@ -56,64 +229,724 @@ pub mod fast {
     #[derive(Clone)]
     struct Binding {
         ty: FastType,
+        mutable: bool,
+        /// Set once a closure has captured this mutable binding; closures
+        /// capture by value, so it may not be assigned from then on.
+        captured: bool,
+        function: Option<HirId>,
     }
 
     struct FunctionContext {
         return_ty: FastType,
+        annotated: bool,
         has_explicit_return: bool,
     }
 
-    struct Context {
+    #[derive(Clone, Default)]
+    struct StructInfo {
+        params: Vec<SmolStr>,
+        fields: Vec<(Symbol, FastType)>,
+    }
+
+    #[derive(Clone, Default)]
+    struct EnumInfo {
+        variants: Vec<(Symbol, Vec<FastType>)>,
+    }
+
+    #[derive(Clone)]
+    struct MethodInfo {
+        function: HirId,
+        arity: usize,
+        has_self: bool,
+    }
+
+    struct LambdaContext {
+        scope_depth: usize,
+        captures: Vec<Symbol>,
+    }
+
+    struct LoopContext {
+        allows_value: bool,
+        break_ty: Option<FastType>,
+        /// Scope depth of the loop body; bindings below it outlive an
+        /// iteration.
+        scope_depth: usize,
+        /// Outer mutable bindings assigned in the loop before any capture,
+        /// as `(name, depth, assignment span)`.
+        assigned: Vec<(Symbol, usize, Span)>,
+        /// Outer mutable bindings captured by closures created in the loop.
+        captured: Vec<(Symbol, usize)>,
+    }
+
+    /// A method call as seen by the checker, detached from the HIR so it can
+    /// be checked after its receiver type is known.
+    struct MethodSite {
+        id: HirId,
+        span: Span,
+        method: HirNameRef,
+        arg_spans: Vec<Span>,
+    }
+
+    /// A use whose base type was still unsolved when it was analyzed. It is
+    /// checked once the enclosing function body has been inferred.
+    enum Deferred {
+        Field {
+            id: HirId,
+            field: HirNameRef,
+            base: FastType,
+            base_span: Span,
+            ty: FastType,
+        },
+        Index {
+            span: Span,
+            index_span: Span,
+            base: FastType,
+            base_span: Span,
+            index_ty: FastType,
+            ty: FastType,
+        },
+        Method {
+            site: MethodSite,
+            receiver: FastType,
+            receiver_span: Span,
+            args: Vec<FastType>,
+            ty: FastType,
+        },
+    }
+
+    struct Context<'a> {
+        interner: &'a SymbolInterner,
+        host_functions: HashSet<&'a str>,
+        builtins: HashMap<&'a str, &'a Builtin>,
         types: HashMap<HirId, FastType>,
+        field_indices: HashMap<HirId, usize>,
+        method_targets: HashMap<HirId, String>,
+        captures: HashMap<HirId, Vec<Symbol>>,
+        structs: HashMap<SmolStr, StructInfo>,
+        enums: HashMap<SmolStr, EnumInfo>,
+        methods: HashMap<SmolStr, HashMap<Symbol, MethodInfo>>,
         diagnostics: Vec<Diagnostic>,
         scopes: Vec<HashMap<Symbol, Binding>>,
         current_function: Option<FunctionContext>,
+        loops: Vec<LoopContext>,
+        lambdas: Vec<LambdaContext>,
+        deferred: Vec<Deferred>,
+        substitution: Vec<Option<FastType>>,
+        functions: Vec<HirId>,
+        pending: HashMap<HirId, &'a HirFunction>,
+        schemes: HashMap<HirId, Vec<u32>>,
+        type_params: HashMap<HirId, Vec<SmolStr>>,
+        type_scope: Vec<SmolStr>,
     }
 
-    impl Context {
-        fn new() -> Self {
+    impl<'a> Context<'a> {
+        fn new(
+            interner: &'a SymbolInterner,
+            host_functions: &'a [String],
+            builtins: &'a [Builtin],
+        ) -> Self {
             Self {
+                interner,
+                host_functions: host_functions.iter().map(String::as_str).collect(),
+                builtins: builtins
+                    .iter()
+                    .map(|builtin| (builtin.name, builtin))
+                    .collect(),
                 types: HashMap::new(),
+                field_indices: HashMap::new(),
+                method_targets: HashMap::new(),
+                captures: HashMap::new(),
+                structs: HashMap::new(),
+                enums: HashMap::new(),
+                methods: HashMap::new(),
                 diagnostics: Vec::new(),
                 scopes: vec![HashMap::new()],
                 current_function: None,
+                loops: Vec::new(),
+                lambdas: Vec::new(),
+                deferred: Vec::new(),
+                substitution: Vec::new(),
+                functions: Vec::new(),
+                pending: HashMap::new(),
+                schemes: HashMap::new(),
+                type_params: HashMap::new(),
+                type_scope: Vec::new(),
             }
         }
 
-        fn analyze_module(&mut self, module: &HirModule) {
+        fn analyze_module(&mut self, module: &'a HirModule) {
+            self.declare_types(module);
+            self.declare_impls(module);
             for item in &module.items {
                 if let HirItem::Function(func) = item {
-                    let func_ty = FastType::Function {
-                        arity: func.params.len(),
-                        return_ty: Box::new(FastType::Unknown),
-                    };
+                    let params = self.declare_type_params(&func.type_params, &[]);
+                    self.type_params.insert(func.id, params);
+                    let func_ty = self.signature(func, None);
                     self.types.insert(func.id, func_ty.clone());
-                    self.bind(func.name, func_ty);
+                    self.functions.push(func.id);
+                    self.pending.insert(func.id, func);
+                    self.declare(
+                        func.name,
+                        Binding {
+                            ty: func_ty,
+                            mutable: false,
+                            captured: false,
+                            function: Some(func.id),
+                        },
+                    );
+                }
+            }
+
+            // Globals are bound before any function body is checked so that
+            // functions can read them regardless of declaration order.
+            for item in &module.items {
+                if let HirItem::Let(binding) = item {
+                    self.analyze_let(binding);
+                }
+            }
+            for item in &module.items {
+                match item {
+                    HirItem::Let(_) => {}
+                    HirItem::Function(func) => self.ensure_analyzed(func.id),
+                    HirItem::Impl(block) => {
+                        for method in &block.methods {
+                            self.ensure_analyzed(method.id);
+                        }
+                    }
+                    HirItem::Struct(_) | HirItem::Enum(_) => {}
+                }
+            }
+            self.check_deferred();
+        }
+
+        /// Analyzes a function body before its first use so callers see its
+        /// generalized signature regardless of declaration order.
+        fn ensure_analyzed(&mut self, function: HirId) {
+            let Some(func) = self.pending.remove(&function) else {
+                return;
+            };
+            let scopes = self.scopes.split_off(1);
+            let current_function = self.current_function.take();
+            let loops = std::mem::take(&mut self.loops);
+            let lambdas = std::mem::take(&mut self.lambdas);
+            self.analyze_function(func);
+            self.scopes.extend(scopes);
+            self.current_function = current_function;
+            self.loops = loops;
+            self.lambdas = lambdas;
+        }
+
+        fn fresh_var(&mut self) -> FastType {
+            let var = self.substitution.len() as u32;
+            self.substitution.push(None);
+            FastType::Var(var)
+        }
+
+        fn shallow(&self, ty: &FastType) -> FastType {
+            let mut ty = ty.clone();
+            while let FastType::Var(var) = ty {
+                match &self.substitution[var as usize] {
+                    Some(bound) => ty = bound.clone(),
+                    None => break,
+                }
+            }
+            ty
+        }
+
+        fn resolve(&self, ty: &FastType) -> FastType {
+            match self.shallow(ty) {
+                FastType::List(elem) => FastType::List(Box::new(self.resolve(&elem))),
+                FastType::Map(key, value) => {
+                    FastType::Map(Box::new(self.resolve(&key)), Box::new(self.resolve(&value)))
+                }
+                FastType::Tuple(elements) => FastType::Tuple(
+                    elements
+                        .iter()
+                        .map(|element| self.resolve(element))
+                        .collect(),
+                ),
+                FastType::Struct(name, args) => {
+                    FastType::Struct(name, args.iter().map(|arg| self.resolve(arg)).collect())
+                }
+                FastType::Function { params, return_ty } => FastType::Function {
+                    params: params.iter().map(|param| self.resolve(param)).collect(),
+                    return_ty: Box::new(self.resolve(&return_ty)),
+                },
+                other => other,
+            }
+        }
+
+        /// Unifies two types, binding type variables as needed. `Unknown`
+        /// marks dynamically typed values and unifies with anything.
+        fn unify(&mut self, lhs: &FastType, rhs: &FastType) -> bool {
+            let lhs = self.shallow(lhs);
+            let rhs = self.shallow(rhs);
+            match (&lhs, &rhs) {
+                (FastType::Unknown, _) | (_, FastType::Unknown) => true,
+                (FastType::Var(a), FastType::Var(b)) if a == b => true,
+                (FastType::Var(var), other) | (other, FastType::Var(var)) => {
+                    let mut vars = Vec::new();
+                    self.resolve(other).free_vars(&mut vars);
+                    if vars.contains(var) {
+                        return false;
+                    }
+                    self.substitution[*var as usize] = Some(other.clone());
+                    true
+                }
+                (FastType::List(lhs), FastType::List(rhs)) => self.unify(lhs, rhs),
+                (FastType::Map(lhs_key, lhs_value), FastType::Map(rhs_key, rhs_value)) => {
+                    self.unify(lhs_key, rhs_key) && self.unify(lhs_value, rhs_value)
+                }
+                (FastType::Tuple(lhs), FastType::Tuple(rhs)) => {
+                    lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(l, r)| self.unify(l, r))
+                }
+                (FastType::Struct(lhs_name, lhs), FastType::Struct(rhs_name, rhs)) => {
+                    lhs_name == rhs_name
+                        && lhs.len() == rhs.len()
+                        && lhs.iter().zip(rhs).all(|(l, r)| self.unify(l, r))
+                }
+                (
+                    FastType::Function {
+                        params: lhs_params,
+                        return_ty: lhs_ret,
+                    },
+                    FastType::Function {
+                        params: rhs_params,
+                        return_ty: rhs_ret,
+                    },
+                ) => {
+                    lhs_params.len() == rhs_params.len()
+                        && lhs_params
+                            .iter()
+                            .zip(rhs_params)
+                            .all(|(l, r)| self.unify(l, r))
+                        && self.unify(lhs_ret, rhs_ret)
+                }
+                _ => lhs == rhs,
+            }
+        }
+
+        fn expect(
+            &mut self,
+            expected: &FastType,
+            found: &FastType,
+            message: &str,
+            span: Span,
+        ) -> bool {
+            if self.unify(expected, found) {
+                return true;
+            }
+            let diag = Diagnostic::error(message, span).with_note(format!(
+                "expected `{}`, found `{}`",
+                self.resolve(expected),
+                self.resolve(found)
+            ));
+            self.diagnostics.push(diag);
+            false
+        }
+
+        fn instantiate(&mut self, function: HirId) -> FastType {
+            self.ensure_analyzed(function);
+            let ty = self
+                .types
+                .get(&function)
+                .cloned()
+                .unwrap_or(FastType::Unknown);
+            let ty = match self.schemes.get(&function).cloned() {
+                Some(vars) => {
+                    let mapping = vars
+                        .into_iter()
+                        .map(|var| (var, self.fresh_var()))
+                        .collect();
+                    self.resolve(&ty).substitute(&mapping)
+                }
+                None => ty,
+            };
+            match self.type_params.get(&function).cloned() {
+                Some(params) if !params.is_empty() => {
+                    let mapping = self.fresh_params(&params);
+                    self.resolve(&ty).replace_params(&mapping)
+                }
+                _ => ty,
+            }
+        }
+
+        fn fresh_params(&mut self, params: &[SmolStr]) -> HashMap<SmolStr, FastType> {
+            params
+                .iter()
+                .map(|param| (param.clone(), self.fresh_var()))
+                .collect()
+        }
+
+        /// Checks a generic item's type parameters for duplicates and
+        /// returns them after the `outer` parameters already in scope.
+        fn declare_type_params(
+            &mut self,
+            params: &[HirNameRef],
+            outer: &[SmolStr],
+        ) -> Vec<SmolStr> {
+            let mut names = outer.to_vec();
+            for param in params {
+                let name = SmolStr::from(self.name_of(param.name));
+                if names.contains(&name) {
+                    self.error(
+                        format!("type parameter `{name}` is declared more than once"),
+                        param.span,
+                    );
+                    continue;
+                }
+                names.push(name);
+            }
+            names
+        }
+
+        fn generalize(&mut self, function: HirId) {
+            let mut env = Vec::new();
+            for id in &self.functions {
+                if *id != function && !self.schemes.contains_key(id) {
+                    if let Some(ty) = self.types.get(id) {
+                        self.resolve(ty).free_vars(&mut env);
+                    }
+                }
+            }
+            for binding in self.scopes[0].values() {
+                if binding.function.is_none() {
+                    self.resolve(&binding.ty).free_vars(&mut env);
                 }
             }
+            let Some(ty) = self.types.get(&function).map(|ty| self.resolve(ty)) else {
+                return;
+            };
+            let mut vars = Vec::new();
+            ty.free_vars(&mut vars);
+            vars.retain(|var| !env.contains(var));
+            self.types.insert(function, ty);
+            self.schemes.insert(function, vars);
+        }
 
+        fn declare_types(&mut self, module: &HirModule) {
+            let mut structs = Vec::new();
+            let mut enums = Vec::new();
             for item in &module.items {
+                let (kind, name, span) = match item {
+                    HirItem::Struct(decl) => ("struct", decl.name, decl.span),
+                    HirItem::Enum(decl) => ("enum", decl.name, decl.span),
+                    _ => continue,
+                };
+                let name = SmolStr::from(self.name_of(name));
+                if self.structs.contains_key(&name) || self.enums.contains_key(&name) {
+                    self.error(format!("{kind} `{name}` is defined more than once"), span);
+                    continue;
+                }
                 match item {
-                    HirItem::Let(binding) => {
-                        let ty = self.analyze_expr(&binding.value);
-                        self.types.insert(binding.id, ty.clone());
-                        self.bind(binding.name, ty);
+                    HirItem::Struct(decl) => {
+                        let params = self.declare_type_params(&decl.type_params, &[]);
+                        self.structs.insert(
+                            name,
+                            StructInfo {
+                                params,
+                                fields: Vec::new(),
+                            },
+                        );
+                        structs.push(decl);
+                    }
+                    HirItem::Enum(decl) => {
+                        self.enums.insert(name, EnumInfo::default());
+                        enums.push(decl);
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            for decl in structs {
+                let name = SmolStr::from(self.name_of(decl.name));
+                let params = self.structs[&name].params.clone();
+                self.type_scope = params.clone();
+                let mut fields: Vec<(Symbol, FastType)> = Vec::new();
+                for field in &decl.fields {
+                    if fields.iter().any(|(name, _)| *name == field.name) {
+                        let message = format!(
+                            "field `{}` is declared more than once",
+                            self.name_of(field.name)
+                        );
+                        self.error(message, field.span);
+                        continue;
+                    }
+                    let ty = self.resolve_type(&field.ty);
+                    self.types.insert(field.id, ty.clone());
+                    fields.push((field.name, ty));
+                }
+                self.type_scope.clear();
+                let ty = FastType::Struct(
+                    name.clone(),
+                    params.iter().cloned().map(FastType::Param).collect(),
+                );
+                self.types.insert(decl.id, ty);
+                self.structs.insert(name, StructInfo { params, fields });
+            }
+            for decl in enums {
+                let mut variants: Vec<(Symbol, Vec<FastType>)> = Vec::new();
+                for variant in &decl.variants {
+                    if variants.iter().any(|(name, _)| *name == variant.name) {
+                        let message = format!(
+                            "variant `{}` is declared more than once",
+                            self.name_of(variant.name)
+                        );
+                        self.error(message, variant.span);
+                        continue;
+                    }
+                    let fields = variant
+                        .fields
+                        .iter()
+                        .map(|field| {
+                            let ty = field
+                                .ty
+                                .as_ref()
+                                .map(|ty| self.resolve_type(ty))
+                                .unwrap_or(FastType::Unknown);
+                            self.types.insert(field.id, ty.clone());
+                            ty
+                        })
+                        .collect();
+                    variants.push((variant.name, fields));
+                }
+                let name = SmolStr::from(self.name_of(decl.name));
+                self.types.insert(decl.id, FastType::Enum(name.clone()));
+                self.enums.insert(name, EnumInfo { variants });
+            }
+        }
+
+        fn declare_impls(&mut self, module: &'a HirModule) {
+            for item in &module.items {
+                let HirItem::Impl(block) = item else {
+                    continue;
+                };
+                let type_name = SmolStr::from(self.name_of(block.ty.name));
+                let outer = self
+                    .structs
+                    .get(&type_name)
+                    .map(|info| info.params.clone())
+                    .unwrap_or_default();
+                for method in &block.methods {
+                    self.functions.push(method.id);
+                    self.pending.insert(method.id, method);
+                    let params = self.declare_type_params(&method.type_params, &outer);
+                    self.type_params.insert(method.id, params);
+                }
+                let self_ty = self.impl_type(&block.ty);
+                if matches!(self_ty, FastType::Unknown) {
+                    self.error(
+                        format!("cannot define methods on unknown type `{type_name}`"),
+                        block.ty.span,
+                    );
+                    continue;
+                }
+                for method in &block.methods {
+                    let has_self = method
+                        .params
+                        .first()
+                        .is_some_and(|param| self.name_of(param.name) == "self");
+                    let method_ty = self.signature(method, Some(&self_ty));
+                    self.types.insert(method.id, method_ty);
+                    let info = MethodInfo {
+                        function: method.id,
+                        arity: method.params.len(),
+                        has_self,
+                    };
+                    let methods = self.methods.entry(type_name.clone()).or_default();
+                    if methods.insert(method.name, info).is_some() {
+                        let message = format!(
+                            "method `{}` is defined more than once for `{type_name}`",
+                            self.name_of(method.name)
+                        );
+                        self.error(message, method.span);
+                    }
+                }
+            }
+        }
+
+        fn impl_type(&self, ty: &HirNameRef) -> FastType {
+            let name = SmolStr::from(self.name_of(ty.name));
+            if let Some(info) = self.structs.get(&name) {
+                let args = info.params.iter().cloned().map(FastType::Param).collect();
+                FastType::Struct(name, args)
+            } else if self.enums.contains_key(&name) {
+                FastType::Enum(name)
+            } else {
+                FastType::Unknown
+            }
+        }
+
+        fn signature(&mut self, func: &HirFunction, self_ty: Option<&FastType>) -> FastType {
+            let scope = self.type_params.get(&func.id).cloned().unwrap_or_default();
+            let saved_scope = std::mem::replace(&mut self.type_scope, scope);
+            let params = func
+                .params
+                .iter()
+                .enumerate()
+                .map(|(idx, param)| match (&param.ty, self_ty) {
+                    (Some(ty), _) => self.resolve_type(ty),
+                    (None, Some(ty)) if idx == 0 && self.name_of(param.name) == "self" => {
+                        ty.clone()
+                    }
+                    (None, _) => self.fresh_var(),
+                })
+                .collect();
+            let return_ty = match &func.return_ty {
+                Some(ty) => self.resolve_type(ty),
+                None => self.fresh_var(),
+            };
+            self.type_scope = saved_scope;
+            FastType::Function {
+                params,
+                return_ty: Box::new(return_ty),
+            }
+        }
+
+        fn resolve_type(&mut self, ty: &HirType) -> FastType {
+            match ty {
+                HirType::Unit(_) => FastType::Unit,
+                HirType::Tuple(tuple) => FastType::Tuple(
+                    tuple
+                        .elements
+                        .iter()
+                        .map(|ty| self.resolve_type(ty))
+                        .collect(),
+                ),
+                HirType::Function(function) => FastType::Function {
+                    params: function
+                        .params
+                        .iter()
+                        .map(|ty| self.resolve_type(ty))
+                        .collect(),
+                    return_ty: Box::new(
+                        function
+                            .return_ty
+                            .as_ref()
+                            .map(|ty| self.resolve_type(ty))
+                            .unwrap_or(FastType::Unit),
+                    ),
+                },
+                HirType::Named(named) if self.name_of(named.name) == "List" => {
+                    if let [element] = named.args.as_slice() {
+                        FastType::List(Box::new(self.resolve_type(element)))
+                    } else {
+                        self.error(
+                            format!("`List` takes 1 type argument, found {}", named.args.len()),
+                            named.span,
+                        );
+                        FastType::Unknown
+                    }
+                }
+                HirType::Named(named) if self.name_of(named.name) == "Map" => {
+                    if let [key, value] = named.args.as_slice() {
+                        FastType::Map(
+                            Box::new(self.resolve_type(key)),
+                            Box::new(self.resolve_type(value)),
+                        )
+                    } else {
+                        self.error(
+                            format!("`Map` takes 2 type arguments, found {}", named.args.len()),
+                            named.span,
+                        );
+                        FastType::Unknown
+                    }
+                }
+                HirType::Named(named) => {
+                    let name = self.name_of(named.name);
+                    if let Some(info) = self.structs.get(name.as_str()) {
+                        let expected = info.params.len();
+                        if expected > 0 || !named.args.is_empty() {
+                            return self.resolve_generic_struct(named, expected);
+                        }
+                    }
+                    let ty = match name.as_str() {
+                        _ if self.type_scope.iter().any(|param| *param == name) => {
+                            FastType::Param(name.as_str().into())
+                        }
+                        "Int" => FastType::Int,
+                        "Float" => FastType::Float,
+                        "Bool" => FastType::Bool,
+                        "String" => FastType::String,
+                        _ if self.structs.contains_key(name.as_str()) => {
+                            FastType::Struct(name.as_str().into(), Vec::new())
+                        }
+                        _ if self.enums.contains_key(name.as_str()) => {
+                            FastType::Enum(name.as_str().into())
+                        }
+                        _ => {
+                            self.error(format!("unknown type `{name}`"), named.span);
+                            return FastType::Unknown;
+                        }
+                    };
+                    if !named.args.is_empty() {
+                        self.error(
+                            format!("type `{name}` does not take type arguments"),
+                            named.span,
+                        );
+                        return FastType::Unknown;
                     }
-                    HirItem::Function(func) => self.analyze_function(func),
+                    ty
                 }
             }
         }
 
+        fn resolve_generic_struct(&mut self, named: &HirNamedType, expected: usize) -> FastType {
+            let name = self.name_of(named.name);
+            if expected == 0 {
+                self.error(
+                    format!("type `{name}` does not take type arguments"),
+                    named.span,
+                );
+                return FastType::Unknown;
+            }
+            if named.args.len() != expected {
+                let noun = if expected == 1 {
+                    "argument"
+                } else {
+                    "arguments"
+                };
+                self.error(
+                    format!(
+                        "`{name}` takes {expected} type {noun}, found {}",
+                        named.args.len()
+                    ),
+                    named.span,
+                );
+                return FastType::Unknown;
+            }
+            let args = named
+                .args
+                .iter()
+                .map(|arg| self.resolve_type(arg))
+                .collect();
+            FastType::Struct(name.as_str().into(), args)
+        }
+
         fn analyze_function(&mut self, func: &HirFunction) {
+            let signature = match self.types.get(&func.id) {
+                Some(ty) => ty.clone(),
+                None => {
+                    let ty = self.signature(func, None);
+                    self.types.insert(func.id, ty.clone());
+                    ty
+                }
+            };
+            let FastType::Function { params, return_ty } = signature else {
+                return;
+            };
+            let scope = self.type_params.get(&func.id).cloned().unwrap_or_default();
+            let saved_scope = std::mem::replace(&mut self.type_scope, scope);
+            let saved_deferred = std::mem::take(&mut self.deferred);
             self.scopes.push(HashMap::new());
             self.current_function = Some(FunctionContext {
-                return_ty: FastType::Unknown,
+                return_ty: *return_ty,
+                annotated: func.return_ty.is_some(),
                 has_explicit_return: false,
             });
 
-            for param in &func.params {
-                self.types.insert(param.id, FastType::Unknown);
-                self.bind(param.name, FastType::Unknown);
+            for (param, ty) in func.params.iter().zip(&params) {
+                self.types.insert(param.id, ty.clone());
+                self.bind(param.name, ty.clone());
             }
 
             let body_ty = self.analyze_block(&func.body);
@@ -121,18 +954,118 @@ pub mod fast {
                 .current_function
                 .take()
                 .expect("current function context missing");
-            let final_return = if fn_ctx.has_explicit_return {
-                fn_ctx.return_ty
-            } else {
-                body_ty
-            };
-            if let Some(entry) = self.types.get_mut(&func.id) {
-                *entry = FastType::Function {
-                    arity: func.params.len(),
-                    return_ty: Box::new(final_return.clone()),
+            let span = func
+                .body
+                .tail
+                .as_ref()
+                .map(|tail| tail.span())
+                .unwrap_or(func.body.span);
+            self.check_body_return(&fn_ctx, &body_ty, span);
+            self.pop_scope();
+            self.type_scope = saved_scope;
+            self.check_deferred();
+            self.deferred = saved_deferred;
+            self.generalize(func.id);
+        }
+
+        fn check_body_return(&mut self, fn_ctx: &FunctionContext, body_ty: &FastType, span: Span) {
+            if fn_ctx.has_explicit_return && self.resolve(body_ty) == FastType::Unit {
+                return;
+            }
+            self.expect(
+                &fn_ctx.return_ty,
+                body_ty,
+                return_mismatch(fn_ctx.annotated),
+                span,
+            );
+        }
+
+        fn check_arguments(
+            &mut self,
+            params: &[FastType],
+            arg_spans: impl IntoIterator<Item = Span>,
+            arg_tys: &[FastType],
+        ) {
+            for ((param, span), arg_ty) in params.iter().zip(arg_spans).zip(arg_tys) {
+                self.expect(param, arg_ty, "mismatched argument type", span);
+            }
+        }
+
+        /// Checks the uses deferred while their base was unsolved. A base
+        /// that is still unsolved cannot be typed without an annotation.
+        fn check_deferred(&mut self) {
+            for deferred in std::mem::take(&mut self.deferred) {
+                let (base, base_span, ty) = match &deferred {
+                    Deferred::Field {
+                        base,
+                        base_span,
+                        ty,
+                        ..
+                    }
+                    | Deferred::Index {
+                        base,
+                        base_span,
+                        ty,
+                        ..
+                    } => (base, *base_span, ty),
+                    Deferred::Method {
+                        receiver,
+                        receiver_span,
+                        ty,
+                        ..
+                    } => (receiver, *receiver_span, ty),
                 };
+                if let FastType::Var(_) = self.shallow(base) {
+                    let diag = Diagnostic::error("cannot infer type", base_span)
+                        .with_note("add a type annotation");
+                    self.diagnostics.push(diag);
+                    self.settle(ty, FastType::Unknown, base_span);
+                    continue;
+                }
+                match &deferred {
+                    Deferred::Field {
+                        id,
+                        field,
+                        base,
+                        ty,
+                        ..
+                    } => {
+                        let found = self.field_type(*id, field, base);
+                        self.settle(ty, found, field.span);
+                    }
+                    Deferred::Index {
+                        span,
+                        index_span,
+                        base,
+                        index_ty,
+                        ty,
+                        ..
+                    } => {
+                        let found = self.index_type(*span, *index_span, base, index_ty);
+                        self.settle(ty, found, *span);
+                    }
+                    Deferred::Method {
+                        site,
+                        receiver,
+                        args,
+                        ty,
+                        ..
+                    } => {
+                        let found = self.method_type(site, receiver, args);
+                        self.settle(ty, found, site.span);
+                    }
+                }
+            }
+        }
+
+        /// Gives a deferred use the type found for it once its base is known.
+        fn settle(&mut self, ty: &FastType, found: FastType, span: Span) {
+            match self.shallow(ty) {
+                FastType::Var(var) => self.substitution[var as usize] = Some(found),
+                ty => {
+                    self.expect(&found, &ty, "mismatched types", span);
+                }
             }
-            self.pop_scope();
         }
 
         fn analyze_block(&mut self, block: &HirBlock) -> FastType {
@@ -152,22 +1085,8 @@ pub mod fast {
 
         fn analyze_stmt(&mut self, stmt: &HirStmt) {
             match stmt {
-                HirStmt::Let(binding) => {
-                    let ty = self.analyze_expr(&binding.value);
-                    self.types.insert(binding.id, ty.clone());
-                    self.bind(binding.name, ty);
-                }
-                HirStmt::While(while_stmt) => {
-                    let cond_ty = self.analyze_expr(&while_stmt.condition);
-                    if !matches!(cond_ty, FastType::Bool | FastType::Unknown) {
-                        self.error("while condition must be bool", while_stmt.span);
-                    }
-                    let body_ty = self.analyze_block(&while_stmt.body);
-                    if !matches!(body_ty, FastType::Unit | FastType::Unknown) {
-                        self.error("while body must produce unit", while_stmt.body.span);
-                    }
-                    self.types.insert(while_stmt.id, FastType::Unit);
-                }
+                HirStmt::Let(binding) => self.analyze_let(binding),
+                HirStmt::Assign(assign) => self.analyze_assign(assign),
                 HirStmt::Return(ret) => {
                     let ty = ret
                         .value
@@ -175,169 +1094,1439 @@ pub mod fast {
                         .map(|expr| self.analyze_expr(expr))
                         .unwrap_or(FastType::Unit);
                     self.types.insert(ret.id, ty.clone());
-                    let mut conflict = None;
-                    if let Some(fn_ctx) = self.current_function.as_mut() {
-                        let unified = fn_ctx.return_ty.unify(&ty);
-                        if matches!(unified, FastType::Unknown) && !matches!(ty, FastType::Unknown)
-                        {
-                            conflict = Some(ret.span);
-                        }
-                        if !matches!(ty, FastType::Unknown) {
-                            fn_ctx.return_ty = ty.clone();
-                        }
-                        fn_ctx.has_explicit_return = true;
-                    }
-                    if let Some(span) = conflict {
-                        self.error("conflicting return types", span);
+                    let Some(fn_ctx) = self.current_function.as_mut() else {
+                        return;
+                    };
+                    fn_ctx.has_explicit_return = true;
+                    let (expected, annotated) = (fn_ctx.return_ty.clone(), fn_ctx.annotated);
+                    let span = if annotated {
+                        ret.value.as_ref().map_or(ret.span, |value| value.span())
+                    } else {
+                        ret.span
+                    };
+                    self.expect(&expected, &ty, return_mismatch(annotated), span);
+                }
+                HirStmt::Break(brk) => self.analyze_break(brk),
+                HirStmt::Continue(cont) => {
+                    self.types.insert(cont.id, FastType::Unit);
+                    if self.loops.is_empty() {
+                        self.error("`continue` outside of a loop", cont.span);
                     }
                 }
                 HirStmt::Expr(expr) => {
                     let ty = self.analyze_expr(expr);
-                    if !matches!(ty, FastType::Unit | FastType::Unknown) {
-                        self.error("expression statement must evaluate to unit", expr.span());
+                    let ty = self.resolve(&ty);
+                    if !matches!(ty, FastType::Unit | FastType::Unknown | FastType::Var(_)) {
+                        let diag = Diagnostic::error(
+                            "expression statement must evaluate to unit",
+                            expr.span(),
+                        )
+                        .with_note(format!("found `{ty}`"));
+                        self.diagnostics.push(diag);
                     }
                 }
             }
         }
 
-        fn analyze_expr(&mut self, expr: &HirExpr) -> FastType {
-            match expr {
-                HirExpr::Literal(lit) => match lit {
-                    HirLiteral::Int(int) => {
-                        self.types.insert(int.id, FastType::Int);
-                        FastType::Int
+        fn analyze_let(&mut self, binding: &HirLetBinding) {
+            let mut ty = self.analyze_expr(&binding.value);
+            if let Some(annotation) = &binding.ty {
+                let declared = self.resolve_type(annotation);
+                self.expect(
+                    &declared,
+                    &ty,
+                    "mismatched types in `let`",
+                    binding.value.span(),
+                );
+                ty = declared;
+            }
+            self.types.insert(binding.id, ty.clone());
+            if !is_irrefutable(&binding.pattern) {
+                self.error("refutable pattern in `let` binding", binding.pattern.span());
+            }
+            self.check_pattern(&binding.pattern, &ty);
+            if binding.mutable {
+                let mut names = Vec::new();
+                collect_bindings(&binding.pattern, &mut names);
+                for name in names {
+                    if let Some(bound) = self.scopes.last_mut().and_then(|s| s.get_mut(&name)) {
+                        bound.mutable = true;
                     }
-                    HirLiteral::String(string) => {
-                        self.types.insert(string.id, FastType::String);
-                        FastType::String
+                }
+            }
+        }
+
+        fn analyze_assign(&mut self, assign: &HirAssign) {
+            let value_ty = self.analyze_expr(&assign.value);
+            self.types.insert(assign.id, FastType::Unit);
+            let (target_ty, target_span) = match &assign.target {
+                HirAssignTarget::Name(target) => {
+                    let Some((depth, binding)) = self.lookup_depth(target.name) else {
+                        let name = self.name_of(target.name);
+                        self.unresolved_name(&name, target.span);
+                        return;
+                    };
+                    self.types.insert(target.id, binding.ty.clone());
+                    if self.is_captured(depth) {
+                        let name = self.name_of(target.name);
+                        let diag = Diagnostic::error(
+                            format!("cannot assign to captured variable `{name}`"),
+                            assign.span,
+                        )
+                        .with_note("closures capture variables by value");
+                        self.diagnostics.push(diag);
+                    } else if binding.captured {
+                        let diag = self.assigned_after_capture(target.name, assign.span);
+                        self.diagnostics.push(diag);
+                    } else if binding.mutable {
+                        for loop_ctx in &mut self.loops {
+                            if depth < loop_ctx.scope_depth {
+                                loop_ctx.assigned.push((target.name, depth, assign.span));
+                            }
+                        }
                     }
-                    HirLiteral::Bool(boolean) => {
-                        self.types.insert(boolean.id, FastType::Bool);
-                        FastType::Bool
+                    if !binding.mutable {
+                        let name = self.name_of(target.name);
+                        let diag = Diagnostic::error(
+                            format!("cannot assign to immutable binding `{name}`"),
+                            assign.span,
+                        )
+                        .with_note(format!("consider declaring it with `let mut {name}`"));
+                        self.diagnostics.push(diag);
                     }
-                    HirLiteral::Unit(unit) => {
-                        self.types.insert(unit.id, FastType::Unit);
-                        FastType::Unit
+                    (binding.ty, target.span)
+                }
+                HirAssignTarget::Field(access) => (self.analyze_field(access), access.span),
+                HirAssignTarget::Index(index) => (self.analyze_index(index), index.span),
+            };
+            if let Some(op) = assign.op {
+                let concat = op == HirBinaryOp::Add;
+                let allowed = |ty: &FastType| {
+                    matches!(
+                        ty,
+                        FastType::Int | FastType::Float | FastType::Var(_) | FastType::Unknown
+                    ) || (concat && *ty == FastType::String)
+                };
+                let operands = [
+                    (self.resolve(&target_ty), target_span),
+                    (self.resolve(&value_ty), assign.value.span()),
+                ];
+                for (ty, span) in &operands {
+                    if !allowed(ty) {
+                        let message = if concat {
+                            "`+=` requires numeric or String operands"
+                        } else {
+                            "compound assignment requires numeric operands"
+                        };
+                        let diag =
+                            Diagnostic::error(message, *span).with_note(format!("found `{ty}`"));
+                        self.diagnostics.push(diag);
                     }
-                },
+                }
+                if !operands.iter().all(|(ty, _)| allowed(ty)) {
+                    return;
+                }
+            }
+            self.expect(
+                &target_ty,
+                &value_ty,
+                "mismatched types in assignment",
+                assign.value.span(),
+            );
+        }
+
+        fn analyze_expr(&mut self, expr: &HirExpr) -> FastType {
+            match expr {
+                HirExpr::Literal(lit) => self.analyze_literal(lit),
                 HirExpr::Name(name) => {
-                    if let Some(binding) = self.lookup(name.name) {
-                        self.types.insert(name.id, binding.ty.clone());
-                        binding.ty
+                    if let Some((depth, binding)) = self.lookup_depth(name.name) {
+                        self.note_capture(name.name, depth);
+                        let ty = match binding.function {
+                            Some(function) => self.instantiate(function),
+                            None => binding.ty,
+                        };
+                        self.types.insert(name.id, ty.clone());
+                        ty
                     } else {
-                        FastType::Unknown
+                        let text = self.name_of(name.name);
+                        if !self.host_functions.contains(text.as_str()) {
+                            self.unresolved_name(&text, name.span);
+                            return FastType::Unknown;
+                        }
+                        let Some(builtin) = self.builtins.get(text.as_str()).copied() else {
+                            return FastType::Unknown;
+                        };
+                        let mapping = builtin
+                            .type_params
+                            .iter()
+                            .map(|param| (SmolStr::from(*param), self.fresh_var()))
+                            .collect();
+                        let ty = builtin.ty.replace_params(&mapping);
+                        self.types.insert(name.id, ty.clone());
+                        ty
                     }
                 }
                 HirExpr::Call(call) => {
-                    let callee_ty = self.analyze_expr(&call.callee);
-                    for arg in &call.args {
-                        self.analyze_expr(arg);
+                    if let HirExpr::Path(path) = &*call.callee {
+                        if let Some((type_name, info)) = self.associated_function(path) {
+                            return self.analyze_associated_call(call, path, type_name, info);
+                        }
+                        return self.analyze_variant_call(call, path);
                     }
-                    match callee_ty {
+                    let callee_ty = self.analyze_expr(&call.callee);
+                    let arg_tys: Vec<FastType> =
+                        call.args.iter().map(|arg| self.analyze_expr(arg)).collect();
+                    match self.shallow(&callee_ty) {
                         FastType::Function {
-                            arity,
+                            ref params,
                             ref return_ty,
                         } => {
-                            if arity != call.args.len() {
+                            if params.len() != call.args.len() {
                                 self.error(
                                     format!(
-                                        "expected {arity} arguments, found {}",
+                                        "expected {} arguments, found {}",
+                                        params.len(),
                                         call.args.len()
                                     ),
                                     call.span,
                                 );
+                            } else {
+                                self.check_arguments(
+                                    params,
+                                    call.args.iter().map(HirExpr::span),
+                                    &arg_tys,
+                                );
                             }
                             let ret = (*return_ty.clone()).clone();
                             self.types.insert(call.id, ret.clone());
                             ret
                         }
-                        FastType::Unknown => FastType::Unknown,
-                        _ => {
-                            self.error("cannot call non-function", call.span);
+                        FastType::Var(_) => {
+                            let ret = self.fresh_var();
+                            let expected = FastType::Function {
+                                params: arg_tys,
+                                return_ty: Box::new(ret.clone()),
+                            };
+                            self.expect(
+                                &callee_ty,
+                                &expected,
+                                "mismatched function type",
+                                call.span,
+                            );
+                            self.types.insert(call.id, ret.clone());
+                            ret
+                        }
+                        FastType::Unknown => {
+                            self.types.insert(call.id, FastType::Unknown);
+                            FastType::Unknown
+                        }
+                        other => {
+                            let diag = Diagnostic::error("cannot call non-function", call.span)
+                                .with_note(format!("found `{}`", self.resolve(&other)));
+                            self.diagnostics.push(diag);
                             FastType::Unknown
                         }
                     }
                 }
+                HirExpr::MethodCall(call) => self.analyze_method_call(call),
+                HirExpr::Lambda(lambda) => self.analyze_lambda(lambda),
                 HirExpr::If(if_expr) => {
                     let cond_ty = self.analyze_expr(&if_expr.condition);
-                    if !matches!(cond_ty, FastType::Bool | FastType::Unknown) {
-                        self.error("if condition must be bool", if_expr.condition.span());
-                    }
+                    self.expect(
+                        &FastType::Bool,
+                        &cond_ty,
+                        "if condition must be bool",
+                        if_expr.condition.span(),
+                    );
                     let then_ty = self.analyze_block(&if_expr.then_branch);
                     let else_ty = if let Some(else_branch) = &if_expr.else_branch {
                         self.analyze_block(else_branch)
                     } else {
                         FastType::Unit
                     };
-                    let unified = then_ty.unify(&else_ty);
-                    if matches!(unified, FastType::Unknown)
-                        && !matches!(then_ty, FastType::Unknown | FastType::Unit)
-                        && !matches!(else_ty, FastType::Unknown | FastType::Unit)
-                    {
-                        self.error("mismatched branch types", if_expr.span);
-                    }
+                    let unified = self.join_branches(&then_ty, &else_ty, if_expr.span);
                     self.types.insert(if_expr.id, unified.clone());
                     unified
                 }
-                HirExpr::Block(block) => {
-                    let ty = self.analyze_block(block);
-                    self.types.insert(block.id, ty.clone());
+                HirExpr::Match(match_expr) => self.analyze_match(match_expr),
+                HirExpr::While(while_expr) => self.analyze_while(while_expr),
+                HirExpr::For(for_expr) => self.analyze_for(for_expr),
+                HirExpr::Range(range) => {
+                    self.analyze_range(range);
+                    self.error(
+                        "range expressions are only supported as `for` loop iterables",
+                        range.span,
+                    );
+                    FastType::Unknown
+                }
+                HirExpr::StructLit(lit) => self.analyze_struct_literal(lit),
+                HirExpr::Field(access) => self.analyze_field(access),
+                HirExpr::List(list) => {
+                    let elem_ty = self.fresh_var();
+                    for element in &list.elements {
+                        let ty = self.analyze_expr(element);
+                        self.expect(
+                            &elem_ty,
+                            &ty,
+                            "mismatched list element types",
+                            element.span(),
+                        );
+                    }
+                    let ty = FastType::List(Box::new(elem_ty));
+                    self.types.insert(list.id, ty.clone());
                     ty
                 }
-                HirExpr::Binary(bin) => {
-                    let lhs = self.analyze_expr(&bin.lhs);
-                    let rhs = self.analyze_expr(&bin.rhs);
-                    let (required, result) = match bin.op {
-                        HirBinaryOp::Add
-                        | HirBinaryOp::Sub
-                        | HirBinaryOp::Mul
-                        | HirBinaryOp::Div => (FastType::Int, FastType::Int),
-                        HirBinaryOp::Eq
-                        | HirBinaryOp::Ne
-                        | HirBinaryOp::Lt
-                        | HirBinaryOp::Le
-                        | HirBinaryOp::Gt
-                        | HirBinaryOp::Ge => (FastType::Int, FastType::Bool),
+                HirExpr::Tuple(tuple) => {
+                    let ty = FastType::Tuple(
+                        tuple
+                            .elements
+                            .iter()
+                            .map(|element| self.analyze_expr(element))
+                            .collect(),
+                    );
+                    self.types.insert(tuple.id, ty.clone());
+                    ty
+                }
+                HirExpr::Index(index) => self.analyze_index(index),
+                HirExpr::Path(path) => {
+                    let Some((enum_name, fields)) = self.resolve_variant(path) else {
+                        return FastType::Unknown;
                     };
-                    if !matches!(lhs, FastType::Unknown) && lhs != required {
-                        self.error("left operand has wrong type", bin.lhs.span());
+                    if !fields.is_empty() {
+                        let diag = Diagnostic::error(
+                            format!(
+                                "variant `{}` has {} field(s) and must be called",
+                                self.path_name(path),
+                                fields.len()
+                            ),
+                            path.span,
+                        )
+                        .with_note(format!("use `{}(...)`", self.path_name(path)));
+                        self.diagnostics.push(diag);
                     }
-                    if !matches!(rhs, FastType::Unknown) && rhs != required {
-                        self.error("right operand has wrong type", bin.rhs.span());
+                    let ty = FastType::Enum(enum_name);
+                    self.types.insert(path.id, ty.clone());
+                    ty
+                }
+                HirExpr::Block(block) => {
+                    let ty = self.analyze_block(block);
+                    self.types.insert(block.id, ty.clone());
+                    ty
+                }
+                HirExpr::Binary(bin) => {
+                    let lhs = self.analyze_expr(&bin.lhs);
+                    let rhs = self.analyze_expr(&bin.rhs);
+                    if matches!(bin.op, HirBinaryOp::Eq | HirBinaryOp::Ne) {
+                        if !self.unify(&lhs, &rhs) {
+                            let diag = Diagnostic::error(
+                                "cannot compare values of different types",
+                                bin.span,
+                            )
+                            .with_note(format!(
+                                "left is `{}`, right is `{}`",
+                                self.resolve(&lhs),
+                                self.resolve(&rhs)
+                            ));
+                            self.diagnostics.push(diag);
+                        }
+                        self.types.insert(bin.id, FastType::Bool);
+                        return FastType::Bool;
                     }
+                    if matches!(
+                        bin.op,
+                        HirBinaryOp::Add
+                            | HirBinaryOp::Sub
+                            | HirBinaryOp::Mul
+                            | HirBinaryOp::Div
+                            | HirBinaryOp::Mod
+                            | HirBinaryOp::Lt
+                            | HirBinaryOp::Le
+                            | HirBinaryOp::Gt
+                            | HirBinaryOp::Ge
+                    ) {
+                        if bin.op == HirBinaryOp::Add
+                            && (self.shallow(&lhs) == FastType::String
+                                || self.shallow(&rhs) == FastType::String)
+                        {
+                            if !self.unify(&lhs, &rhs) {
+                                let diag = Diagnostic::error(
+                                    format!(
+                                        "cannot add `{}` and `{}`",
+                                        self.resolve(&lhs),
+                                        self.resolve(&rhs)
+                                    ),
+                                    bin.span,
+                                )
+                                .with_note("convert the value with `str(...)` or use an f-string");
+                                self.diagnostics.push(diag);
+                            }
+                            self.types.insert(bin.id, FastType::String);
+                            return FastType::String;
+                        }
+                        let operand = self.check_numeric_operands(bin, &lhs, &rhs);
+                        let result = match bin.op {
+                            HirBinaryOp::Lt
+                            | HirBinaryOp::Le
+                            | HirBinaryOp::Gt
+                            | HirBinaryOp::Ge => FastType::Bool,
+                            _ => operand,
+                        };
+                        self.types.insert(bin.id, result.clone());
+                        return result;
+                    }
+                    let (required, result) = match bin.op {
+                        HirBinaryOp::BitAnd
+                        | HirBinaryOp::BitOr
+                        | HirBinaryOp::BitXor
+                        | HirBinaryOp::Shl
+                        | HirBinaryOp::Shr => (FastType::Int, FastType::Int),
+                        HirBinaryOp::And | HirBinaryOp::Or => (FastType::Bool, FastType::Bool),
+                        _ => unreachable!("handled above"),
+                    };
+                    self.expect(
+                        &required,
+                        &lhs,
+                        "left operand has wrong type",
+                        bin.lhs.span(),
+                    );
+                    self.expect(
+                        &required,
+                        &rhs,
+                        "right operand has wrong type",
+                        bin.rhs.span(),
+                    );
                     self.types.insert(bin.id, result.clone());
                     result
                 }
                 HirExpr::Unary(un) => {
                     let operand_ty = self.analyze_expr(&un.expr);
+                    if un.op == HirUnaryOp::Neg {
+                        let operand_ty = self.shallow(&operand_ty);
+                        let result = match operand_ty {
+                            FastType::Int | FastType::Float | FastType::Var(_) => operand_ty,
+                            FastType::Unknown => FastType::Unknown,
+                            other => {
+                                let diag = Diagnostic::error(
+                                    "unary operand has wrong type",
+                                    un.expr.span(),
+                                )
+                                .with_note(format!(
+                                    "expected `Int` or `Float`, found `{}`",
+                                    self.resolve(&other)
+                                ));
+                                self.diagnostics.push(diag);
+                                FastType::Unknown
+                            }
+                        };
+                        self.types.insert(un.id, result.clone());
+                        return result;
+                    }
                     let (required, result) = match un.op {
-                        HirUnaryOp::Neg => (FastType::Int, FastType::Int),
+                        HirUnaryOp::Neg | HirUnaryOp::BitNot => (FastType::Int, FastType::Int),
                         HirUnaryOp::Not => (FastType::Bool, FastType::Bool),
                     };
-                    if !matches!(operand_ty, FastType::Unknown) && operand_ty != required {
-                        self.error("unary operand has wrong type", un.expr.span());
-                    }
+                    self.expect(
+                        &required,
+                        &operand_ty,
+                        "unary operand has wrong type",
+                        un.expr.span(),
+                    );
                     self.types.insert(un.id, result.clone());
                     result
                 }
             }
         }
 
-        fn lookup(&self, name: Symbol) -> Option<Binding> {
-            for scope in self.scopes.iter().rev() {
+        fn check_numeric_operands(
+            &mut self,
+            bin: &HirBinary,
+            lhs: &FastType,
+            rhs: &FastType,
+        ) -> FastType {
+            let operands = [
+                (self.resolve(lhs), "left", bin.lhs.span()),
+                (self.resolve(rhs), "right", bin.rhs.span()),
+            ];
+            let mut ok = true;
+            for (ty, side, span) in &operands {
+                if !matches!(
+                    ty,
+                    FastType::Int | FastType::Float | FastType::Var(_) | FastType::Unknown
+                ) {
+                    let diag = Diagnostic::error(format!("{side} operand has wrong type"), *span)
+                        .with_note(format!("expected `Int` or `Float`, found `{ty}`"));
+                    self.diagnostics.push(diag);
+                    ok = false;
+                }
+            }
+            if !ok {
+                return FastType::Unknown;
+            }
+            if !self.unify(lhs, rhs) {
+                let diag = Diagnostic::error("mismatched numeric operands", bin.span)
+                    .with_note(format!(
+                        "left is `{}`, right is `{}`",
+                        operands[0].0, operands[1].0
+                    ))
+                    .with_note("convert one side with `float(...)` or `int(...)`");
+                self.diagnostics.push(diag);
+                return FastType::Unknown;
+            }
+            match self.shallow(lhs) {
+                FastType::Unknown => self.shallow(rhs),
+                ty => ty,
+            }
+        }
+
+        fn analyze_literal(&mut self, lit: &HirLiteral) -> FastType {
+            match lit {
+                HirLiteral::Int(int) => {
+                    self.types.insert(int.id, FastType::Int);
+                    FastType::Int
+                }
+                HirLiteral::Float(float) => {
+                    self.types.insert(float.id, FastType::Float);
+                    FastType::Float
+                }
+                HirLiteral::String(string) => {
+                    self.types.insert(string.id, FastType::String);
+                    FastType::String
+                }
+                HirLiteral::Bool(boolean) => {
+                    self.types.insert(boolean.id, FastType::Bool);
+                    FastType::Bool
+                }
+                HirLiteral::Unit(unit) => {
+                    self.types.insert(unit.id, FastType::Unit);
+                    FastType::Unit
+                }
+            }
+        }
+
+        fn analyze_struct_literal(&mut self, lit: &HirStructLiteral) -> FastType {
+            let name = SmolStr::from(self.name_of(lit.name.name));
+            let Some(info) = self.structs.get(&name).cloned() else {
+                for init in &lit.fields {
+                    self.analyze_expr(&init.value);
+                }
+                self.error(format!("unknown struct `{name}`"), lit.name.span);
+                return FastType::Unknown;
+            };
+            let mapping = self.fresh_params(&info.params);
+            let mut seen = Vec::new();
+            for init in &lit.fields {
+                let value_ty = self.analyze_expr(&init.value);
+                let field_name = self.name_of(init.name.name);
+                if seen.contains(&init.name.name) {
+                    self.error(
+                        format!("field `{field_name}` is specified more than once"),
+                        init.name.span,
+                    );
+                    continue;
+                }
+                seen.push(init.name.name);
+                match info
+                    .fields
+                    .iter()
+                    .find(|(field, _)| *field == init.name.name)
+                {
+                    Some((_, expected)) => {
+                        let expected = expected.replace_params(&mapping);
+                        if !self.unify(&expected, &value_ty) {
+                            let expected = self.resolve(&expected);
+                            let value_ty = self.resolve(&value_ty);
+                            let diag =
+                                Diagnostic::error("mismatched field type", init.value.span())
+                                    .with_note(format!(
+                                    "field `{field_name}` expects `{expected}`, found `{value_ty}`"
+                                ));
+                            self.diagnostics.push(diag);
+                        }
+                    }
+                    None => self.error(
+                        format!("struct `{name}` has no field named `{field_name}`"),
+                        init.name.span,
+                    ),
+                }
+            }
+            let missing: Vec<String> = info
+                .fields
+                .iter()
+                .filter(|(field, _)| !seen.contains(field))
+                .map(|(field, _)| format!("`{}`", self.name_of(*field)))
+                .collect();
+            if !missing.is_empty() {
+                let noun = if missing.len() == 1 {
+                    "field"
+                } else {
+                    "fields"
+                };
+                self.error(
+                    format!("missing {noun} {} in `{name}` literal", missing.join(", ")),
+                    lit.span,
+                );
+            }
+            let args = info.params.iter().map(|param| mapping[param].clone());
+            let ty = FastType::Struct(name, args.collect());
+            self.types.insert(lit.id, ty.clone());
+            ty
+        }
+
+        fn analyze_field(&mut self, access: &HirFieldAccess) -> FastType {
+            let base_ty = self.analyze_expr(&access.base);
+            let ty = if let FastType::Var(_) = self.shallow(&base_ty) {
+                let ty = self.fresh_var();
+                self.deferred.push(Deferred::Field {
+                    id: access.id,
+                    field: access.field.clone(),
+                    base: base_ty,
+                    base_span: access.base.span(),
+                    ty: ty.clone(),
+                });
+                ty
+            } else {
+                self.field_type(access.id, &access.field, &base_ty)
+            };
+            self.types.insert(access.id, ty.clone());
+            ty
+        }
+
+        fn field_type(&mut self, id: HirId, field: &HirNameRef, base_ty: &FastType) -> FastType {
+            match &self.resolve(base_ty) {
+                FastType::Struct(name, args) => {
+                    let index = self.structs.get(name).and_then(|info| {
+                        let idx = info
+                            .fields
+                            .iter()
+                            .position(|(name, _)| *name == field.name)?;
+                        let mapping: HashMap<SmolStr, FastType> =
+                            info.params.iter().cloned().zip(args.clone()).collect();
+                        Some((idx, info.fields[idx].1.replace_params(&mapping)))
+                    });
+                    match index {
+                        Some((idx, field_ty)) => {
+                            self.field_indices.insert(id, idx);
+                            field_ty
+                        }
+                        None => {
+                            let field_name = self.name_of(field.name);
+                            self.error(
+                                format!("no field `{field_name}` on type `{name}`"),
+                                field.span,
+                            );
+                            FastType::Unknown
+                        }
+                    }
+                }
+                FastType::Unknown => FastType::Unknown,
+                other => {
+                    self.error(format!("type `{other}` has no fields"), field.span);
+                    FastType::Unknown
+                }
+            }
+        }
+
+        fn analyze_index(&mut self, index: &HirIndex) -> FastType {
+            let base_ty = self.analyze_expr(&index.base);
+            let index_ty = self.analyze_expr(&index.index);
+            let ty = if let FastType::Var(_) = self.shallow(&base_ty) {
+                let ty = self.fresh_var();
+                self.deferred.push(Deferred::Index {
+                    span: index.span,
+                    index_span: index.index.span(),
+                    base: base_ty,
+                    base_span: index.base.span(),
+                    index_ty,
+                    ty: ty.clone(),
+                });
+                ty
+            } else {
+                self.index_type(index.span, index.index.span(), &base_ty, &index_ty)
+            };
+            self.types.insert(index.id, ty.clone());
+            ty
+        }
+
+        fn index_type(
+            &mut self,
+            span: Span,
+            index_span: Span,
+            base_ty: &FastType,
+            index_ty: &FastType,
+        ) -> FastType {
+            match self.shallow(base_ty) {
+                FastType::List(elem) => {
+                    self.expect(
+                        &FastType::Int,
+                        index_ty,
+                        "list index must be Int",
+                        index_span,
+                    );
+                    *elem
+                }
+                FastType::Map(key, value) => {
+                    self.expect(&key, index_ty, "mismatched map key type", index_span);
+                    *value
+                }
+                FastType::Unknown => FastType::Unknown,
+                other => {
+                    self.error(format!("type `{other}` cannot be indexed"), span);
+                    FastType::Unknown
+                }
+            }
+        }
+
+        fn analyze_lambda(&mut self, lambda: &HirLambda) -> FastType {
+            let saved_loops = std::mem::take(&mut self.loops);
+            let return_ty = self.fresh_var();
+            let saved_function = self.current_function.replace(FunctionContext {
+                return_ty: return_ty.clone(),
+                annotated: false,
+                has_explicit_return: false,
+            });
+            self.push_scope();
+            self.lambdas.push(LambdaContext {
+                scope_depth: self.scopes.len() - 1,
+                captures: Vec::new(),
+            });
+            let mut params = Vec::with_capacity(lambda.params.len());
+            for param in &lambda.params {
+                let ty = match &param.ty {
+                    Some(ty) => self.resolve_type(ty),
+                    None => self.fresh_var(),
+                };
+                self.types.insert(param.id, ty.clone());
+                self.bind(param.name, ty.clone());
+                params.push(ty);
+            }
+            let body_ty = self.analyze_expr(&lambda.body);
+            let lambda_ctx = self.lambdas.pop().expect("lambda context missing");
+            self.pop_scope();
+            let fn_ctx = std::mem::replace(&mut self.current_function, saved_function)
+                .expect("lambda function context missing");
+            self.loops = saved_loops;
+            self.check_body_return(&fn_ctx, &body_ty, lambda.body.span());
+            for name in &lambda_ctx.captures {
+                self.note_mutable_capture(*name);
+            }
+            self.captures.insert(lambda.id, lambda_ctx.captures);
+            let ty = FastType::Function {
+                params,
+                return_ty: Box::new(return_ty),
+            };
+            self.types.insert(lambda.id, ty.clone());
+            ty
+        }
+
+        fn analyze_method_call(&mut self, call: &HirMethodCall) -> FastType {
+            let receiver_ty = self.analyze_expr(&call.receiver);
+            let arg_tys: Vec<FastType> =
+                call.args.iter().map(|arg| self.analyze_expr(arg)).collect();
+            let site = MethodSite {
+                id: call.id,
+                span: call.span,
+                method: call.method.clone(),
+                arg_spans: call.args.iter().map(HirExpr::span).collect(),
+            };
+            if let FastType::Var(_) = self.shallow(&receiver_ty) {
+                let ty = self.fresh_var();
+                self.types.insert(call.id, ty.clone());
+                self.deferred.push(Deferred::Method {
+                    site,
+                    receiver: receiver_ty,
+                    receiver_span: call.receiver.span(),
+                    args: arg_tys,
+                    ty: ty.clone(),
+                });
+                return ty;
+            }
+            self.method_type(&site, &receiver_ty, &arg_tys)
+        }
+
+        fn method_type(
+            &mut self,
+            call: &MethodSite,
+            receiver_ty: &FastType,
+            arg_tys: &[FastType],
+        ) -> FastType {
+            let type_name = match self.resolve(receiver_ty) {
+                FastType::Struct(name, _) | FastType::Enum(name) => name,
+                FastType::Unknown => {
+                    self.types.insert(call.id, FastType::Unknown);
+                    return FastType::Unknown;
+                }
+                other => {
+                    self.error(format!("type `{other}` has no methods"), call.method.span);
+                    return FastType::Unknown;
+                }
+            };
+            let method_name = self.name_of(call.method.name);
+            let Some(info) = self
+                .methods
+                .get(&type_name)
+                .and_then(|methods| methods.get(&call.method.name))
+                .cloned()
+            else {
+                self.error(
+                    format!("no method `{method_name}` on type `{type_name}`"),
+                    call.method.span,
+                );
+                return FastType::Unknown;
+            };
+            if !info.has_self {
+                let diag = Diagnostic::error(
+                    format!("`{type_name}::{method_name}` is an associated function, not a method"),
+                    call.method.span,
+                )
+                .with_note(format!("call it as `{type_name}::{method_name}(...)`"));
+                self.diagnostics.push(diag);
+                return FastType::Unknown;
+            }
+            let (params, ty) = self.instantiate_method(info.function);
+            if info.arity - 1 != call.arg_spans.len() {
+                self.error(
+                    format!(
+                        "expected {} arguments, found {}",
+                        info.arity - 1,
+                        call.arg_spans.len()
+                    ),
+                    call.span,
+                );
+            } else if let Some((self_param, params)) = params.split_first() {
+                self.unify(self_param, receiver_ty);
+                self.check_arguments(params, call.arg_spans.iter().copied(), arg_tys);
+            }
+            self.method_targets
+                .insert(call.id, format!("{type_name}::{method_name}"));
+            self.types.insert(call.id, ty.clone());
+            ty
+        }
+
+        fn associated_function(&self, path: &HirPath) -> Option<(SmolStr, MethodInfo)> {
+            let [type_ref, fn_ref] = path.segments.as_slice() else {
+                return None;
+            };
+            let type_name = SmolStr::from(self.name_of(type_ref.name));
+            let is_variant = self.enums.get(&type_name).is_some_and(|info| {
+                info.variants
+                    .iter()
+                    .any(|(variant, _)| *variant == fn_ref.name)
+            });
+            if is_variant {
+                return None;
+            }
+            let info = self.methods.get(&type_name)?.get(&fn_ref.name)?.clone();
+            Some((type_name, info))
+        }
+
+        fn analyze_associated_call(
+            &mut self,
+            call: &HirCall,
+            path: &HirPath,
+            type_name: SmolStr,
+            info: MethodInfo,
+        ) -> FastType {
+            let arg_tys: Vec<FastType> =
+                call.args.iter().map(|arg| self.analyze_expr(arg)).collect();
+            let (params, ty) = self.instantiate_method(info.function);
+            if info.arity != call.args.len() {
+                self.error(
+                    format!(
+                        "expected {} arguments, found {}",
+                        info.arity,
+                        call.args.len()
+                    ),
+                    call.span,
+                );
+            } else {
+                self.check_arguments(&params, call.args.iter().map(HirExpr::span), &arg_tys);
+            }
+            let method_name = self.name_of(path.segments[1].name);
+            self.method_targets
+                .insert(call.id, format!("{type_name}::{method_name}"));
+            self.types.insert(call.id, ty.clone());
+            ty
+        }
+
+        fn instantiate_method(&mut self, function: HirId) -> (Vec<FastType>, FastType) {
+            match self.instantiate(function) {
+                FastType::Function { params, return_ty } => (params, *return_ty),
+                _ => (Vec::new(), FastType::Unknown),
+            }
+        }
+
+        fn analyze_variant_call(&mut self, call: &HirCall, path: &HirPath) -> FastType {
+            let arg_tys: Vec<FastType> =
+                call.args.iter().map(|arg| self.analyze_expr(arg)).collect();
+            let Some((enum_name, fields)) = self.resolve_variant(path) else {
+                return FastType::Unknown;
+            };
+            if fields.len() != call.args.len() {
+                self.error(
+                    format!(
+                        "variant `{}` expects {} field(s), found {}",
+                        self.path_name(path),
+                        fields.len(),
+                        call.args.len()
+                    ),
+                    call.span,
+                );
+            }
+            for ((expected, found), arg) in fields.iter().zip(&arg_tys).zip(&call.args) {
+                self.expect(expected, found, "mismatched variant field type", arg.span());
+            }
+            let ty = FastType::Enum(enum_name);
+            self.types.insert(path.id, ty.clone());
+            self.types.insert(call.id, ty.clone());
+            ty
+        }
+
+        fn resolve_variant(&mut self, path: &HirPath) -> Option<(SmolStr, Vec<FastType>)> {
+            let [enum_ref, variant_ref] = path.segments.as_slice() else {
+                self.error(
+                    format!(
+                        "expected a path of the form `Enum::Variant`, found `{}`",
+                        self.path_name(path)
+                    ),
+                    path.span,
+                );
+                return None;
+            };
+            let enum_name = SmolStr::from(self.name_of(enum_ref.name));
+            let Some(info) = self.enums.get(&enum_name) else {
+                if self.structs.contains_key(&enum_name) {
+                    let fn_name = self.name_of(variant_ref.name);
+                    self.error(
+                        format!("no associated function `{fn_name}` on type `{enum_name}`"),
+                        variant_ref.span,
+                    );
+                } else {
+                    self.error(format!("unknown enum `{enum_name}`"), enum_ref.span);
+                }
+                return None;
+            };
+            let Some((_, fields)) = info
+                .variants
+                .iter()
+                .find(|(name, _)| *name == variant_ref.name)
+            else {
+                let variant_name = self.name_of(variant_ref.name);
+                self.error(
+                    format!("enum `{enum_name}` has no variant `{variant_name}`"),
+                    variant_ref.span,
+                );
+                return None;
+            };
+            Some((enum_name.clone(), fields.clone()))
+        }
+
+        fn analyze_while(&mut self, while_expr: &HirWhile) -> FastType {
+            let cond_ty = self.analyze_expr(&while_expr.condition);
+            self.expect(
+                &FastType::Bool,
+                &cond_ty,
+                "while condition must be bool",
+                while_expr.condition.span(),
+            );
+            let allows_value = matches!(
+                *while_expr.condition,
+                HirExpr::Literal(HirLiteral::Bool(HirBoolLiteral { value: true, .. }))
+            );
+            let loop_ctx = self.analyze_loop_body(&while_expr.body, allows_value, "while");
+            let ty = loop_ctx.break_ty.unwrap_or(FastType::Unit);
+            self.types.insert(while_expr.id, ty.clone());
+            ty
+        }
+
+        fn analyze_for(&mut self, for_expr: &HirFor) -> FastType {
+            let elem_ty = match &*for_expr.iterable {
+                HirExpr::Range(range) => {
+                    self.analyze_range(range);
+                    self.types.insert(range.id, FastType::Unknown);
+                    FastType::Int
+                }
+                other => {
+                    let ty = self.analyze_expr(other);
+                    match self.shallow(&ty) {
+                        FastType::List(elem) => *elem,
+                        FastType::Var(_) => {
+                            let elem = self.fresh_var();
+                            self.unify(&FastType::List(Box::new(elem.clone())), &ty);
+                            elem
+                        }
+                        FastType::Unknown => FastType::Unknown,
+                        ty => {
+                            let diag = Diagnostic::error(
+                                "`for` loops can only iterate over ranges and lists",
+                                other.span(),
+                            )
+                            .with_note(format!("found a value of type `{}`", self.resolve(&ty)));
+                            self.diagnostics.push(diag);
+                            FastType::Unknown
+                        }
+                    }
+                }
+            };
+            if !is_irrefutable(&for_expr.pattern) {
+                self.error("refutable pattern in `for` loop", for_expr.pattern.span());
+            }
+            self.push_scope();
+            self.check_pattern(&for_expr.pattern, &elem_ty);
+            self.analyze_loop_body(&for_expr.body, false, "for");
+            self.pop_scope();
+            self.types.insert(for_expr.id, FastType::Unit);
+            FastType::Unit
+        }
+
+        fn analyze_loop_body(
+            &mut self,
+            body: &HirBlock,
+            allows_value: bool,
+            kind: &str,
+        ) -> LoopContext {
+            self.loops.push(LoopContext {
+                allows_value,
+                break_ty: None,
+                scope_depth: self.scopes.len(),
+                assigned: Vec::new(),
+                captured: Vec::new(),
+            });
+            let body_ty = self.analyze_block(body);
+            let body_ty = self.resolve(&body_ty);
+            if !matches!(
+                body_ty,
+                FastType::Unit | FastType::Var(_) | FastType::Unknown
+            ) {
+                let diag = Diagnostic::error(format!("{kind} body must produce unit"), body.span)
+                    .with_note(format!("found `{body_ty}`"));
+                self.diagnostics.push(diag);
+            }
+            let loop_ctx = self.loops.pop().expect("loop context missing");
+            // The next iteration runs these assignments after the capture.
+            for (name, depth, span) in &loop_ctx.assigned {
+                if loop_ctx.captured.contains(&(*name, *depth)) {
+                    let diag = self
+                        .assigned_after_capture(*name, *span)
+                        .with_note("the loop assigns it again after the closure is created");
+                    self.diagnostics.push(diag);
+                }
+            }
+            loop_ctx
+        }
+
+        fn analyze_range(&mut self, range: &HirRange) {
+            for bound in [&range.start, &range.end] {
+                let ty = self.analyze_expr(bound);
+                self.expect(
+                    &FastType::Int,
+                    &ty,
+                    "range bounds must be integers",
+                    bound.span(),
+                );
+            }
+        }
+
+        fn analyze_break(&mut self, brk: &HirBreak) {
+            let ty = brk
+                .value
+                .as_ref()
+                .map(|value| self.analyze_expr(value))
+                .unwrap_or(FastType::Unit);
+            self.types.insert(brk.id, ty.clone());
+            let Some(loop_ctx) = self.loops.last_mut() else {
+                self.error("`break` outside of a loop", brk.span);
+                return;
+            };
+            if !loop_ctx.allows_value {
+                if brk.value.is_some() {
+                    let diag = Diagnostic::error(
+                        "`break` with a value is only allowed in `while true` loops",
+                        brk.span,
+                    )
+                    .with_note("other loops always evaluate to `()`");
+                    self.diagnostics.push(diag);
+                }
+                return;
+            }
+            match loop_ctx.break_ty.clone() {
+                Some(expected) => {
+                    self.expect(&expected, &ty, "mismatched `break` value types", brk.span);
+                }
+                None => loop_ctx.break_ty = Some(ty),
+            }
+        }
+
+        fn analyze_match(&mut self, match_expr: &HirMatch) -> FastType {
+            let scrutinee_ty = self.analyze_expr(&match_expr.scrutinee);
+            if match_expr.source == HirMatchSource::IfLet {
+                return self.analyze_if_let(match_expr, &scrutinee_ty);
+            }
+            let mut result: Option<FastType> = None;
+            for arm in &match_expr.arms {
+                self.push_scope();
+                self.check_pattern(&arm.pattern, &scrutinee_ty);
+                if let Some(guard) = &arm.guard {
+                    let guard_ty = self.analyze_expr(guard);
+                    self.expect(
+                        &FastType::Bool,
+                        &guard_ty,
+                        "match guard must be bool",
+                        guard.span(),
+                    );
+                }
+                let body_ty = self.analyze_block(&arm.body);
+                self.pop_scope();
+                if matches!(self.shallow(&body_ty), FastType::Unknown) {
+                    continue;
+                }
+                match &result {
+                    None => result = Some(body_ty),
+                    Some(expected) => {
+                        let expected = expected.clone();
+                        self.expect(
+                            &expected,
+                            &body_ty,
+                            "mismatched match arm types",
+                            arm.body.span,
+                        );
+                    }
+                }
+            }
+            let result = result.unwrap_or(FastType::Unknown);
+            let scrutinee_ty = self.resolve(&scrutinee_ty);
+            if let Some(missing) = self.missing_variants(&match_expr.arms, &scrutinee_ty) {
+                if !missing.is_empty() {
+                    let diag = Diagnostic::error("non-exhaustive match", match_expr.span)
+                        .with_note(format!("{} not covered", missing.join(", ")));
+                    self.diagnostics.push(diag);
+                }
+            } else if !self.is_exhaustive(&match_expr.arms, &scrutinee_ty) {
+                let diag = Diagnostic::error("non-exhaustive match", match_expr.span)
+                    .with_note("add a `_` arm to cover the remaining values");
+                self.diagnostics.push(diag);
+            }
+            self.types.insert(match_expr.id, result.clone());
+            result
+        }
+
+        fn analyze_if_let(&mut self, match_expr: &HirMatch, scrutinee_ty: &FastType) -> FastType {
+            let mut branch_tys = Vec::new();
+            for arm in &match_expr.arms {
+                self.push_scope();
+                self.check_pattern(&arm.pattern, scrutinee_ty);
+                branch_tys.push(self.analyze_block(&arm.body));
+                self.pop_scope();
+            }
+            let [then_ty, else_ty] = branch_tys.as_slice() else {
+                unreachable!("`if let` lowers to exactly two arms");
+            };
+            let unified = self.join_branches(then_ty, else_ty, match_expr.span);
+            self.types.insert(match_expr.id, unified.clone());
+            unified
+        }
+
+        /// Joins the types of two `if` branches. A `()` or dynamic branch
+        /// never conflicts, since such `if`s are usually used as statements.
+        fn join_branches(
+            &mut self,
+            then_ty: &FastType,
+            else_ty: &FastType,
+            span: Span,
+        ) -> FastType {
+            match (self.shallow(then_ty), self.shallow(else_ty)) {
+                (FastType::Unknown, other) | (other, FastType::Unknown) => other,
+                (FastType::Unit, _) | (_, FastType::Unit) => {
+                    if self.unify(then_ty, else_ty) {
+                        FastType::Unit
+                    } else {
+                        FastType::Unknown
+                    }
+                }
+                _ => {
+                    if self.expect(then_ty, else_ty, "mismatched branch types", span) {
+                        then_ty.clone()
+                    } else {
+                        FastType::Unknown
+                    }
+                }
+            }
+        }
+
+        fn check_pattern(&mut self, pattern: &HirPattern, expected: &FastType) {
+            match pattern {
+                HirPattern::Wildcard(wildcard) => {
+                    self.types.insert(wildcard.id, expected.clone());
+                }
+                HirPattern::Binding(binding) => {
+                    self.types.insert(binding.id, expected.clone());
+                    self.bind(binding.name, expected.clone());
+                }
+                HirPattern::Literal(lit) => {
+                    let ty = self.analyze_literal(lit);
+                    self.expect(expected, &ty, "mismatched pattern type", pattern.span());
+                }
+                HirPattern::Or(or) => {
+                    let mut expected_names: Option<Vec<Symbol>> = None;
+                    for alt in &or.alternatives {
+                        self.check_pattern(alt, expected);
+                        let mut names = Vec::new();
+                        collect_bindings(alt, &mut names);
+                        names.sort_by_key(|name| name.raw());
+                        match &expected_names {
+                            None => expected_names = Some(names),
+                            Some(first) => {
+                                let missing = first
+                                    .iter()
+                                    .filter(|name| !names.contains(name))
+                                    .chain(names.iter().filter(|name| !first.contains(name)))
+                                    .next()
+                                    .copied();
+                                if let Some(name) = missing {
+                                    let message = format!(
+                                        "variable `{}` is not bound in all alternatives",
+                                        self.name_of(name)
+                                    );
+                                    self.error(message, alt.span());
+                                }
+                            }
+                        }
+                    }
+                    self.types.insert(or.id, expected.clone());
+                }
+                HirPattern::Variant(variant) => {
+                    let Some((enum_name, fields)) = self.resolve_variant(&variant.path) else {
+                        for field in &variant.fields {
+                            self.check_pattern(field, &FastType::Unknown);
+                        }
+                        return;
+                    };
+                    let ty = FastType::Enum(enum_name);
+                    self.expect(expected, &ty, "mismatched pattern type", variant.span);
+                    if fields.len() != variant.fields.len() {
+                        self.error(
+                            format!(
+                                "pattern has {} field(s), but variant `{}` has {}",
+                                variant.fields.len(),
+                                self.path_name(&variant.path),
+                                fields.len()
+                            ),
+                            variant.span,
+                        );
+                    }
+                    for (idx, field) in variant.fields.iter().enumerate() {
+                        let field_ty = fields.get(idx).cloned().unwrap_or(FastType::Unknown);
+                        self.check_pattern(field, &field_ty);
+                    }
+                    self.types.insert(variant.path.id, ty.clone());
+                    self.types.insert(variant.id, ty);
+                }
+                HirPattern::Tuple(tuple) => {
+                    let element_tys = match self.shallow(expected) {
+                        FastType::Tuple(element_tys)
+                            if element_tys.len() == tuple.elements.len() =>
+                        {
+                            element_tys
+                        }
+                        FastType::Tuple(element_tys) => {
+                            self.error(
+                                format!(
+                                    "pattern has {} element(s), but the tuple has {}",
+                                    tuple.elements.len(),
+                                    element_tys.len()
+                                ),
+                                tuple.span,
+                            );
+                            vec![FastType::Unknown; tuple.elements.len()]
+                        }
+                        FastType::Var(_) => {
+                            let element_tys: Vec<FastType> =
+                                tuple.elements.iter().map(|_| self.fresh_var()).collect();
+                            self.unify(expected, &FastType::Tuple(element_tys.clone()));
+                            element_tys
+                        }
+                        FastType::Unknown => vec![FastType::Unknown; tuple.elements.len()],
+                        other => {
+                            let other = self.resolve(&other);
+                            let diag = Diagnostic::error("mismatched pattern type", tuple.span)
+                                .with_note(format!("expected `{other}`, found a tuple"));
+                            self.diagnostics.push(diag);
+                            vec![FastType::Unknown; tuple.elements.len()]
+                        }
+                    };
+                    for (element, ty) in tuple.elements.iter().zip(&element_tys) {
+                        self.check_pattern(element, ty);
+                    }
+                    self.types.insert(tuple.id, FastType::Tuple(element_tys));
+                }
+            }
+        }
+
+        fn missing_variants(
+            &self,
+            arms: &[HirMatchArm],
+            scrutinee_ty: &FastType,
+        ) -> Option<Vec<String>> {
+            let mut covered = Vec::new();
+            let mut irrefutable = false;
+            for arm in arms.iter().filter(|arm| arm.guard.is_none()) {
+                irrefutable |= is_irrefutable(&arm.pattern);
+                collect_variants(&arm.pattern, &mut covered);
+            }
+            let enum_name = match scrutinee_ty {
+                FastType::Enum(name) => name.clone(),
+                FastType::Unknown => {
+                    let (enum_sym, _) = covered.first()?;
+                    SmolStr::from(self.name_of(*enum_sym))
+                }
+                _ => return None,
+            };
+            let info = self.enums.get(&enum_name)?;
+            if irrefutable {
+                return Some(Vec::new());
+            }
+            let missing = info
+                .variants
+                .iter()
+                .filter(|(variant, _)| {
+                    !covered.iter().any(|(enum_sym, covered_variant)| {
+                        self.name_of(*enum_sym) == enum_name && covered_variant == variant
+                    })
+                })
+                .map(|(variant, _)| format!("`{enum_name}::{}`", self.name_of(*variant)))
+                .collect();
+            Some(missing)
+        }
+
+        fn is_exhaustive(&self, arms: &[HirMatchArm], scrutinee_ty: &FastType) -> bool {
+            let mut covered = Vec::new();
+            for arm in arms.iter().filter(|arm| arm.guard.is_none()) {
+                if is_irrefutable(&arm.pattern) {
+                    return true;
+                }
+                collect_literals(&arm.pattern, &mut covered);
+            }
+            let has = |value: &str| covered.iter().any(|lit| lit == value);
+            match scrutinee_ty {
+                FastType::Bool => has("true") && has("false"),
+                FastType::Unit => has("()"),
+                FastType::Unknown => (has("true") && has("false")) || has("()"),
+                _ => false,
+            }
+        }
+
+        fn unresolved_name(&mut self, name: &str, span: Span) {
+            let mut candidates: Vec<(usize, String)> = Vec::new();
+            let visible = self
+                .scopes
+                .iter()
+                .flat_map(|scope| scope.keys())
+                .map(|symbol| self.name_of(*symbol))
+                .chain(self.host_functions.iter().map(|name| name.to_string()));
+            for candidate in visible {
+                let distance = edit_distance(name, &candidate);
+                let limit = (name.chars().count() / 3).max(1);
+                if distance <= limit && !candidates.iter().any(|(_, seen)| *seen == candidate) {
+                    candidates.push((distance, candidate));
+                }
+            }
+            candidates.sort();
+            let mut diag =
+                Diagnostic::error(format!("cannot find value `{name}` in this scope"), span);
+            for (_, candidate) in candidates.into_iter().take(3) {
+                diag = diag.with_note(format!("a similar name exists: `{candidate}`"));
+            }
+            self.diagnostics.push(diag);
+        }
+
+        fn lookup_depth(&self, name: Symbol) -> Option<(usize, Binding)> {
+            for (depth, scope) in self.scopes.iter().enumerate().rev() {
                 if let Some(binding) = scope.get(&name) {
-                    return Some(binding.clone());
+                    return Some((depth, binding.clone()));
                 }
             }
             None
         }
 
+        fn is_captured(&self, depth: usize) -> bool {
+            depth > 0
+                && self
+                    .lambdas
+                    .last()
+                    .is_some_and(|lambda| depth < lambda.scope_depth)
+        }
+
+        fn note_capture(&mut self, name: Symbol, depth: usize) {
+            if depth == 0 {
+                return;
+            }
+            for lambda in &mut self.lambdas {
+                if depth < lambda.scope_depth && !lambda.captures.contains(&name) {
+                    lambda.captures.push(name);
+                }
+            }
+        }
+
+        /// Marks a mutable binding a closure has just captured, so it can no
+        /// longer change under the closure's copy.
+        fn note_mutable_capture(&mut self, name: Symbol) {
+            let Some((depth, binding)) = self.lookup_depth(name) else {
+                return;
+            };
+            if !binding.mutable {
+                return;
+            }
+            if let Some(binding) = self.scopes[depth].get_mut(&name) {
+                binding.captured = true;
+            }
+            for loop_ctx in &mut self.loops {
+                if depth < loop_ctx.scope_depth {
+                    loop_ctx.captured.push((name, depth));
+                }
+            }
+        }
+
+        fn assigned_after_capture(&self, name: Symbol, span: Span) -> Diagnostic {
+            let name = self.name_of(name);
+            Diagnostic::error(
+                format!("cannot assign to `{name}` after a closure captures it"),
+                span,
+            )
+            .with_note("closures capture variables by value")
+        }
+
         fn bind(&mut self, name: Symbol, ty: FastType) {
+            self.declare(
+                name,
+                Binding {
+                    ty,
+                    mutable: false,
+                    captured: false,
+                    function: None,
+                },
+            );
+        }
+
+        fn declare(&mut self, name: Symbol, binding: Binding) {
             if let Some(scope) = self.scopes.last_mut() {
-                scope.insert(name, Binding { ty });
+                scope.insert(name, binding);
             }
         }
 
+        fn name_of(&self, name: Symbol) -> String {
+            self.interner
+                .resolve(name)
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| format!("#{}", name.raw()))
+        }
+
+        fn path_name(&self, path: &HirPath) -> String {
+            path.segments
+                .iter()
+                .map(|segment| self.name_of(segment.name))
+                .collect::<Vec<_>>()
+                .join("::")
+        }
+
         fn push_scope(&mut self) {
             self.scopes.push(HashMap::new());
         }
@@ -352,26 +2541,322 @@ pub mod fast {
         }
     }
 
+    fn edit_distance(lhs: &str, rhs: &str) -> usize {
+        let rhs: Vec<char> = rhs.chars().collect();
+        let mut row: Vec<usize> = (0..=rhs.len()).collect();
+        for (i, l) in lhs.chars().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, r) in rhs.iter().enumerate() {
+                let substitution = diagonal + usize::from(l != *r);
+                diagonal = row[j + 1];
+                row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+            }
+        }
+        row[rhs.len()]
+    }
+
+    fn return_mismatch(annotated: bool) -> &'static str {
+        if annotated {
+            "mismatched return type"
+        } else {
+            "conflicting return types"
+        }
+    }
+
+    fn is_irrefutable(pattern: &HirPattern) -> bool {
+        match pattern {
+            HirPattern::Wildcard(_) | HirPattern::Binding(_) => true,
+            HirPattern::Literal(_) | HirPattern::Variant(_) => false,
+            HirPattern::Or(or) => or.alternatives.iter().any(is_irrefutable),
+            HirPattern::Tuple(tuple) => tuple.elements.iter().all(is_irrefutable),
+        }
+    }
+
+    fn collect_literals(pattern: &HirPattern, out: &mut Vec<String>) {
+        match pattern {
+            HirPattern::Literal(HirLiteral::Bool(boolean)) => out.push(boolean.value.to_string()),
+            HirPattern::Literal(HirLiteral::Unit(_)) => out.push("()".to_string()),
+            HirPattern::Or(or) => {
+                for alt in &or.alternatives {
+                    collect_literals(alt, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn collect_variants(pattern: &HirPattern, out: &mut Vec<(Symbol, Symbol)>) {
+        match pattern {
+            HirPattern::Variant(variant) => {
+                if let [enum_ref, variant_ref] = variant.path.segments.as_slice() {
+                    if variant.fields.iter().all(is_irrefutable) {
+                        out.push((enum_ref.name, variant_ref.name));
+                    }
+                }
+            }
+            HirPattern::Or(or) => {
+                for alt in &or.alternatives {
+                    collect_variants(alt, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn collect_bindings(pattern: &HirPattern, out: &mut Vec<Symbol>) {
+        match pattern {
+            HirPattern::Binding(binding) => out.push(binding.name),
+            HirPattern::Or(or) => {
+                if let Some(first) = or.alternatives.first() {
+                    collect_bindings(first, out);
+                }
+            }
+            HirPattern::Variant(variant) => {
+                for field in &variant.fields {
+                    collect_bindings(field, out);
+                }
+            }
+            HirPattern::Tuple(tuple) => {
+                for element in &tuple.elements {
+                    collect_bindings(element, out);
+                }
+            }
+            HirPattern::Wildcard(_) | HirPattern::Literal(_) => {}
+        }
+    }
+
+    fn literal_span(lit: &HirLiteral) -> Span {
+        match lit {
+            HirLiteral::Int(int) => int.span,
+            HirLiteral::Float(float) => float.span,
+            HirLiteral::String(string) => string.span,
+            HirLiteral::Bool(boolean) => boolean.span,
+            HirLiteral::Unit(unit) => unit.span,
+        }
+    }
+
     trait ExprExt {
         fn span(&self) -> Span;
     }
 
+    impl ExprExt for HirPattern {
+        fn span(&self) -> Span {
+            match self {
+                HirPattern::Wildcard(wildcard) => wildcard.span,
+                HirPattern::Binding(binding) => binding.span,
+                HirPattern::Literal(lit) => literal_span(lit),
+                HirPattern::Or(or) => or.span,
+                HirPattern::Variant(variant) => variant.span,
+                HirPattern::Tuple(tuple) => tuple.span,
+            }
+        }
+    }
+
     impl ExprExt for HirExpr {
         fn span(&self) -> Span {
             match self {
-                HirExpr::Literal(lit) => match lit {
-                    HirLiteral::Int(int) => int.span,
-                    HirLiteral::String(string) => string.span,
-                    HirLiteral::Bool(boolean) => boolean.span,
-                    HirLiteral::Unit(unit) => unit.span,
-                },
+                HirExpr::Literal(lit) => literal_span(lit),
                 HirExpr::Name(name) => name.span,
                 HirExpr::Call(call) => call.span,
+                HirExpr::MethodCall(call) => call.span,
                 HirExpr::If(if_expr) => if_expr.span,
+                HirExpr::Match(match_expr) => match_expr.span,
+                HirExpr::While(while_expr) => while_expr.span,
+                HirExpr::For(for_expr) => for_expr.span,
+                HirExpr::Range(range) => range.span,
+                HirExpr::StructLit(lit) => lit.span,
+                HirExpr::Field(access) => access.span,
+                HirExpr::List(list) => list.span,
+                HirExpr::Tuple(tuple) => tuple.span,
+                HirExpr::Index(index) => index.span,
+                HirExpr::Path(path) => path.span,
+                HirExpr::Lambda(lambda) => lambda.span,
                 HirExpr::Block(block) => block.span,
                 HirExpr::Binary(bin) => bin.span,
                 HirExpr::Unary(un) => un.span,
             }
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use kayton_front::tests_support::parse_str;
+
+        /// Messages of the diagnostics `analyze` reports for `source`.
+        fn messages(source: &str) -> Vec<String> {
+            let parsed = parse_str("test.ktn", source);
+            assert!(parsed.diagnostics.is_empty(), "{:?}", parsed.diagnostics);
+            analyze(&parsed.module, &[], &[])
+                .diagnostics
+                .iter()
+                .map(|diag| diag.message.to_string())
+                .collect()
+        }
+
+        #[test]
+        fn rejects_loop_control_outside_loops() {
+            assert_eq!(
+                messages("fn main():\n    break\n"),
+                ["`break` outside of a loop"]
+            );
+            assert_eq!(
+                messages("fn main():\n    continue\n"),
+                ["`continue` outside of a loop"]
+            );
+        }
+
+        #[test]
+        fn rejects_assignment_to_immutable_bindings() {
+            for source in [
+                "fn main():\n    let x = 1\n    x = 2\n",
+                "fn main():\n    let x = 1\n    x += 2\n",
+            ] {
+                assert_eq!(
+                    messages(source),
+                    ["cannot assign to immutable binding `x`"],
+                    "{source}"
+                );
+            }
+        }
+
+        #[test]
+        fn rejects_assignment_after_capture() {
+            assert!(messages(
+                "fn main():\n    let mut c = 0\n    c += 1\n    let f = || c + 1\n    f()\n"
+            )
+            .is_empty());
+            assert_eq!(
+                messages(
+                    "fn main():\n    let mut c = 0\n    let f = || c + 1\n    c = 5\n    f()\n"
+                ),
+                ["cannot assign to `c` after a closure captures it"]
+            );
+            assert_eq!(
+                messages(
+                    "fn main():\n    let mut i = 0\n    while i < 3:\n        i += 1\n        let f = || i\n"
+                ),
+                ["cannot assign to `i` after a closure captures it"]
+            );
+            assert_eq!(
+                messages("fn main():\n    let mut c = 0\n    let f = || { c = 1 }\n"),
+                ["cannot assign to captured variable `c`"]
+            );
+        }
+
+        #[test]
+        fn rejects_non_bool_logical_operands() {
+            assert_eq!(
+                messages("fn main():\n    let a = 1 && true\n"),
+                ["left operand has wrong type"]
+            );
+            assert_eq!(
+                messages("fn main():\n    let b = false || 2\n"),
+                ["right operand has wrong type"]
+            );
+        }
+
+        #[test]
+        fn rejects_unknown_fields() {
+            let point = "struct Point:\n    x: Int\n    y: Int\n\n";
+            assert_eq!(
+                messages(&format!(
+                    "{point}fn main():\n    let p = Point {{ x: 1, y: 2, z: 3 }}\n"
+                )),
+                ["struct `Point` has no field named `z`"]
+            );
+            assert_eq!(
+                messages(&format!(
+                    "{point}fn main():\n    let p = Point {{ x: 1, y: 2 }}\n    let w = p.w\n"
+                )),
+                ["no field `w` on type `Point`"]
+            );
+        }
+
+        #[test]
+        fn rejects_wrong_variant_arity() {
+            let shape = "enum Shape:\n    Circle(r)\n    Empty\n\n";
+            assert_eq!(
+                messages(&format!(
+                    "{shape}fn main():\n    let s = Shape::Circle(1, 2)\n"
+                )),
+                ["variant `Shape::Circle` expects 1 field(s), found 2"]
+            );
+            assert_eq!(
+                messages(&format!(
+                    "{shape}fn area(s):\n    match s:\n        Shape::Circle(a, b) => a\n        Shape::Empty => 0\n"
+                )),
+                ["pattern has 2 field(s), but variant `Shape::Circle` has 1"]
+            );
+        }
+
+        #[test]
+        fn rejects_annotation_mismatches() {
+            assert_eq!(
+                messages("fn f(x: Int) -> Int:\n    x\n\nfn main():\n    f(true)\n"),
+                ["mismatched argument type"]
+            );
+            assert_eq!(
+                messages("fn f() -> Bool:\n    1\n"),
+                ["mismatched return type"]
+            );
+        }
+
+        #[test]
+        fn types_calls_to_stdlib_builtins() {
+            let host: Vec<String> = ["int", "float", "str", "map", "len"]
+                .into_iter()
+                .map(String::from)
+                .collect();
+            let builtins = stdlib_builtins();
+            let messages = |source: &str| -> Vec<String> {
+                let parsed = parse_str("test.ktn", source);
+                analyze(&parsed.module, &host, &builtins)
+                    .diagnostics
+                    .iter()
+                    .map(|diag| diag.message.to_string())
+                    .collect()
+            };
+            assert_eq!(
+                messages("fn main():\n    float(1) < 2\n"),
+                ["mismatched numeric operands"]
+            );
+            assert_eq!(
+                messages("fn main():\n    let s: String = str(int(\"4\"))\n    s\n"),
+                Vec::<String>::new()
+            );
+            assert_eq!(
+                messages("fn main():\n    let m = map()\n    m[\"a\"] = 1\n    m[2]\n"),
+                ["mismatched map key type"]
+            );
+            assert_eq!(
+                messages("fn get(m: Map[String, Int]) -> Int:\n    m[\"a\"] + len(m)\n"),
+                Vec::<String>::new()
+            );
+        }
+
+        #[test]
+        fn checks_uses_of_inferred_lambda_parameters() {
+            let source = "struct P: x: Int\n\nfn main():\n    let f = |p| p.x\n    f(P { x: 1 })\n";
+            assert!(messages(source).is_empty());
+            assert_eq!(
+                messages("fn main():\n    let f = |p| p.x\n    f(3)\n"),
+                ["type `Int` has no fields"]
+            );
+            assert_eq!(
+                messages("fn main():\n    let f = |xs| xs[0]\n    f(true)\n"),
+                ["type `Bool` cannot be indexed"]
+            );
+        }
+
+        #[test]
+        fn rejects_uses_of_uninferred_types() {
+            assert_eq!(messages("fn get(p):\n    p.x\n"), ["cannot infer type"]);
+            assert_eq!(
+                messages("fn get(p):\n    p.inner.x + p.len()\n"),
+                ["cannot infer type", "cannot infer type"]
+            );
+        }
+    }
 }
@@ -13,11 +13,16 @@ pub fn print(_ctx: &KayCtx, value: KayHandle) -> KayResult<()> {
     Ok(())
 }
 
-#[kayton_extension(name = "len", doc = "Return the length of a string or bytes value.")]
+#[kayton_extension(
+    name = "len",
+    doc = "Return the length of a string, bytes, list or map value."
+)]
 pub fn len(_ctx: &KayCtx, value: KayHandle) -> KayResult<i64> {
     match value.describe()? {
         KayValueKind::String(data) => Ok(data.len() as i64),
         KayValueKind::Bytes(data) => Ok(data.len() as i64),
+        KayValueKind::List(items) => Ok(items.len() as i64),
+        KayValueKind::Map(entries) => Ok(entries.len() as i64),
         other => Err(KayError::new(
             KayErrorCode::TypeMismatch,
             format!("len is not defined for {other:?}"),
@@ -25,19 +30,107 @@ pub fn len(_ctx: &KayCtx, value: KayHandle) -> KayResult<i64> {
     }
 }
 
+#[kayton_extension(
+    name = "int",
+    doc = "Convert a float (truncating toward zero) or a numeric string to an int."
+)]
+pub fn int(_ctx: &KayCtx, value: KayHandle) -> KayResult<i64> {
+    match value.describe()? {
+        KayValueKind::Int(value) => Ok(value),
+        KayValueKind::Float(value) => {
+            if value.is_finite() && value >= i64::MIN as f64 && value < i64::MAX as f64 {
+                Ok(value as i64)
+            } else {
+                Err(KayError::new(
+                    KayErrorCode::InvalidArgument,
+                    format!("float {value:?} does not fit in an int"),
+                ))
+            }
+        }
+        KayValueKind::String(data) => data.trim().parse().map_err(|_| {
+            KayError::new(
+                KayErrorCode::InvalidArgument,
+                format!("cannot parse {data:?} as an int"),
+            )
+        }),
+        other => Err(KayError::new(
+            KayErrorCode::TypeMismatch,
+            format!("int is not defined for {other:?}"),
+        )),
+    }
+}
+
+#[kayton_extension(name = "float", doc = "Convert an int or a numeric string to a float.")]
+pub fn float(_ctx: &KayCtx, value: KayHandle) -> KayResult<f64> {
+    match value.describe()? {
+        KayValueKind::Int(value) => Ok(value as f64),
+        KayValueKind::Float(value) => Ok(value),
+        KayValueKind::String(data) => data.trim().parse().map_err(|_| {
+            KayError::new(
+                KayErrorCode::InvalidArgument,
+                format!("cannot parse {data:?} as a float"),
+            )
+        }),
+        other => Err(KayError::new(
+            KayErrorCode::TypeMismatch,
+            format!("float is not defined for {other:?}"),
+        )),
+    }
+}
+
+#[kayton_extension(
+    name = "str",
+    doc = "Format a value as a string; used by f-string interpolation."
+)]
+pub fn str(_ctx: &KayCtx, value: KayHandle) -> KayResult<String> {
+    format_value(&value)
+}
+
+#[kayton_extension(name = "map", doc = "Create a new, empty map.")]
+pub fn map(ctx: &KayCtx) -> KayResult<KayHandle> {
+    ctx.alloc_map(&[])
+}
+
 fn format_value(handle: &KayHandle) -> KayResult<String> {
+    let ctx = handle.ctx();
     match handle.describe()? {
         KayValueKind::Int(value) => Ok(value.to_string()),
+        KayValueKind::Float(value) => Ok(format!("{value:?}")),
         KayValueKind::Bool(value) => Ok(value.to_string()),
         KayValueKind::String(data) => Ok(data.to_string()),
         KayValueKind::Bytes(data) => Ok(format!("bytes[{}]", data.len())),
+        KayValueKind::List(items) => {
+            let items = items
+                .iter()
+                .map(|raw| format_value(&ctx.clone_raw(*raw)?))
+                .collect::<KayResult<Vec<_>>>()?;
+            Ok(format!("[{}]", items.join(", ")))
+        }
+        KayValueKind::Map(entries) => {
+            let entries = entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = format_value(&ctx.clone_raw(*key)?)?;
+                    let value = format_value(&ctx.clone_raw(*value)?)?;
+                    Ok(format!("{key}: {value}"))
+                })
+                .collect::<KayResult<Vec<_>>>()?;
+            Ok(format!("{{{}}}", entries.join(", ")))
+        }
         KayValueKind::Unit => Ok("()".to_string()),
         KayValueKind::Capsule { tag } => Ok(format!("<capsule {tag}>", tag = tag)),
     }
 }
 
 pub fn extensions() -> &'static [KayExtension] {
-    &[PRINT_EXTENSION, LEN_EXTENSION]
+    &[
+        PRINT_EXTENSION,
+        LEN_EXTENSION,
+        INT_EXTENSION,
+        FLOAT_EXTENSION,
+        STR_EXTENSION,
+        MAP_EXTENSION,
+    ]
 }
 
 #[cfg(test)]
@@ -57,4 +150,31 @@ mod tests {
         // print should not fail
         print(&ctx, hello).expect("print");
     }
+
+    #[test]
+    fn converts_between_ints_and_floats() {
+        let host = KayHost::new();
+        let ctx = host.api_ctx();
+        let truncated = int(&ctx, (-2.75_f64).to_kay(&ctx).expect("alloc")).expect("int");
+        assert_eq!(truncated, -2);
+        let widened = float(&ctx, 3_i64.to_kay(&ctx).expect("alloc")).expect("float");
+        assert_eq!(widened, 3.0);
+        let parsed = float(&ctx, " 1.5e3 ".to_kay(&ctx).expect("alloc")).expect("parse");
+        assert_eq!(parsed, 1500.0);
+        assert!(int(&ctx, f64::NAN.to_kay(&ctx).expect("alloc")).is_err());
+    }
+
+    #[test]
+    fn len_counts_collection_elements() {
+        let host = KayHost::new();
+        host.register_extensions(extensions()).expect("register");
+        let ctx = host.api_ctx();
+        let list = vec![1_i64, 2, 3].to_kay(&ctx).expect("alloc");
+        assert_eq!(len(&ctx, list.clone()).expect("len"), 3);
+        assert_eq!(format_value(&list).expect("format"), "[1, 2, 3]");
+        let empty = map(&ctx).expect("map");
+        assert_eq!(len(&ctx, empty).expect("len"), 0);
+        let rendered = str(&ctx, vec![1.5_f64].to_kay(&ctx).expect("alloc")).expect("str");
+        assert_eq!(rendered, "[1.5]");
+    }
 }
@@ -1,30 +1,155 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::Arc;
 
 use kayton_api::{KayCtx, KayError, KayHandle, KayValueKind};
-use kayton_bytecode::{BytecodeModule, ConstId, Constant, FunctionId, HostSlot, Instruction};
+use kayton_bytecode::{
+    BytecodeModule, ConstId, Constant, EnumLayout, FunctionId, GlobalId, HostSlot, Instruction,
+    SourceLocation, StructLayout,
+};
 use kayton_host::KayHost;
+use smol_str::SmolStr;
 use thiserror::Error;
 
 #[derive(Debug, Clone)]
 pub enum Value {
     Int(i64),
+    Float(f64),
     Bool(bool),
     Str(Arc<str>),
     Unit,
+    Struct(Rc<StructValue>),
+    Enum(Rc<EnumValue>),
+    List(Rc<RefCell<Vec<Value>>>),
+    /// Entries in insertion order. Keys are compared with `==` and may be
+    /// floats or structs and lists that can still be mutated, so they are not
+    /// hashed: lookup and insert are linear scans, O(n) in the entry count.
+    Map(Rc<RefCell<Vec<(Value, Value)>>>),
+    Tuple(Rc<[Value]>),
+    Function(Rc<Closure>),
     Handle(KayHandle),
 }
 
+#[derive(Debug)]
+pub struct StructValue {
+    pub layout: Rc<StructLayout>,
+    pub fields: RefCell<Vec<Value>>,
+}
+
+#[derive(Debug)]
+pub struct Closure {
+    pub function: FunctionId,
+    pub name: SmolStr,
+    pub captures: Vec<Value>,
+}
+
+#[derive(Debug)]
+pub struct EnumValue {
+    pub layout: Rc<EnumLayout>,
+    pub tag: u16,
+    pub fields: Vec<Value>,
+}
+
+impl EnumValue {
+    pub fn variant_name(&self) -> &str {
+        self.layout
+            .variants
+            .get(self.tag as usize)
+            .map(|variant| variant.name.as_str())
+            .unwrap_or("?")
+    }
+}
+
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
+        Equality::default().values(self, other)
+    }
+}
+
+/// Structural equality that terminates on cyclic values. Lists, maps and
+/// structs can come to contain themselves; a pair of them that is already
+/// being compared further up is assumed equal, so any difference is found
+/// along another path instead of recursing forever.
+#[derive(Default)]
+struct Equality {
+    active: Vec<(*const (), *const ())>,
+}
+
+impl Equality {
+    fn values(&mut self, lhs: &Value, rhs: &Value) -> bool {
+        match (lhs, rhs) {
             (Value::Int(lhs), Value::Int(rhs)) => lhs == rhs,
+            (Value::Float(lhs), Value::Float(rhs)) => lhs == rhs,
             (Value::Bool(lhs), Value::Bool(rhs)) => lhs == rhs,
             (Value::Str(lhs), Value::Str(rhs)) => lhs == rhs,
             (Value::Unit, Value::Unit) => true,
+            (Value::Struct(lhs), Value::Struct(rhs)) => {
+                Rc::ptr_eq(lhs, rhs)
+                    || (lhs.layout == rhs.layout
+                        && self.nested(Rc::as_ptr(lhs).cast(), Rc::as_ptr(rhs).cast(), |eq| {
+                            eq.all(&lhs.fields.borrow(), &rhs.fields.borrow())
+                        }))
+            }
+            (Value::Enum(lhs), Value::Enum(rhs)) => {
+                Rc::ptr_eq(lhs, rhs)
+                    || (lhs.layout == rhs.layout
+                        && lhs.tag == rhs.tag
+                        && self.all(&lhs.fields, &rhs.fields))
+            }
+            (Value::List(lhs), Value::List(rhs)) => {
+                Rc::ptr_eq(lhs, rhs)
+                    || self.nested(Rc::as_ptr(lhs).cast(), Rc::as_ptr(rhs).cast(), |eq| {
+                        eq.all(&lhs.borrow(), &rhs.borrow())
+                    })
+            }
+            (Value::Map(lhs), Value::Map(rhs)) => {
+                Rc::ptr_eq(lhs, rhs)
+                    || self.nested(Rc::as_ptr(lhs).cast(), Rc::as_ptr(rhs).cast(), |eq| {
+                        eq.maps(&lhs.borrow(), &rhs.borrow())
+                    })
+            }
+            (Value::Tuple(lhs), Value::Tuple(rhs)) => self.all(lhs, rhs),
+            (Value::Function(lhs), Value::Function(rhs)) => {
+                Rc::ptr_eq(lhs, rhs)
+                    || (lhs.function == rhs.function && self.all(&lhs.captures, &rhs.captures))
+            }
             (Value::Handle(lhs), Value::Handle(rhs)) => lhs.raw() == rhs.raw(),
             _ => false,
         }
     }
+
+    fn all(&mut self, lhs: &[Value], rhs: &[Value]) -> bool {
+        lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(lhs, rhs)| self.values(lhs, rhs))
+    }
+
+    /// Every entry of `lhs` has an equal value under the first equal key of
+    /// `rhs`, as in [`map_get`].
+    fn maps(&mut self, lhs: &[(Value, Value)], rhs: &[(Value, Value)]) -> bool {
+        lhs.len() == rhs.len()
+            && lhs.iter().all(|(key, value)| {
+                match rhs.iter().find(|(other, _)| self.values(key, other)) {
+                    Some((_, other)) => self.values(value, other),
+                    None => false,
+                }
+            })
+    }
+
+    fn nested(
+        &mut self,
+        lhs: *const (),
+        rhs: *const (),
+        compare: impl FnOnce(&mut Self) -> bool,
+    ) -> bool {
+        if self.active.contains(&(lhs, rhs)) {
+            return true;
+        }
+        self.active.push((lhs, rhs));
+        let equal = compare(self);
+        self.active.pop();
+        equal
+    }
 }
 
 impl Value {
@@ -47,9 +172,10 @@ impl From<&Constant> for Value {
     fn from(constant: &Constant) -> Self {
         match constant {
             Constant::Int(v) => Value::Int(*v),
+            Constant::Float(v) => Value::Float(*v),
             Constant::Bool(v) => Value::Bool(*v),
             Constant::String(s) => Value::Str(Arc::from(s.as_str())),
-            Constant::Unit => Value::Unit,
+            Constant::Unit | Constant::Struct(_) | Constant::Enum(_) => Value::Unit,
         }
     }
 }
@@ -66,14 +192,61 @@ pub enum VmError {
     HostNameType,
     #[error("local index out of range")]
     BadLocal,
+    #[error("global index {0} out of range")]
+    BadGlobal(GlobalId),
     #[error("stack underflow")]
     StackUnderflow,
     #[error("type error: expected {expected}")]
     TypeError { expected: &'static str },
     #[error("call arity mismatch: expected {expected}, found {found}")]
     CallArity { expected: usize, found: usize },
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("shift amount {0} is out of range")]
+    ShiftOverflow(i64),
+    #[error("integer overflow")]
+    IntegerOverflow,
+    #[error("field index {0} out of range")]
+    BadField(u16),
+    #[error("variant tag {0} out of range")]
+    BadVariant(u16),
+    #[error("index {index} out of bounds for list of length {len}")]
+    IndexOutOfBounds { index: i64, len: usize },
+    #[error("key not found in map")]
+    MissingKey,
+    #[error("cannot unpack a tuple of {found} element(s) into {expected}")]
+    TupleArity { expected: usize, found: usize },
+    #[error("no method `{method}` on `{ty}`")]
+    UnknownMethod { ty: String, method: String },
+    #[error("no field `{0}` on struct value")]
+    UnknownField(String),
+    #[error("cannot pass a {0} value to a host function")]
+    UnsupportedHostValue(&'static str),
     #[error("host call failed: {0:?}")]
     HostFailure(KayError),
+    #[error("{error} ({location})")]
+    At {
+        location: SourceLocation,
+        error: Box<VmError>,
+    },
+}
+
+impl VmError {
+    /// Where the failing instruction came from, when the module has debug info.
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            VmError::At { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// The error without its source location.
+    pub fn into_inner(self) -> VmError {
+        match self {
+            VmError::At { error, .. } => *error,
+            other => other,
+        }
+    }
 }
 
 impl From<KayError> for VmError {
@@ -86,14 +259,17 @@ struct Frame {
     function: FunctionId,
     ip: usize,
     locals: Vec<Value>,
+    /// Operand stack height when the frame was entered.
+    stack_base: usize,
 }
 
 impl Frame {
-    fn new(function_id: FunctionId, locals: Vec<Value>) -> Self {
+    fn new(function_id: FunctionId, locals: Vec<Value>, stack_base: usize) -> Self {
         Self {
             function: function_id,
             ip: 0,
             locals,
+            stack_base,
         }
     }
 }
@@ -104,6 +280,9 @@ pub fn run_module(module: &BytecodeModule, entry: &str, host: &KayHost) -> Resul
         .ok_or_else(|| VmError::EntryNotFound(entry.to_string()))?;
     let ctx = host.api_ctx();
     let mut vm = Vm::new(module, ctx);
+    if let Some(initializer) = module.initializer {
+        vm.run(initializer)?;
+    }
     vm.run(entry_id)
 }
 
@@ -111,6 +290,9 @@ struct Vm<'a> {
     module: &'a BytecodeModule,
     stack: Vec<Value>,
     frames: Vec<Frame>,
+    globals: Vec<Value>,
+    layouts: HashMap<ConstId, Rc<StructLayout>>,
+    enum_layouts: HashMap<ConstId, Rc<EnumLayout>>,
     ctx: KayCtx,
 }
 
@@ -120,12 +302,43 @@ impl<'a> Vm<'a> {
             module,
             stack: Vec::new(),
             frames: Vec::new(),
+            globals: module
+                .globals
+                .iter()
+                .map(|global| {
+                    module
+                        .constants
+                        .get(global.value as usize)
+                        .map(Value::from)
+                        .unwrap_or(Value::Unit)
+                })
+                .collect(),
+            layouts: HashMap::new(),
+            enum_layouts: HashMap::new(),
             ctx,
         }
     }
 
     fn run(&mut self, entry: FunctionId) -> Result<Value, VmError> {
-        self.call_function(entry, Vec::new())?;
+        self.call_function(entry, Vec::new(), &[])?;
+        self.execute().map_err(|error| self.locate(error))
+    }
+
+    fn locate(&self, error: VmError) -> VmError {
+        let location = self
+            .frames
+            .last()
+            .and_then(|frame| self.module.location(frame.function, frame.ip));
+        match location {
+            Some(location) => VmError::At {
+                location,
+                error: Box::new(error),
+            },
+            None => error,
+        }
+    }
+
+    fn execute(&mut self) -> Result<Value, VmError> {
         loop {
             let frame_index = match self.frames.len() {
                 0 => return Ok(Value::Unit),
@@ -175,6 +388,23 @@ impl<'a> Vm<'a> {
                         frame.ip += 1;
                     }
                 }
+                Instruction::LoadGlobal(idx) => {
+                    let value = self
+                        .globals
+                        .get(idx as usize)
+                        .cloned()
+                        .ok_or(VmError::BadGlobal(idx))?;
+                    self.stack.push(value);
+                    self.advance_ip(frame_index);
+                }
+                Instruction::StoreGlobal(idx) => {
+                    let value = self.pop()?;
+                    *self
+                        .globals
+                        .get_mut(idx as usize)
+                        .ok_or(VmError::BadGlobal(idx))? = value;
+                    self.advance_ip(frame_index);
+                }
                 Instruction::Jump(target) => {
                     if let Some(frame) = self.frames.get_mut(frame_index) {
                         frame.ip = target;
@@ -191,24 +421,85 @@ impl<'a> Vm<'a> {
                     }
                 }
                 Instruction::Add => {
-                    self.binary_int(|a, b| a + b)?;
+                    if let [.., Value::Str(lhs), Value::Str(rhs)] = self.stack.as_slice() {
+                        let joined = Value::Str(Arc::from(format!("{lhs}{rhs}")));
+                        self.stack.truncate(self.stack.len() - 2);
+                        self.stack.push(joined);
+                    } else {
+                        self.binary_numeric(
+                            |a, b| a.checked_add(b).ok_or(VmError::IntegerOverflow),
+                            |a, b| a + b,
+                        )?;
+                    }
                     self.advance_ip(frame_index);
                 }
                 Instruction::Sub => {
-                    self.binary_int(|a, b| a - b)?;
+                    self.binary_numeric(
+                        |a, b| a.checked_sub(b).ok_or(VmError::IntegerOverflow),
+                        |a, b| a - b,
+                    )?;
                     self.advance_ip(frame_index);
                 }
                 Instruction::Mul => {
-                    self.binary_int(|a, b| a * b)?;
+                    self.binary_numeric(
+                        |a, b| a.checked_mul(b).ok_or(VmError::IntegerOverflow),
+                        |a, b| a * b,
+                    )?;
                     self.advance_ip(frame_index);
                 }
                 Instruction::Div => {
-                    self.binary_int(|a, b| a / b)?;
+                    self.binary_numeric(
+                        |a, b| {
+                            if b == 0 {
+                                return Err(VmError::DivisionByZero);
+                            }
+                            a.checked_div(b).ok_or(VmError::IntegerOverflow)
+                        },
+                        |a, b| a / b,
+                    )?;
+                    self.advance_ip(frame_index);
+                }
+                Instruction::Mod => {
+                    self.binary_numeric(
+                        |a, b| {
+                            if b == 0 {
+                                return Err(VmError::DivisionByZero);
+                            }
+                            Ok(a.wrapping_rem(b))
+                        },
+                        |a, b| a % b,
+                    )?;
+                    self.advance_ip(frame_index);
+                }
+                Instruction::BitAnd => {
+                    self.binary_int(|a, b| Ok(a & b))?;
+                    self.advance_ip(frame_index);
+                }
+                Instruction::BitOr => {
+                    self.binary_int(|a, b| Ok(a | b))?;
+                    self.advance_ip(frame_index);
+                }
+                Instruction::BitXor => {
+                    self.binary_int(|a, b| Ok(a ^ b))?;
+                    self.advance_ip(frame_index);
+                }
+                Instruction::Shl => {
+                    self.binary_int(|a, b| Ok(a << shift_amount(b)?))?;
+                    self.advance_ip(frame_index);
+                }
+                Instruction::Shr => {
+                    self.binary_int(|a, b| Ok(a >> shift_amount(b)?))?;
                     self.advance_ip(frame_index);
                 }
                 Instruction::Neg => {
-                    let value = self.pop_int()?;
-                    self.stack.push(Value::Int(-value));
+                    let value = match self.pop()? {
+                        Value::Int(value) => {
+                            Value::Int(value.checked_neg().ok_or(VmError::IntegerOverflow)?)
+                        }
+                        Value::Float(value) => Value::Float(-value),
+                        _ => return Err(VmError::TypeError { expected: "number" }),
+                    };
+                    self.stack.push(value);
                     self.advance_ip(frame_index);
                 }
                 Instruction::Not => {
@@ -216,40 +507,37 @@ impl<'a> Vm<'a> {
                     self.stack.push(Value::Bool(!value));
                     self.advance_ip(frame_index);
                 }
+                Instruction::BitNot => {
+                    let value = self.pop_int()?;
+                    self.stack.push(Value::Int(!value));
+                    self.advance_ip(frame_index);
+                }
                 Instruction::Eq => {
-                    let rhs = self.pop_int()?;
-                    let lhs = self.pop_int()?;
+                    let rhs = self.pop()?;
+                    let lhs = self.pop()?;
                     self.stack.push(Value::Bool(lhs == rhs));
                     self.advance_ip(frame_index);
                 }
                 Instruction::Ne => {
-                    let rhs = self.pop_int()?;
-                    let lhs = self.pop_int()?;
+                    let rhs = self.pop()?;
+                    let lhs = self.pop()?;
                     self.stack.push(Value::Bool(lhs != rhs));
                     self.advance_ip(frame_index);
                 }
                 Instruction::Lt => {
-                    let rhs = self.pop_int()?;
-                    let lhs = self.pop_int()?;
-                    self.stack.push(Value::Bool(lhs < rhs));
+                    self.compare(Ordering::is_lt)?;
                     self.advance_ip(frame_index);
                 }
                 Instruction::Le => {
-                    let rhs = self.pop_int()?;
-                    let lhs = self.pop_int()?;
-                    self.stack.push(Value::Bool(lhs <= rhs));
+                    self.compare(Ordering::is_le)?;
                     self.advance_ip(frame_index);
                 }
                 Instruction::Gt => {
-                    let rhs = self.pop_int()?;
-                    let lhs = self.pop_int()?;
-                    self.stack.push(Value::Bool(lhs > rhs));
+                    self.compare(Ordering::is_gt)?;
                     self.advance_ip(frame_index);
                 }
                 Instruction::Ge => {
-                    let rhs = self.pop_int()?;
-                    let lhs = self.pop_int()?;
-                    self.stack.push(Value::Bool(lhs >= rhs));
+                    self.compare(Ordering::is_ge)?;
                     self.advance_ip(frame_index);
                 }
                 Instruction::Call(func, arg_count) => {
@@ -258,7 +546,43 @@ impl<'a> Vm<'a> {
                         args.push(self.pop()?);
                     }
                     args.reverse();
-                    self.call_function(func, args)?;
+                    self.call_function(func, args, &[])?;
+                }
+                Instruction::CallValue(arg_count) => {
+                    let mut args = Vec::with_capacity(arg_count as usize);
+                    for _ in 0..arg_count {
+                        args.push(self.pop()?);
+                    }
+                    args.reverse();
+                    let closure = match self.pop()? {
+                        Value::Function(closure) => closure,
+                        _ => {
+                            return Err(VmError::TypeError {
+                                expected: "function",
+                            })
+                        }
+                    };
+                    self.call_function(closure.function, args, &closure.captures)?;
+                }
+                Instruction::MakeClosure(func, capture_count) => {
+                    let name = self
+                        .module
+                        .functions
+                        .get(func as usize)
+                        .ok_or(VmError::BadFunction(func))?
+                        .name
+                        .clone();
+                    let count = capture_count as usize;
+                    if self.stack.len() < count {
+                        return Err(VmError::StackUnderflow);
+                    }
+                    let captures = self.stack.split_off(self.stack.len() - count);
+                    self.stack.push(Value::Function(Rc::new(Closure {
+                        function: func,
+                        name,
+                        captures,
+                    })));
+                    self.advance_ip(frame_index);
                 }
                 Instruction::CallHost(slot, arg_count) => {
                     let result = self.invoke_host(slot, arg_count)?;
@@ -280,9 +604,199 @@ impl<'a> Vm<'a> {
                     self.stack.push(result);
                     self.advance_ip(frame_index);
                 }
+                Instruction::CallMethod(name_const, arg_count) => {
+                    let func = self.resolve_method(name_const, arg_count)?;
+                    let mut args = Vec::with_capacity(arg_count as usize + 1);
+                    for _ in 0..=arg_count {
+                        args.push(self.pop()?);
+                    }
+                    args.reverse();
+                    self.call_function(func, args, &[])?;
+                }
+                Instruction::MakeStruct(layout_const) => {
+                    let layout = self.struct_layout(layout_const)?;
+                    let count = layout.fields.len();
+                    if self.stack.len() < count {
+                        return Err(VmError::StackUnderflow);
+                    }
+                    let fields = self.stack.split_off(self.stack.len() - count);
+                    self.stack.push(Value::Struct(Rc::new(StructValue {
+                        layout,
+                        fields: RefCell::new(fields),
+                    })));
+                    self.advance_ip(frame_index);
+                }
+                Instruction::LoadField(index) => {
+                    let target = self.pop_struct()?;
+                    let value = target
+                        .fields
+                        .borrow()
+                        .get(index as usize)
+                        .cloned()
+                        .ok_or(VmError::BadField(index))?;
+                    self.stack.push(value);
+                    self.advance_ip(frame_index);
+                }
+                Instruction::StoreField(index) => {
+                    let value = self.pop()?;
+                    let target = self.pop_struct()?;
+                    let mut fields = target.fields.borrow_mut();
+                    let slot = fields
+                        .get_mut(index as usize)
+                        .ok_or(VmError::BadField(index))?;
+                    *slot = value;
+                    self.advance_ip(frame_index);
+                }
+                Instruction::LoadFieldNamed(name_const) => {
+                    let target = self.pop_struct()?;
+                    let index = self.named_field_index(&target, name_const)?;
+                    let value = target.fields.borrow()[index].clone();
+                    self.stack.push(value);
+                    self.advance_ip(frame_index);
+                }
+                Instruction::StoreFieldNamed(name_const) => {
+                    let value = self.pop()?;
+                    let target = self.pop_struct()?;
+                    let index = self.named_field_index(&target, name_const)?;
+                    target.fields.borrow_mut()[index] = value;
+                    self.advance_ip(frame_index);
+                }
+                Instruction::MakeVariant(layout_const, tag) => {
+                    let layout = self.enum_layout(layout_const)?;
+                    let count = layout
+                        .variants
+                        .get(tag as usize)
+                        .ok_or(VmError::BadVariant(tag))?
+                        .arity as usize;
+                    if self.stack.len() < count {
+                        return Err(VmError::StackUnderflow);
+                    }
+                    let fields = self.stack.split_off(self.stack.len() - count);
+                    self.stack.push(Value::Enum(Rc::new(EnumValue {
+                        layout,
+                        tag,
+                        fields,
+                    })));
+                    self.advance_ip(frame_index);
+                }
+                Instruction::TestVariant(layout_const, tag) => {
+                    let layout = self.enum_layout(layout_const)?;
+                    let target = self.pop_enum()?;
+                    let matches = target.tag == tag
+                        && (Rc::ptr_eq(&target.layout, &layout) || target.layout == layout);
+                    self.stack.push(Value::Bool(matches));
+                    self.advance_ip(frame_index);
+                }
+                Instruction::LoadVariantField(index) => {
+                    let target = self.pop_enum()?;
+                    let value = target
+                        .fields
+                        .get(index as usize)
+                        .cloned()
+                        .ok_or(VmError::BadField(index))?;
+                    self.stack.push(value);
+                    self.advance_ip(frame_index);
+                }
+                Instruction::MakeList(count) => {
+                    let count = count as usize;
+                    if self.stack.len() < count {
+                        return Err(VmError::StackUnderflow);
+                    }
+                    let items = self.stack.split_off(self.stack.len() - count);
+                    self.stack.push(Value::List(Rc::new(RefCell::new(items))));
+                    self.advance_ip(frame_index);
+                }
+                Instruction::MakeTuple(count) => {
+                    let count = count as usize;
+                    if self.stack.len() < count {
+                        return Err(VmError::StackUnderflow);
+                    }
+                    let items = self.stack.split_off(self.stack.len() - count);
+                    self.stack.push(Value::Tuple(items.into()));
+                    self.advance_ip(frame_index);
+                }
+                Instruction::UnpackTuple(count) => {
+                    let items = match self.pop()? {
+                        Value::Tuple(items) => items,
+                        _ => return Err(VmError::TypeError { expected: "tuple" }),
+                    };
+                    if items.len() != count as usize {
+                        return Err(VmError::TupleArity {
+                            expected: count as usize,
+                            found: items.len(),
+                        });
+                    }
+                    self.stack.extend(items.iter().cloned());
+                    self.advance_ip(frame_index);
+                }
+                Instruction::Index => {
+                    let key = self.pop()?;
+                    let value = match self.pop()? {
+                        Value::List(items) => {
+                            let items = items.borrow();
+                            items[list_index(&key, items.len())?].clone()
+                        }
+                        Value::Map(entries) => map_get(&entries.borrow(), &key)
+                            .cloned()
+                            .ok_or(VmError::MissingKey)?,
+                        _ => {
+                            return Err(VmError::TypeError {
+                                expected: "list or map",
+                            })
+                        }
+                    };
+                    self.stack.push(value);
+                    self.advance_ip(frame_index);
+                }
+                Instruction::Len => {
+                    let len = match self.pop()? {
+                        Value::List(items) => items.borrow().len(),
+                        Value::Map(entries) => entries.borrow().len(),
+                        _ => {
+                            return Err(VmError::TypeError {
+                                expected: "list or map",
+                            })
+                        }
+                    };
+                    self.stack.push(Value::Int(len as i64));
+                    self.advance_ip(frame_index);
+                }
+                Instruction::StoreIndex => {
+                    let value = self.pop()?;
+                    let key = self.pop()?;
+                    match self.pop()? {
+                        Value::List(items) => {
+                            let mut items = items.borrow_mut();
+                            let index = list_index(&key, items.len())?;
+                            items[index] = value;
+                        }
+                        Value::Map(entries) => {
+                            // Comparing keys may borrow this map again, e.g.
+                            // when it is its own key, so only borrow it
+                            // mutably once the slot is known.
+                            let slot = entries
+                                .borrow()
+                                .iter()
+                                .position(|(existing, _)| *existing == key);
+                            let mut entries = entries.borrow_mut();
+                            match slot {
+                                Some(index) => entries[index].1 = value,
+                                None => entries.push((key, value)),
+                            }
+                        }
+                        _ => {
+                            return Err(VmError::TypeError {
+                                expected: "list or map",
+                            })
+                        }
+                    }
+                    self.advance_ip(frame_index);
+                }
                 Instruction::Return => {
                     let result = self.stack.pop().unwrap_or(Value::Unit);
-                    self.frames.pop();
+                    if let Some(frame) = self.frames.pop() {
+                        self.stack.truncate(frame.stack_base);
+                    }
                     if let Some(prev) = self.frames.last_mut() {
                         self.stack.push(result);
                         prev.ip += 1;
@@ -310,6 +824,39 @@ impl<'a> Vm<'a> {
         self.handle_to_value(handle)
     }
 
+    fn resolve_method(&self, name_const: ConstId, arg_count: u16) -> Result<FunctionId, VmError> {
+        let method = match self.module.constants.get(name_const as usize) {
+            Some(Constant::String(name)) => name,
+            _ => return Err(VmError::BadConstant(name_const)),
+        };
+        let receiver = self
+            .stack
+            .len()
+            .checked_sub(arg_count as usize + 1)
+            .and_then(|idx| self.stack.get(idx))
+            .ok_or(VmError::StackUnderflow)?;
+        let ty = match receiver {
+            Value::Struct(value) => value.layout.name.as_str(),
+            Value::Enum(value) => value.layout.name.as_str(),
+            Value::List(_) => "List",
+            Value::Map(_) => "Map",
+            Value::Tuple(_) => "Tuple",
+            Value::Int(_) => "Int",
+            Value::Float(_) => "Float",
+            Value::Bool(_) => "Bool",
+            Value::Str(_) => "String",
+            Value::Unit => "()",
+            Value::Function(_) => "fn",
+            Value::Handle(_) => "handle",
+        };
+        self.module
+            .function_index(&format!("{ty}::{method}"))
+            .ok_or_else(|| VmError::UnknownMethod {
+                ty: ty.to_string(),
+                method: method.clone(),
+            })
+    }
+
     fn collect_host_args(&mut self, arg_count: u16) -> Result<Vec<KayHandle>, VmError> {
         let mut handles = Vec::with_capacity(arg_count as usize);
         for _ in 0..arg_count {
@@ -324,9 +871,35 @@ impl<'a> Vm<'a> {
     fn ensure_handle(&mut self, value: Value) -> Result<KayHandle, VmError> {
         match value {
             Value::Int(v) => self.ctx.alloc_int(v).map_err(VmError::from),
+            Value::Float(v) => self.ctx.alloc_float(v).map_err(VmError::from),
             Value::Bool(v) => self.ctx.alloc_bool(v).map_err(VmError::from),
             Value::Str(s) => self.ctx.alloc_string(s).map_err(VmError::from),
             Value::Unit => self.ctx.alloc_unit().map_err(VmError::from),
+            Value::Struct(_) => Err(VmError::UnsupportedHostValue("struct")),
+            Value::Enum(_) => Err(VmError::UnsupportedHostValue("enum")),
+            Value::List(items) => {
+                let items = items
+                    .borrow()
+                    .iter()
+                    .map(|item| self.ensure_handle(item.clone()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.ctx.alloc_list(&items).map_err(VmError::from)
+            }
+            Value::Map(entries) => {
+                let entries = entries
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok((
+                            self.ensure_handle(key.clone())?,
+                            self.ensure_handle(value.clone())?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, VmError>>()?;
+                self.ctx.alloc_map(&entries).map_err(VmError::from)
+            }
+            Value::Tuple(_) => Err(VmError::UnsupportedHostValue("tuple")),
+            Value::Function(_) => Err(VmError::UnsupportedHostValue("function")),
             Value::Handle(handle) => Ok(handle),
         }
     }
@@ -334,15 +907,39 @@ impl<'a> Vm<'a> {
     fn handle_to_value(&self, handle: KayHandle) -> Result<Value, VmError> {
         match handle.describe().map_err(VmError::from)? {
             KayValueKind::Int(value) => Ok(Value::Int(value)),
+            KayValueKind::Float(value) => Ok(Value::Float(value)),
             KayValueKind::Bool(value) => Ok(Value::Bool(value)),
+            KayValueKind::String(value) => Ok(Value::Str(value)),
             KayValueKind::Unit => Ok(Value::Unit),
-            KayValueKind::String(_) | KayValueKind::Bytes(_) | KayValueKind::Capsule { .. } => {
-                Ok(Value::Handle(handle))
+            KayValueKind::List(items) => {
+                let items = items
+                    .iter()
+                    .map(|raw| self.handle_to_value(self.ctx.clone_raw(*raw)?))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::List(Rc::new(RefCell::new(items))))
             }
+            KayValueKind::Map(entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok((
+                            self.handle_to_value(self.ctx.clone_raw(*key)?)?,
+                            self.handle_to_value(self.ctx.clone_raw(*value)?)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, VmError>>()?;
+                Ok(Value::Map(Rc::new(RefCell::new(entries))))
+            }
+            KayValueKind::Bytes(_) | KayValueKind::Capsule { .. } => Ok(Value::Handle(handle)),
         }
     }
 
-    fn call_function(&mut self, func_id: FunctionId, args: Vec<Value>) -> Result<(), VmError> {
+    fn call_function(
+        &mut self,
+        func_id: FunctionId,
+        args: Vec<Value>,
+        captures: &[Value],
+    ) -> Result<(), VmError> {
         let function = self
             .module
             .functions
@@ -356,10 +953,11 @@ impl<'a> Vm<'a> {
             });
         }
         let mut locals = vec![Value::Unit; function.locals as usize];
-        for (idx, arg) in args.into_iter().enumerate() {
-            locals[idx] = arg;
+        for (idx, value) in args.into_iter().chain(captures.iter().cloned()).enumerate() {
+            *locals.get_mut(idx).ok_or(VmError::BadLocal)? = value;
         }
-        self.frames.push(Frame::new(func_id, locals));
+        self.frames
+            .push(Frame::new(func_id, locals, self.stack.len()));
         Ok(())
     }
 
@@ -379,6 +977,62 @@ impl<'a> Vm<'a> {
             .ok_or(VmError::TypeError { expected: "int" })
     }
 
+    fn pop_struct(&mut self) -> Result<Rc<StructValue>, VmError> {
+        match self.pop()? {
+            Value::Struct(value) => Ok(value),
+            _ => Err(VmError::TypeError { expected: "struct" }),
+        }
+    }
+
+    fn struct_layout(&mut self, id: ConstId) -> Result<Rc<StructLayout>, VmError> {
+        if let Some(layout) = self.layouts.get(&id) {
+            return Ok(layout.clone());
+        }
+        match self.module.constants.get(id as usize) {
+            Some(Constant::Struct(layout)) => {
+                let layout = Rc::new(layout.clone());
+                self.layouts.insert(id, layout.clone());
+                Ok(layout)
+            }
+            _ => Err(VmError::BadConstant(id)),
+        }
+    }
+
+    fn pop_enum(&mut self) -> Result<Rc<EnumValue>, VmError> {
+        match self.pop()? {
+            Value::Enum(value) => Ok(value),
+            _ => Err(VmError::TypeError { expected: "enum" }),
+        }
+    }
+
+    fn enum_layout(&mut self, id: ConstId) -> Result<Rc<EnumLayout>, VmError> {
+        if let Some(layout) = self.enum_layouts.get(&id) {
+            return Ok(layout.clone());
+        }
+        match self.module.constants.get(id as usize) {
+            Some(Constant::Enum(layout)) => {
+                let layout = Rc::new(layout.clone());
+                self.enum_layouts.insert(id, layout.clone());
+                Ok(layout)
+            }
+            _ => Err(VmError::BadConstant(id)),
+        }
+    }
+
+    fn named_field_index(
+        &self,
+        target: &StructValue,
+        name_const: ConstId,
+    ) -> Result<usize, VmError> {
+        match self.module.constants.get(name_const as usize) {
+            Some(Constant::String(name)) => target
+                .layout
+                .field_index(name)
+                .ok_or_else(|| VmError::UnknownField(name.clone())),
+            _ => Err(VmError::BadConstant(name_const)),
+        }
+    }
+
     fn pop_bool(&mut self) -> Result<bool, VmError> {
         self.pop()?
             .as_bool()
@@ -387,37 +1041,220 @@ impl<'a> Vm<'a> {
 
     fn binary_int<F>(&mut self, op: F) -> Result<(), VmError>
     where
-        F: FnOnce(i64, i64) -> i64,
+        F: FnOnce(i64, i64) -> Result<i64, VmError>,
     {
         let rhs = self.pop_int()?;
         let lhs = self.pop_int()?;
-        self.stack.push(Value::Int(op(lhs, rhs)));
+        self.stack.push(Value::Int(op(lhs, rhs)?));
         Ok(())
     }
+
+    fn binary_numeric<I, F>(&mut self, int_op: I, float_op: F) -> Result<(), VmError>
+    where
+        I: FnOnce(i64, i64) -> Result<i64, VmError>,
+        F: FnOnce(f64, f64) -> f64,
+    {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+        let result = match (lhs, rhs) {
+            (Value::Int(lhs), Value::Int(rhs)) => Value::Int(int_op(lhs, rhs)?),
+            (Value::Float(lhs), Value::Float(rhs)) => Value::Float(float_op(lhs, rhs)),
+            _ => {
+                return Err(VmError::TypeError {
+                    expected: "matching numeric operands",
+                })
+            }
+        };
+        self.stack.push(result);
+        Ok(())
+    }
+
+    fn compare(&mut self, test: fn(Ordering) -> bool) -> Result<(), VmError> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+        let ordering = match (lhs, rhs) {
+            (Value::Int(lhs), Value::Int(rhs)) => Some(lhs.cmp(&rhs)),
+            (Value::Float(lhs), Value::Float(rhs)) => lhs.partial_cmp(&rhs),
+            _ => {
+                return Err(VmError::TypeError {
+                    expected: "matching numeric operands",
+                })
+            }
+        };
+        self.stack.push(Value::Bool(ordering.is_some_and(test)));
+        Ok(())
+    }
+}
+
+fn list_index(key: &Value, len: usize) -> Result<usize, VmError> {
+    let index = key.as_int().ok_or(VmError::TypeError { expected: "int" })?;
+    usize::try_from(index)
+        .ok()
+        .filter(|idx| *idx < len)
+        .ok_or(VmError::IndexOutOfBounds { index, len })
+}
+
+/// Linear search for `key`; see [`Value::Map`].
+fn map_get<'v>(entries: &'v [(Value, Value)], key: &Value) -> Option<&'v Value> {
+    entries
+        .iter()
+        .find(|(existing, _)| existing == key)
+        .map(|(_, value)| value)
+}
+
+fn shift_amount(amount: i64) -> Result<u32, VmError> {
+    if (0..64).contains(&amount) {
+        Ok(amount as u32)
+    } else {
+        Err(VmError::ShiftOverflow(amount))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kayton_bytecode::asm::{assemble, disassemble};
     use kayton_emitter_bc::emit;
     use kayton_front::tests_support::parse_str;
     use kayton_host::KayHost;
-    use kayton_sema::fast::analyze;
+    use kayton_sema::fast::{analyze, stdlib_builtins};
 
     fn compile_and_run(source: &str) -> Value {
+        try_compile_and_run(source).expect("vm run")
+    }
+
+    fn try_compile_and_run(source: &str) -> Result<Value, VmError> {
+        let host = stdlib_host();
+        let module = compile(source, &host);
+        run_module(&module, "main", &host).map_err(VmError::into_inner)
+    }
+
+    fn stdlib_host() -> KayHost {
+        let host = KayHost::new();
+        host.register_extensions(kayton_stdlib::extensions())
+            .expect("register stdlib");
+        host
+    }
+
+    fn compile(source: &str, host: &KayHost) -> BytecodeModule {
         let parsed = parse_str("test.ktn", source);
         assert!(parsed.diagnostics.is_empty(), "{:?}", parsed.diagnostics);
-        let analysis = analyze(&parsed.module);
+        let analysis = analyze(&parsed.module, &host.extension_names(), &stdlib_builtins());
         assert!(
             analysis.diagnostics.is_empty(),
             "{:?}",
             analysis.diagnostics
         );
-        let module = emit(&parsed.module, &analysis).expect("emit");
-        let host = KayHost::new();
-        host.register_extensions(kayton_stdlib::extensions())
-            .expect("register stdlib");
-        run_module(&module, "main", &host).expect("vm run")
+        emit(&parsed.module, &analysis, &parsed.source_map).expect("emit")
+    }
+
+    #[test]
+    fn disassembles_emitted_bytecode() {
+        let module = compile(
+            r#"
+fn sum(n: Int) -> Int:
+    let mut total = 0
+    for i in 0..n:
+        total = total + i
+    total
+
+fn main():
+    print(sum(4))
+"#,
+            &stdlib_host(),
+        );
+        assert_eq!(
+            disassemble(&module),
+            r#"import print
+
+fn sum params=1 locals=5 max_stack=2
+    ; locals: 0 n, 1 total, 4 i
+    ; test.ktn:3
+    load_const int 0
+    store_local 1
+    ; test.ktn:4
+    load_const int 0
+    store_local 2
+    load_local 0
+    store_local 3
+L0:
+    load_local 2
+    load_local 3
+    lt
+    jump_if_false L1
+    load_local 2
+    store_local 4
+    ; test.ktn:5
+    load_local 1
+    load_local 4
+    add
+    store_local 1
+    ; test.ktn:4
+    load_local 2
+    load_const int 1
+    add
+    store_local 2
+    jump L0
+L1:
+    load_const unit
+    pop
+    ; test.ktn:6
+    load_local 1
+    return
+end
+
+fn main params=0 locals=0 max_stack=1
+    ; test.ktn:9
+    load_const int 4
+    call sum 1
+    call_host_dynamic "print" 1
+    return
+end
+"#
+        );
+    }
+
+    #[test]
+    fn runs_hand_assembled_bytecode() {
+        let mut module = assemble(
+            r#"
+struct Pair left right
+
+fn main params=0 locals=1
+    load_const int 5
+    call fact 1
+    load_const int 2
+    make_struct Pair
+    store_local 0
+    load_local 0
+    load_field_named "left"
+    return
+end
+
+; n! by recursion
+fn fact params=1 locals=1
+    load_local 0
+    load_const int 1
+    le
+    jump_if_false recurse
+    load_const int 1
+    return
+recurse:
+    load_local 0
+    load_local 0
+    load_const int 1
+    sub
+    call fact 1
+    mul
+    return
+end
+"#,
+        )
+        .expect("assemble");
+        module.verify().expect("verify");
+        assert_eq!(module.functions[1].max_stack, 3);
+        let value = run_module(&module, "main", &stdlib_host()).expect("vm run");
+        assert_eq!(value, Value::Int(120));
     }
 
     #[test]
@@ -464,6 +1301,814 @@ fn main():
         assert_eq!(value, Value::Int(120));
     }
 
+    #[test]
+    fn runs_match_on_ints() {
+        let value = compile_and_run(
+            r#"
+fn classify(n):
+    match n:
+        0 => 100
+        1 | 2 => 200
+        x if x < 0 => 300
+        -5 => 999
+        x =>:
+            let doubled = x * 2
+            doubled
+
+fn main():
+    classify(0) + classify(2) + classify(-5) + classify(7)
+"#,
+        );
+        assert_eq!(value, Value::Int(614));
+    }
+
+    #[test]
+    fn runs_match_on_strings_and_bools() {
+        let value = compile_and_run(
+            r#"
+fn code(name, loud):
+    let base = match name {
+        "alpha" => 1,
+        "beta" => 2,
+        _ => 0,
+    }
+    match loud:
+        true => base * 10
+        false => base
+
+fn main():
+    code("beta", true) + code("alpha", false) + code("gamma", true)
+"#,
+        );
+        assert_eq!(value, Value::Int(21));
+    }
+
+    #[test]
+    fn runs_for_loops_with_break_and_continue() {
+        let value = compile_and_run(
+            r#"
+fn first_square_over(limit):
+    for i in 0..limit:
+        if i * i > 50:
+            return i
+    -1
+
+fn first_odd_square_over(limit):
+    for i in 0..limit:
+        if i / 2 * 2 == i:
+            continue
+        if i * i > 15:
+            return i
+    0
+
+fn last_in(n):
+    for i in 0..=n:
+        if i == n:
+            return i * 10
+    0
+
+fn main():
+    let a = first_square_over(100)
+    let b = first_odd_square_over(10)
+    let c = while true:
+        break a + b
+    let d = for _ in 0..3:
+        break
+    let e = first_square_over(3)
+    a * 100 + b * 10 + c + last_in(3) + e
+"#,
+        );
+        assert_eq!(value, Value::Int(892));
+    }
+
+    #[test]
+    fn runs_for_loops_over_lists() {
+        let value = compile_and_run(
+            r#"
+fn positive_sum(xs):
+    let mut total = 0
+    for x in xs:
+        if x < 0:
+            continue
+        if x > 50:
+            break
+        total += x
+    total
+
+fn main():
+    let mut products = 0
+    for (a, b) in [(1, 2), (3, 4)]:
+        products += a * b
+    for _ in []:
+        return 0
+    positive_sum([3, -1, 4, 100, 5]) * 100 + products
+"#,
+        );
+        assert_eq!(value, Value::Int(714));
+    }
+
+    #[test]
+    fn loop_control_discards_pending_operands() {
+        let value = compile_and_run(
+            r#"
+fn pair(a, b):
+    a * 1000 + b
+
+fn first_kept(n):
+    for i in 0..n:
+        let y = 1 + if i < 2: { continue } else: i
+        return y
+    0
+
+fn main():
+    let found = while true:
+        let z = 100 + if true: { break 7 } else: 0
+    let stopped = for i in 0..5:
+        let w = 10 * if i == 3: { break } else: 1
+    pair(found, first_kept(5))
+"#,
+        );
+        assert_eq!(value, Value::Int(7003));
+    }
+
+    #[test]
+    fn inclusive_range_ending_at_max_int_terminates() {
+        let value = compile_and_run(
+            r#"
+fn main():
+    for i in 9223372036854775806..=9223372036854775807:
+        let last = i
+    for i in 3..=1:
+        return 1
+    2
+"#,
+        );
+        assert_eq!(value, Value::Int(2));
+    }
+
+    #[test]
+    fn early_return_discards_pending_operands() {
+        let value = compile_and_run(
+            r#"
+fn g(a, b):
+    a + b
+
+fn f(c):
+    g(10, if c: { return 5 } else: 2)
+
+fn main():
+    let mut total = 0
+    for i in 0..4:
+        total = total * 10 + f(i == 1)
+    total
+"#,
+        );
+        assert_eq!(value, Value::Int(12 * 1000 + 5 * 100 + 12 * 10 + 12));
+    }
+
+    #[test]
+    fn runs_while_loop_with_mutable_counter() {
+        let value = compile_and_run(
+            r#"
+fn main():
+    let mut total = 0
+    let mut i = 0
+    while i < 10:
+        i += 1
+        if i == 5:
+            continue
+        total = total + i
+    total *= 2
+    total -= 4
+    total /= 2
+    total
+"#,
+        );
+        assert_eq!(value, Value::Int(48));
+    }
+
+    #[test]
+    fn short_circuits_logical_operators() {
+        let value = compile_and_run(
+            r#"
+fn in_range(n, lo, hi):
+    n >= lo && n < hi
+
+fn check(d):
+    d == 0 || 10 / d > 2
+
+fn main():
+    let mut score = 0
+    if in_range(5, 0, 10) && !in_range(5, 6, 10):
+        score += 1
+    if check(0) && check(3):
+        score += 10
+    if check(5) || false:
+        score += 100
+    if false && 1 / 0 == 0 || true:
+        score += 1000
+    score
+"#,
+        );
+        assert_eq!(value, Value::Int(1011));
+    }
+
+    #[test]
+    fn runs_modulo_bitwise_and_shift_operators() {
+        let value = compile_and_run(
+            r#"
+fn main():
+    let a = 17 % 5
+    let b = -7 % 3
+    let c = 12 & 10 | 1
+    let d = 6 ^ 3
+    let e = 1 << 4 + 1
+    let f = -16 >> 2
+    let g = ~5
+    let h = 4 & 1 == 0
+    if h:
+        a * 1000000 + (b + 1) * 100000 + c * 1000 + d * 100 + e + f + g
+    else:
+        0
+"#,
+        );
+        assert_eq!(value, Value::Int(2009522));
+    }
+
+    #[test]
+    fn runs_struct_construction_and_field_access() {
+        let value = compile_and_run(
+            r#"
+struct Point:
+    x: Int
+    y: Int
+
+struct Size { width: Int, height: Int }
+
+struct Rect: origin: Point, size: Size
+
+fn area(rect: Rect):
+    rect.size.width * rect.size.height
+
+fn main():
+    let rect = Rect { size: Size { width: 3, height: 4 }, origin: Point { x: 1, y: 2 } }
+    rect.origin.x = 10
+    rect.size.height += 1
+    let moved = Point { y: rect.origin.y * 100, x: rect.origin.x }
+    if moved == Point { x: 10, y: 200 }:
+        area(rect) + moved.x * 1000
+    else:
+        0
+"#,
+        );
+        assert_eq!(value, Value::Int(10015));
+    }
+
+    #[test]
+    fn runs_enum_construction_and_destructuring() {
+        let value = compile_and_run(
+            r#"
+enum Shape:
+    Circle(r: Int)
+    Rect(w, h)
+    Empty
+
+enum Opt { Some(value), Nothing }
+
+fn area(shape):
+    match shape:
+        Shape::Circle(r) => 3 * r * r
+        Shape::Rect(w, h) => w * h
+        Shape::Empty => 0
+
+fn unwrap_or(opt, default):
+    if let Opt::Some(v) = opt: v else: default
+
+fn width(opt):
+    match opt:
+        Opt::Some(Shape::Rect(w, _)) => w
+        Opt::Some(_) => 1
+        Opt::Nothing => 0
+
+fn main():
+    let areas = area(Shape::Circle(2)) + area(Shape::Rect(3, 4)) + area(Shape::Empty)
+    let opts = unwrap_or(Opt::Some(7), 0) * 10 + unwrap_or(Opt::Nothing, 5)
+    let same = if Shape::Rect(1, 2) == Shape::Rect(1, 2): 1 else: 0
+    areas * 10000 + opts * 100 + width(Opt::Some(Shape::Rect(9, 1))) * 10 + same
+"#,
+        );
+        assert_eq!(value, Value::Int(247591));
+    }
+
+    #[test]
+    fn runs_static_and_dynamic_method_calls() {
+        let value = compile_and_run(
+            r#"
+struct Point:
+    x: Int
+    y: Int
+
+enum Shape: Square(side), Circle(r)
+
+impl Point:
+    fn new(x, y):
+        Point { x: x, y: y }
+
+    fn len2(self):
+        self.x * self.x + self.y * self.y
+
+    fn shift(self, dx):
+        self.x += dx
+        self
+
+impl Shape:
+    fn area(self):
+        match self:
+            Shape::Square(s) => s * s
+            Shape::Circle(r) => 3 * r * r
+
+fn measure(thing: Shape):
+    thing.area()
+
+fn main():
+    let p = Point::new(3, 4)
+    let q = p.shift(1).len2()
+    q * 1000 + measure(Shape::Square(5)) + Point::len2(Point::new(1, 0))
+"#,
+        );
+        assert_eq!(value, Value::Int(32026));
+        let missing = try_compile_and_run("fn main():\n    len([1]).nope()\n");
+        assert!(matches!(missing, Err(VmError::UnknownMethod { .. })));
+    }
+
+    #[test]
+    fn runs_lambdas_closures_and_function_values() {
+        let value = compile_and_run(
+            r#"
+fn apply_n(f, n, x):
+    let mut acc = x
+    for _ in 0..n:
+        acc = f(acc)
+    acc
+
+fn compose(f, g):
+    |x| g(f(x))
+
+fn make_adder(k):
+    |x| x + k
+
+fn double(x):
+    x * 2
+
+fn sum_where(n, keep):
+    let mut total = 0
+    for i in 0..n:
+        if keep(i):
+            total += i
+    total
+
+fn main():
+    let both = compose(make_adder(3), double)
+    let base = 10
+    let evens = sum_where(10, |i| i % 2 == 0)
+    let nested = (|a| |b| a * b + base)(4)(5)
+    apply_n(both, 2, 1) * 10000 + evens * 100 + nested
+"#,
+        );
+        assert_eq!(value, Value::Int(222030));
+        let fields = compile_and_run(
+            "struct P: x: Int, y: Int\n\nfn main():\n    let f = |p| p.y - p.x\n    f(P { x: 1, y: 5 })\n",
+        );
+        assert_eq!(fields, Value::Int(4));
+    }
+
+    #[test]
+    fn captures_mutable_variables_after_their_last_assignment() {
+        let value = compile_and_run(
+            r#"
+fn main():
+    let mut scale = 1
+    for i in 1..4:
+        scale *= i
+    let mut offset = 0
+    offset += 7
+    let f = |x| x * scale + offset
+    f(5)
+"#,
+        );
+        assert_eq!(value, Value::Int(37));
+    }
+
+    #[test]
+    fn runs_list_and_map_literals_with_indexing() {
+        let value = compile_and_run(
+            r#"
+fn sum(xs: List[Int]):
+    let mut total = 0
+    for i in 0..len(xs):
+        total += xs[i]
+    total
+
+fn main():
+    let xs = [1, 2, 3,
+        4]
+    xs[0] = 10
+    xs[1] += 5
+    let grid = [[1], [2, 3]]
+    grid[1][0] = 20
+    let counts = map()
+    counts["a"] = 1
+    counts["b"] = len(grid)
+    counts["a"] += 40
+    if xs == [10, 7, 3, 4] && len(counts) == 2:
+        sum(xs) * 10000 + counts["a"] * 100 + counts["b"] * 10 + grid[1][0] / 10
+    else:
+        0
+"#,
+        );
+        assert_eq!(value, Value::Int(244122));
+        let out_of_bounds = try_compile_and_run("fn main():\n    let xs = [1, 2]\n    xs[2]\n");
+        assert!(matches!(
+            out_of_bounds,
+            Err(VmError::IndexOutOfBounds { index: 2, len: 2 })
+        ));
+        let missing = try_compile_and_run("fn main():\n    let m = map()\n    m[1]\n");
+        assert!(matches!(missing, Err(VmError::MissingKey)));
+    }
+
+    #[test]
+    fn compares_self_referential_maps_and_lists() {
+        // Sema rejects these values as infinite types, so build them by hand.
+        let module = assemble(
+            r#"
+fn main params=0 locals=4
+    ; m[m] = 3, then m[m] = 4 through the existing key
+    call_host_dynamic "map" 0
+    store_local 0
+    load_local 0
+    load_local 0
+    load_const int 3
+    store_index
+    load_local 0
+    load_local 0
+    load_const int 4
+    store_index
+    ; other[other] = 4
+    call_host_dynamic "map" 0
+    store_local 1
+    load_local 1
+    load_local 1
+    load_const int 4
+    store_index
+    ; xs[0] = xs and ys[0] = ys
+    load_const int 0
+    make_list 1
+    store_local 2
+    load_local 2
+    load_const int 0
+    load_local 2
+    store_index
+    load_const int 0
+    make_list 1
+    store_local 3
+    load_local 3
+    load_const int 0
+    load_local 3
+    store_index
+    ; (m[other], m == other, xs == ys)
+    load_local 0
+    load_local 1
+    index
+    load_local 0
+    load_local 1
+    eq
+    load_local 2
+    load_local 3
+    eq
+    make_tuple 3
+    return
+end
+"#,
+        )
+        .expect("assemble");
+        let value = run_module(&module, "main", &stdlib_host()).expect("vm run");
+        let Value::Tuple(items) = value else {
+            panic!("expected a tuple, found {value:?}");
+        };
+        assert_eq!(
+            &*items,
+            &[Value::Int(4), Value::Bool(true), Value::Bool(true)]
+        );
+    }
+
+    #[test]
+    fn runs_tuples_and_destructuring_let() {
+        let value = compile_and_run(
+            r#"
+fn divmod(a, b):
+    (a / b, a % b)
+
+fn swap(pair):
+    let (x, y) = pair
+    (y, x)
+
+fn main():
+    let (q, r) = divmod(17, 5)
+    let _ = divmod(1, 1)
+    let mut (a, b) = (1, 2)
+    a += 10
+    let ((c, _), d) = ((3, 4), 5)
+    let picked = match swap((q, r)):
+        (2, n) => n * 1000
+        (m, _) => m
+    if (q, r) == (3, 2):
+        picked + a * 100 + b * 10 + c + d
+    else:
+        0
+"#,
+        );
+        assert_eq!(value, Value::Int(4128));
+    }
+
+    #[test]
+    fn runs_string_concatenation_and_interpolation() {
+        let value = compile_and_run(
+            r#"
+fn main():
+    let name = "w\u{f6}rld"
+    let mut greeting = "hi\t" + name
+    greeting += "!"
+    let n = 3
+    let line = f"{greeting} n={n + 1} {{ok}} {[n, 2]}"
+    if line == "hi\twörld! n=4 {ok} [3, 2]" && r"\n" != "\n":
+        f"{line}|" + r"\d"
+    else:
+        ""
+"#,
+        );
+        assert_eq!(
+            value,
+            Value::Str(Arc::from("hi\twörld! n=4 {ok} [3, 2]|\\d"))
+        );
+    }
+
+    #[test]
+    fn runs_hex_octal_and_binary_literals() {
+        let value = compile_and_run("fn main():\n    0xff + 0o17 + 0b1010_0101 + 1_000 + -0x10\n");
+        assert_eq!(value, Value::Int(255 + 15 + 165 + 1000 - 16));
+        let min = compile_and_run("fn main():\n    -9223372036854775808\n");
+        assert_eq!(min, Value::Int(i64::MIN));
+    }
+
+    #[test]
+    fn runs_type_annotated_functions() {
+        let value = compile_and_run(
+            r#"
+struct Point:
+    x: Int
+    y: Int
+
+impl Point:
+    fn scale(self, k: Int) -> Point:
+        Point { x: self.x * k, y: self.y * k }
+
+fn add(a: Int, b: Int) -> Int:
+    a + b
+
+fn apply(f: fn(Int) -> Int, x: Int) -> Int:
+    f(x)
+
+fn first(pair: (Int, String)) -> Int:
+    let (n, _) = pair
+    return n
+
+fn main() -> Int:
+    let xs: List[Int] = [1, 2, 3]
+    let total: Int = add(xs[0], apply(|v: Int| v * 10, 4))
+    let p = Point { x: 1, y: 2 }.scale(3)
+    total + first((p.y, "six"))
+"#,
+        );
+        assert_eq!(value, Value::Int(47));
+    }
+
+    #[test]
+    fn runs_float_arithmetic_and_conversions() {
+        let value = compile_and_run(
+            r#"
+fn main():
+    let mut total = 0.5
+    for i in 0..3:
+        total += float(i) * 1.5
+    let scaled = int(total * 10.0) + int(2e3) + int("7")
+    if total > 4.0 && -1.25e-2 < 0.0 && 7.0 / 2.0 == 3.5:
+        (scaled, total, 7.5 % 2.0)
+    else:
+        (0, 0.0, 0.0)
+"#,
+        );
+        let Value::Tuple(items) = value else {
+            panic!("expected tuple, got {value:?}");
+        };
+        assert_eq!(
+            &items[..],
+            &[Value::Int(2057), Value::Float(5.0), Value::Float(1.5)]
+        );
+    }
+
+    #[test]
+    fn runs_polymorphic_functions_with_inferred_types() {
+        let value = compile_and_run(
+            r#"
+fn main():
+    let (n, s) = pair(id(4), id("ab"))
+    let inc = |v| v + 1
+    twice(inc, n) * 10 + len(s)
+
+fn id(x):
+    x
+
+fn pair(a, b):
+    (a, b)
+
+fn twice(f, x):
+    f(f(x))
+"#,
+        );
+        assert_eq!(value, Value::Int(62));
+
+        let parsed = parse_str(
+            "test.ktn",
+            "fn f(x):\n    x + \"a\"\n\nfn main():\n    f(1)\n",
+        );
+        let analysis = analyze(&parsed.module, &[], &[]);
+        let messages: Vec<&str> = analysis
+            .diagnostics
+            .iter()
+            .map(|diag| diag.message.as_str())
+            .collect();
+        assert_eq!(messages, ["mismatched argument type"]);
+    }
+
+    #[test]
+    fn reports_unresolved_names() {
+        let source = "fn main():\n    let total = 1\n    prnt(totl)\n    missing = 2\n";
+        let parsed = parse_str("test.ktn", source);
+        let analysis = analyze(&parsed.module, &["print".to_string()], &[]);
+        let rendered: Vec<(&str, Vec<&str>)> = analysis
+            .diagnostics
+            .iter()
+            .map(|diag| {
+                let notes = diag.notes.iter().map(|note| note.as_str()).collect();
+                (diag.message.as_str(), notes)
+            })
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                (
+                    "cannot find value `prnt` in this scope",
+                    vec!["a similar name exists: `print`"]
+                ),
+                (
+                    "cannot find value `totl` in this scope",
+                    vec!["a similar name exists: `total`"]
+                ),
+                ("cannot find value `missing` in this scope", vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_generic_functions_and_structs() {
+        let value = compile_and_run(
+            r#"
+struct Pair[A, B]:
+    left: A
+    right: B
+
+impl Pair:
+    fn swap(self) -> Pair[B, A]:
+        Pair { left: self.right, right: self.left }
+
+fn first[T](xs: List[T]) -> T:
+    xs[0]
+
+fn main():
+    let p: Pair[String, Int] = Pair { left: 1, right: "one" }.swap()
+    first([40, 2]) + p.right + len(p.left)
+"#,
+        );
+        assert_eq!(value, Value::Int(44));
+
+        let source = "struct Box[T]:\n    value: T\n\nfn bad[T](x: T) -> T:\n    x + 1\n\nfn main():\n    let b: Box[String] = Box { value: 2 }\n    b\n";
+        let parsed = parse_str("test.ktn", source);
+        let analysis = analyze(&parsed.module, &[], &[]);
+        let rendered: Vec<(&str, Vec<&str>)> = analysis
+            .diagnostics
+            .iter()
+            .map(|diag| {
+                let notes = diag.notes.iter().map(|note| note.as_str()).collect();
+                (diag.message.as_str(), notes)
+            })
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                (
+                    "left operand has wrong type",
+                    vec!["expected `Int` or `Float`, found `T`"]
+                ),
+                (
+                    "mismatched types in `let`",
+                    vec!["expected `Box[String]`, found `Box[Int]`"]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_globals_with_computed_initializers() {
+        let value = compile_and_run(
+            r#"
+fn main():
+    bump()
+    bump()
+    (counter, total(), scale(2), a + b)
+
+let SIZES = [1, 2, 3]
+let mut counter = 0
+let (a, b) = (10, 20)
+let FACTOR = len(SIZES) + 4
+let scale = |x| x * FACTOR
+
+fn bump():
+    counter += 1
+
+fn total():
+    let mut sum = 0
+    for i in 0..len(SIZES):
+        sum += SIZES[i]
+    sum
+"#,
+        );
+        assert_eq!(
+            value,
+            Value::Tuple(Rc::from(vec![
+                Value::Int(2),
+                Value::Int(6),
+                Value::Int(14),
+                Value::Int(30),
+            ]))
+        );
+    }
+
+    #[test]
+    fn reports_arithmetic_errors() {
+        let div = try_compile_and_run("fn main():\n    let zero = 0\n    10 / zero\n");
+        assert!(matches!(div, Err(VmError::DivisionByZero)));
+        let modulo = try_compile_and_run("fn main():\n    10 % 0\n");
+        assert!(matches!(modulo, Err(VmError::DivisionByZero)));
+        let shift = try_compile_and_run("fn main():\n    1 << 64\n");
+        assert!(matches!(shift, Err(VmError::ShiftOverflow(64))));
+        let negative = try_compile_and_run("fn main():\n    1 >> -1\n");
+        assert!(matches!(negative, Err(VmError::ShiftOverflow(-1))));
+        for source in [
+            "fn main():\n    9223372036854775807 + 1\n",
+            "fn main():\n    -9223372036854775807 - 2\n",
+            "fn main():\n    4611686018427387904 * 2\n",
+            "fn main():\n    -(-9223372036854775808)\n",
+            "fn main():\n    let min = -9223372036854775808\n    min / -1\n",
+        ] {
+            let result = try_compile_and_run(source);
+            assert!(
+                matches!(result, Err(VmError::IntegerOverflow)),
+                "{source}: {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn reports_source_location_of_failing_instruction() {
+        let host = stdlib_host();
+        let module = compile(
+            "fn divide(a: Int, b: Int) -> Int:\n    a / b\n\nfn main():\n    divide(1, 0)\n",
+            &host,
+        );
+        let error = run_module(&module, "main", &host).expect_err("division by zero");
+        assert_eq!(error.to_string(), "division by zero (test.ktn:2:5)");
+        assert!(matches!(error.into_inner(), VmError::DivisionByZero));
+
+        let divide = &module.functions[module.function_index("divide").unwrap() as usize];
+        let names: Vec<_> = divide
+            .debug
+            .locals
+            .iter()
+            .map(|l| l.name.as_str())
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+    }
+
     #[test]
     fn calls_host_extension() {
         let value = compile_and_run(